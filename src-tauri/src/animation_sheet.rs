@@ -0,0 +1,328 @@
+use crate::image_pipeline::decode_limits;
+use image::codecs::{gif::GifDecoder, webp::WebPDecoder};
+use image::{AnimationDecoder, Frames, ImageDecoder, ImageFormat, Rgba, RgbaImage};
+use std::io::Cursor;
+
+/// Frames shown on the sheet (3x3 grid).
+const KEYFRAMES: usize = 9;
+/// Frames decoded at most; longer animations are summarized from their beginning.
+const MAX_FRAMES: usize = 300;
+/// Canvas size limit for animations (~16MP, 64MB per decoded frame).
+const MAX_CANVAS_PIXELS: u64 = 16_000_000;
+/// Pixels composited per pass; large canvases get fewer frames than `MAX_FRAMES`.
+const MAX_PASS_PIXELS: u64 = 600_000_000;
+/// Side of the grayscale thumbnails used to measure motion between frames.
+const DIFF_SIDE: u32 = 32;
+
+const MAX_SHEET_SIDE: u32 = 2400;
+const GAP: u32 = 4;
+const LABEL_SCALE: u32 = 3;
+const LABEL_PAD: u32 = 4;
+const BACKGROUND: Rgba<u8> = Rgba([24, 24, 24, 255]);
+const LABEL_COLOR: Rgba<u8> = Rgba([240, 240, 240, 255]);
+
+pub struct ContactSheet {
+    pub image: RgbaImage,
+    pub total_frames: u32,
+    pub keyframes: u32,
+}
+
+// ---------- Decoding ----------
+
+/// Apply the pipeline's decode limits and work out how many frames to take.
+fn prepare<'a>(decoder: &mut impl ImageDecoder<'a>) -> Result<usize, String> {
+    let (width, height) = decoder.dimensions();
+    let canvas = width as u64 * height as u64;
+    if canvas > MAX_CANVAS_PIXELS {
+        return Err("Animation is too large to attach".to_string());
+    }
+    decoder
+        .set_limits(decode_limits())
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    Ok((MAX_PASS_PIXELS / canvas.max(1)).clamp(1, MAX_FRAMES as u64) as usize)
+}
+
+/// Frame iterator and how many frames to take from it.
+fn frames(bytes: &[u8], format: ImageFormat) -> Result<Option<(Frames<'_>, usize)>, String> {
+    match format {
+        ImageFormat::Gif => {
+            let mut decoder = GifDecoder::new(Cursor::new(bytes))
+                .map_err(|e| format!("Failed to decode image: {}", e))?;
+            let limit = prepare(&mut decoder)?;
+            Ok(Some((decoder.into_frames(), limit)))
+        }
+        ImageFormat::WebP => {
+            let mut decoder = WebPDecoder::new(Cursor::new(bytes))
+                .map_err(|e| format!("Failed to decode image: {}", e))?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            let limit = prepare(&mut decoder)?;
+            Ok(Some((decoder.into_frames(), limit)))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn luma_thumbnail(frame: &RgbaImage) -> Vec<u8> {
+    image::imageops::thumbnail(frame, DIFF_SIDE, DIFF_SIDE)
+        .pixels()
+        .map(|p| ((p[0] as u32 * 77 + p[1] as u32 * 150 + p[2] as u32 * 29) >> 8) as u8)
+        .collect()
+}
+
+fn mean_difference(a: &[u8], b: &[u8]) -> f64 {
+    let total: u64 = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| x.abs_diff(*y) as u64)
+        .sum();
+    total as f64 / a.len().max(1) as f64
+}
+
+// ---------- Keyframe selection ----------
+
+/// Spread keyframes evenly over the accumulated change, so fast motion gets more frames
+/// than still stretches. The first and last frames are always included; a still
+/// animation falls back to even spacing.
+fn select_keyframes(differences: &[f64], count: usize) -> Vec<usize> {
+    let frames = differences.len();
+    if frames <= count {
+        return (0..frames).collect();
+    }
+
+    let total: f64 = differences.iter().sum();
+    if total <= f64::EPSILON {
+        return (0..count)
+            .map(|k| k * (frames - 1) / (count - 1))
+            .collect();
+    }
+
+    let mut picked = vec![0];
+    let mut accumulated = 0.0;
+    let mut next_target = 1;
+    for (index, diff) in differences.iter().enumerate().skip(1) {
+        accumulated += diff;
+        while next_target < count - 1
+            && accumulated >= total * next_target as f64 / (count - 1) as f64
+        {
+            if picked.last() != Some(&index) {
+                picked.push(index);
+            }
+            next_target += 1;
+        }
+    }
+    if picked.last() != Some(&(frames - 1)) {
+        picked.push(frames - 1);
+    }
+    picked
+}
+
+// ---------- Labels ----------
+
+/// 3x5 bitmap glyphs for the frame labels ("#12 1.40s").
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        's' => [0b000, 0b011, 0b100, 0b001, 0b110],
+        _ => [0; 5],
+    }
+}
+
+fn draw_label(sheet: &mut RgbaImage, x: u32, y: u32, max_width: u32, text: &str) {
+    let advance = 4 * LABEL_SCALE;
+    for (i, c) in text.chars().enumerate() {
+        let left = x + i as u32 * advance;
+        if left + 3 * LABEL_SCALE > x + max_width {
+            break;
+        }
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..LABEL_SCALE {
+                    for dx in 0..LABEL_SCALE {
+                        let px = left + col * LABEL_SCALE + dx;
+                        let py = y + row as u32 * LABEL_SCALE + dy;
+                        if px < sheet.width() && py < sheet.height() {
+                            sheet.put_pixel(px, py, LABEL_COLOR);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// ---------- Sheet ----------
+
+fn compose(keyframes: Vec<(usize, u64, RgbaImage)>) -> RgbaImage {
+    let count = keyframes.len() as u32;
+    let cols = (count as f64).sqrt().ceil() as u32;
+    let rows = count.div_ceil(cols);
+    let (frame_w, frame_h) = keyframes[0].2.dimensions();
+    let label_h = 5 * LABEL_SCALE + 2 * LABEL_PAD;
+
+    // Largest cell that keeps the whole sheet within MAX_SHEET_SIDE on both axes
+    let fit_w = (MAX_SHEET_SIDE - (cols + 1) * GAP) as f64 / cols as f64;
+    let fit_h = (MAX_SHEET_SIDE - (rows + 1) * GAP - rows * label_h) as f64 / rows as f64;
+    let scale = (fit_w / frame_w as f64)
+        .min(fit_h / frame_h as f64)
+        .min(1.0);
+    let cell_w = ((frame_w as f64 * scale).round() as u32).max(1);
+    let cell_h = ((frame_h as f64 * scale).round() as u32).max(1);
+
+    let mut sheet = RgbaImage::from_pixel(
+        cols * cell_w + (cols + 1) * GAP,
+        rows * (cell_h + label_h) + (rows + 1) * GAP,
+        BACKGROUND,
+    );
+    for (slot, (index, timestamp_ms, frame)) in keyframes.into_iter().enumerate() {
+        let slot = slot as u32;
+        let x = GAP + (slot % cols) * (cell_w + GAP);
+        let y = GAP + (slot / cols) * (cell_h + label_h + GAP);
+        let label = format!("#{} {:.2}s", index + 1, timestamp_ms as f64 / 1000.0);
+        draw_label(&mut sheet, x + LABEL_PAD, y + LABEL_PAD, cell_w, &label);
+
+        let cell = if (cell_w, cell_h) == frame.dimensions() {
+            frame
+        } else {
+            image::imageops::resize(&frame, cell_w, cell_h, image::imageops::FilterType::Triangle)
+        };
+        image::imageops::overlay(&mut sheet, &cell, x as i64, (y + label_h) as i64);
+    }
+    sheet
+}
+
+/// Grid of labelled keyframes for an animated GIF or WebP; `None` for still images.
+pub fn contact_sheet(bytes: &[u8], format: ImageFormat) -> Result<Option<ContactSheet>, String> {
+    // First pass: timestamps and a tiny thumbnail per frame, so memory stays flat
+    let Some((first_pass, limit)) = frames(bytes, format)? else {
+        return Ok(None);
+    };
+    let mut timestamps: Vec<u64> = Vec::new();
+    let mut differences: Vec<f64> = Vec::new();
+    let mut previous: Option<Vec<u8>> = None;
+    let mut elapsed_ms: u64 = 0;
+    for frame in first_pass.take(limit) {
+        let Ok(frame) = frame else {
+            break;
+        };
+        let thumb = luma_thumbnail(frame.buffer());
+        differences.push(previous.as_ref().map_or(0.0, |p| mean_difference(p, &thumb)));
+        timestamps.push(elapsed_ms);
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        elapsed_ms += numer as u64 / denom.max(1) as u64;
+        previous = Some(thumb);
+    }
+    if timestamps.len() <= 1 {
+        return Ok(None);
+    }
+
+    // Second pass: keep only the chosen frames at full size
+    let selected = select_keyframes(&differences, KEYFRAMES);
+    let last = *selected.last().unwrap_or(&0);
+    let Some((second_pass, _)) = frames(bytes, format)? else {
+        return Ok(None);
+    };
+    let mut keyframes = Vec::with_capacity(selected.len());
+    for (index, frame) in second_pass.take(last + 1).enumerate() {
+        let frame = frame.map_err(|e| format!("Failed to decode image: {}", e))?;
+        if selected.contains(&index) {
+            keyframes.push((index, timestamps[index], frame.into_buffer()));
+        }
+    }
+    if keyframes.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(ContactSheet {
+        keyframes: keyframes.len() as u32,
+        total_frames: timestamps.len() as u32,
+        image: compose(keyframes),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, Frame};
+
+    fn solid(width: u32, height: u32, shade: u8) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba([shade, 255 - shade, shade / 2, 255]))
+    }
+
+    #[test]
+    fn keyframes_follow_the_biggest_changes_in_order() {
+        let mut differences = vec![0.0; 50];
+        differences[10] = 40.0;
+        differences[20] = 40.0;
+        differences[30] = 40.0;
+        differences[31] = 0.5;
+        assert_eq!(select_keyframes(&differences, 5), vec![0, 10, 20, 30, 49]);
+
+        // Fewer frames than slots keeps them all; a still animation is spaced evenly
+        assert_eq!(select_keyframes(&[0.0, 3.0, 1.0], 9), vec![0, 1, 2]);
+        assert_eq!(select_keyframes(&[0.0; 9], 3), vec![0, 4, 8]);
+
+        let picked = select_keyframes(&(0..40).map(|i| (i % 7) as f64).collect::<Vec<_>>(), 9);
+        assert_eq!(picked[0], 0);
+        assert!(picked.len() <= 9);
+        assert!(picked.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn sheet_is_a_labelled_grid() {
+        let keyframes = (0..4)
+            .map(|i| (i * 3, i as u64 * 250, solid(60, 40, i as u8 * 60)))
+            .collect();
+        let sheet = compose(keyframes);
+
+        let label_h = 5 * LABEL_SCALE + 2 * LABEL_PAD;
+        assert_eq!(sheet.dimensions(), (2 * 60 + 3 * GAP, 2 * (40 + label_h) + 3 * GAP));
+
+        // Each label starts with '#', whose top-left pixel is set; the frame sits below it
+        for slot in 0..4u32 {
+            let x = GAP + (slot % 2) * (60 + GAP);
+            let y = GAP + (slot / 2) * (40 + label_h + GAP);
+            assert_eq!(*sheet.get_pixel(x + LABEL_PAD, y + LABEL_PAD), LABEL_COLOR);
+            assert_eq!(*sheet.get_pixel(x, y), BACKGROUND);
+            let shade = solid(1, 1, slot as u8 * 60);
+            assert_eq!(sheet.get_pixel(x + 30, y + label_h + 20), shade.get_pixel(0, 0));
+        }
+    }
+
+    #[test]
+    fn animated_gifs_become_a_contact_sheet() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            let frames = (0..12u8).map(|i| {
+                Frame::from_parts(solid(24, 16, i * 20), 0, 0, Delay::from_numer_denom_ms(100, 1))
+            });
+            encoder.encode_frames(frames).unwrap();
+        }
+
+        let normalized = crate::image_pipeline::normalize_bytes(&bytes).unwrap();
+        assert_eq!(normalized.info.animation_frames, Some(12));
+        assert_eq!(normalized.info.keyframes, Some(9));
+        assert!(normalized.info.reencoded);
+        assert!(normalized.info.width > 24);
+
+        let sheet = contact_sheet(&bytes, ImageFormat::Gif).unwrap().unwrap();
+        assert_eq!(sheet.image.dimensions(), (normalized.info.width, normalized.info.height));
+    }
+}
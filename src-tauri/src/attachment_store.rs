@@ -0,0 +1,489 @@
+use crate::history::HistoryStore;
+use crate::history_crypto;
+use crate::image_pipeline;
+use chacha20poly1305::XChaCha20Poly1305;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+const ATTACHMENTS_DIR: &str = "attachments";
+const THUMBNAIL_MAX_SIDE: u32 = 256;
+const THUMBNAIL_QUALITY: u8 = 75;
+pub(crate) const DEFAULT_ATTACHMENT_CAP_BYTES: u64 = 512 * 1024 * 1024; // 512MB
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredAttachment {
+    pub hash: String,
+    pub mime_type: String,
+    pub size_bytes: usize,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct AttachmentData {
+    pub base64: String,
+    pub mime_type: String,
+}
+
+// ---------- File layout ----------
+
+/// Content-addressed files under `<app data>/attachments`:
+/// `objects/ab/<sha256>` for originals and `thumbs/ab/<sha256>.jpg` for previews.
+/// While history encryption is unlocked, files are sealed with the history file key.
+pub struct AttachmentStore {
+    root: PathBuf,
+    cap_bytes: u64,
+    cipher: RwLock<Option<XChaCha20Poly1305>>,
+}
+
+fn hash_bytes(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create attachment folder: {}", e))?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).map_err(|e| format!("Failed to save attachment: {}", e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to save attachment: {}", e))
+}
+
+fn make_thumbnail(bytes: &[u8]) -> Option<(Vec<u8>, u32, u32)> {
+    let img = image_pipeline::decode(bytes).ok()?;
+    let (width, height) = (img.width(), img.height());
+    let thumb = if width > THUMBNAIL_MAX_SIDE || height > THUMBNAIL_MAX_SIDE {
+        img.thumbnail(THUMBNAIL_MAX_SIDE, THUMBNAIL_MAX_SIDE)
+    } else {
+        img
+    };
+
+    let mut buf = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, THUMBNAIL_QUALITY)
+        .encode_image(&image::DynamicImage::ImageRgb8(thumb.to_rgb8()))
+        .ok()?;
+    Some((buf, width, height))
+}
+
+fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+impl AttachmentStore {
+    pub fn new(data_dir: &Path, cap_bytes: u64) -> Self {
+        Self {
+            root: data_dir.join(ATTACHMENTS_DIR),
+            cap_bytes,
+            cipher: RwLock::new(None),
+        }
+    }
+
+    pub fn set_cipher(&self, cipher: Option<XChaCha20Poly1305>) {
+        if let Ok(mut slot) = self.cipher.write() {
+            *slot = cipher;
+        }
+    }
+
+    fn write_file(&self, path: &Path, bytes: &[u8]) -> Result<(), String> {
+        let cipher = self
+            .cipher
+            .read()
+            .map_err(|_| "Attachment store is unavailable".to_string())?;
+        match cipher.as_ref() {
+            Some(cipher) => write_atomic(path, &history_crypto::seal(cipher, bytes)?),
+            None => write_atomic(path, bytes),
+        }
+    }
+
+    fn read_file(&self, path: &Path, missing: &str) -> Result<Vec<u8>, String> {
+        let bytes = fs::read(path).map_err(|_| missing.to_string())?;
+        let cipher = self
+            .cipher
+            .read()
+            .map_err(|_| "Attachment store is unavailable".to_string())?;
+        history_crypto::open(cipher.as_ref(), bytes)
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.root.join("objects").join(&hash[..2]).join(hash)
+    }
+
+    fn thumbnail_path(&self, hash: &str) -> PathBuf {
+        self.root
+            .join("thumbs")
+            .join(&hash[..2])
+            .join(format!("{}.jpg", hash))
+    }
+
+    /// Write the file (if new) and register it. Re-adding known bytes only refreshes its LRU stamp.
+    pub fn put(
+        &self,
+        conn: &Connection,
+        bytes: &[u8],
+        mime_type: &str,
+    ) -> Result<StoredAttachment, String> {
+        let hash = hash_bytes(bytes);
+        let now = now_ms();
+
+        let known: Option<(Option<u32>, Option<u32>, bool)> = conn
+            .query_row(
+                "SELECT width, height, evicted FROM blobs WHERE hash = ?1",
+                params![hash],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? != 0)),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read attachment index: {}", e))?;
+
+        let object = self.object_path(&hash);
+        let (width, height) = match known {
+            Some((width, height, evicted)) if !evicted && object.exists() => {
+                conn.execute(
+                    "UPDATE blobs SET last_access = ?2 WHERE hash = ?1",
+                    params![hash, now],
+                )
+                .map_err(|e| format!("Failed to update attachment index: {}", e))?;
+                (width, height)
+            }
+            _ => {
+                self.write_file(&object, bytes)?;
+                let (width, height) = match make_thumbnail(bytes) {
+                    Some((thumb, w, h)) => {
+                        self.write_file(&self.thumbnail_path(&hash), &thumb)?;
+                        (Some(w), Some(h))
+                    }
+                    None => (None, None),
+                };
+                conn.execute(
+                    "INSERT INTO blobs (hash, mime_type, size_bytes, width, height, created_at, last_access, evicted)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, 0)
+                     ON CONFLICT(hash) DO UPDATE SET
+                        mime_type = excluded.mime_type, size_bytes = excluded.size_bytes, width = excluded.width,
+                        height = excluded.height, last_access = excluded.last_access, evicted = 0",
+                    params![hash, mime_type, bytes.len() as i64, width, height, now],
+                )
+                .map_err(|e| format!("Failed to update attachment index: {}", e))?;
+                (width, height)
+            }
+        };
+
+        Ok(StoredAttachment {
+            hash,
+            mime_type: mime_type.to_string(),
+            size_bytes: bytes.len(),
+            width,
+            height,
+        })
+    }
+
+    fn lookup_mime(&self, conn: &Connection, hash: &str) -> Result<String, String> {
+        if !is_valid_hash(hash) {
+            return Err("Invalid attachment id".to_string());
+        }
+        conn.query_row(
+            "SELECT mime_type FROM blobs WHERE hash = ?1",
+            params![hash],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read attachment index: {}", e))?
+        .ok_or_else(|| "Attachment not found".to_string())
+    }
+
+    pub fn size_of(&self, conn: &Connection, hash: &str) -> Result<usize, String> {
+        self.lookup_mime(conn, hash)?;
+        conn.query_row(
+            "SELECT size_bytes FROM blobs WHERE hash = ?1",
+            params![hash],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|size| size.max(0) as usize)
+        .map_err(|e| format!("Failed to read attachment index: {}", e))
+    }
+
+    fn touch(&self, conn: &Connection, hash: &str) {
+        let _ = conn.execute(
+            "UPDATE blobs SET last_access = ?2 WHERE hash = ?1",
+            params![hash, now_ms()],
+        );
+    }
+
+    pub fn read_original(&self, conn: &Connection, hash: &str) -> Result<(Vec<u8>, String), String> {
+        let mime_type = self.lookup_mime(conn, hash)?;
+        let bytes = self.read_file(&self.object_path(hash), "Attachment was evicted from storage")?;
+        self.touch(conn, hash);
+        Ok((bytes, mime_type))
+    }
+
+    pub fn read_thumbnail(&self, conn: &Connection, hash: &str) -> Result<Vec<u8>, String> {
+        self.lookup_mime(conn, hash)?;
+        self.read_file(&self.thumbnail_path(hash), "No thumbnail for attachment")
+    }
+
+    fn remove_files(&self, hash: &str, keep_thumbnail: bool) {
+        let _ = fs::remove_file(self.object_path(hash));
+        if !keep_thumbnail {
+            let _ = fs::remove_file(self.thumbnail_path(hash));
+        }
+    }
+
+    /// Size-capped LRU: drop least recently used blobs until stored originals fit the cap.
+    /// Unreferenced blobs go first and disappear entirely; blobs still used by history lose
+    /// their original file but keep the thumbnail.
+    pub fn evict_over_cap(&self, conn: &Connection) -> Result<usize, String> {
+        let mut total: i64 = conn
+            .query_row(
+                "SELECT COALESCE(SUM(size_bytes), 0) FROM blobs WHERE evicted = 0",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to read attachment index: {}", e))?;
+        if total as u64 <= self.cap_bytes {
+            return Ok(0);
+        }
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT b.hash, b.size_bytes,
+                        (SELECT COUNT(*) FROM entry_blobs eb WHERE eb.hash = b.hash) AS refs
+                 FROM blobs b WHERE b.evicted = 0
+                 ORDER BY refs > 0, b.last_access",
+            )
+            .map_err(|e| format!("Failed to read attachment index: {}", e))?;
+        let candidates = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read attachment index: {}", e))?;
+
+        let mut evicted = 0usize;
+        for (hash, size, refs) in candidates {
+            if total as u64 <= self.cap_bytes {
+                break;
+            }
+            if refs > 0 {
+                conn.execute("UPDATE blobs SET evicted = 1 WHERE hash = ?1", params![hash])
+            } else {
+                conn.execute("DELETE FROM blobs WHERE hash = ?1", params![hash])
+            }
+            .map_err(|e| format!("Failed to update attachment index: {}", e))?;
+            self.remove_files(&hash, refs > 0);
+            total -= size;
+            evicted += 1;
+        }
+
+        if evicted > 0 {
+            eprintln!("[attachments] Evicted {} blobs to stay under cap", evicted);
+        }
+        Ok(evicted)
+    }
+
+    /// Delete blobs no history entry points at and that nobody read since `before_ms`.
+    /// The grace period keeps fresh captures that are still waiting to be sent.
+    pub fn remove_unreferenced(&self, conn: &Connection, before_ms: i64) -> Result<usize, String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT hash FROM blobs b WHERE b.last_access < ?1
+                 AND NOT EXISTS (SELECT 1 FROM entry_blobs eb WHERE eb.hash = b.hash)",
+            )
+            .map_err(|e| format!("Failed to read attachment index: {}", e))?;
+        let hashes = stmt
+            .query_map(params![before_ms], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read attachment index: {}", e))?;
+
+        for hash in &hashes {
+            conn.execute("DELETE FROM blobs WHERE hash = ?1", params![hash])
+                .map_err(|e| format!("Failed to update attachment index: {}", e))?;
+            self.remove_files(hash, false);
+        }
+        Ok(hashes.len())
+    }
+
+    /// Re-write plaintext originals and thumbnails with the current cipher.
+    pub fn seal_existing(&self) -> Result<usize, String> {
+        let mut sealed = 0usize;
+        for kind in ["objects", "thumbs"] {
+            let Ok(shards) = fs::read_dir(self.root.join(kind)) else {
+                continue;
+            };
+            for shard in shards.flatten() {
+                let Ok(files) = fs::read_dir(shard.path()) else {
+                    continue;
+                };
+                for file in files.flatten() {
+                    let path = file.path();
+                    let Ok(bytes) = fs::read(&path) else {
+                        continue;
+                    };
+                    if history_crypto::is_sealed(&bytes) {
+                        continue;
+                    }
+                    self.write_file(&path, &bytes)?;
+                    sealed += 1;
+                }
+            }
+        }
+        Ok(sealed)
+    }
+
+    /// Remove every stored original and thumbnail.
+    pub fn wipe(&self) -> Result<(), String> {
+        match fs::remove_dir_all(&self.root) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to delete attachments: {}", e)),
+        }
+    }
+}
+
+// ---------- Tauri commands ----------
+
+#[tauri::command]
+pub async fn attachment_original(
+    store: tauri::State<'_, HistoryStore>,
+    hash: String,
+) -> Result<AttachmentData, String> {
+    let (bytes, mime_type) = store.attachment_original(&hash)?;
+    use base64::Engine;
+    Ok(AttachmentData {
+        base64: base64::engine::general_purpose::STANDARD.encode(&bytes),
+        mime_type,
+    })
+}
+
+#[tauri::command]
+pub async fn attachment_thumbnail(
+    store: tauri::State<'_, HistoryStore>,
+    hash: String,
+) -> Result<AttachmentData, String> {
+    let bytes = store.attachment_thumbnail(&hash)?;
+    use base64::Engine;
+    Ok(AttachmentData {
+        base64: base64::engine::general_purpose::STANDARD.encode(&bytes),
+        mime_type: "image/jpeg".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::memory_db;
+
+    fn png(seed: u8) -> Vec<u8> {
+        let image = image::RgbaImage::from_pixel(300, 200, image::Rgba([seed, 10, 20, 255]));
+        let mut bytes = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clawpet-attachments-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn put_at(store: &AttachmentStore, conn: &Connection, bytes: &[u8], last_access: i64) -> StoredAttachment {
+        let stored = store.put(conn, bytes, "image/png").unwrap();
+        conn.execute(
+            "UPDATE blobs SET last_access = ?2 WHERE hash = ?1",
+            params![stored.hash, last_access],
+        )
+        .unwrap();
+        stored
+    }
+
+    fn blob_state(conn: &Connection, hash: &str) -> Option<bool> {
+        conn.query_row("SELECT evicted FROM blobs WHERE hash = ?1", params![hash], |row| {
+            row.get::<_, i64>(0).map(|evicted| evicted != 0)
+        })
+        .optional()
+        .unwrap()
+    }
+
+    #[test]
+    fn eviction_drops_unreferenced_blobs_before_referenced_ones() {
+        let dir = temp_dir("evict");
+        let conn = memory_db();
+        let (a, b, c) = (png(1), png(2), png(3));
+        let cap = (b.len() + c.len()) as u64;
+        let store = AttachmentStore::new(&dir, cap);
+
+        // `referenced` is the least recently used, but history still points at it
+        let referenced = put_at(&store, &conn, &a, 100);
+        let old = put_at(&store, &conn, &b, 200);
+        let recent = put_at(&store, &conn, &c, 300);
+        conn.execute(
+            "INSERT INTO entries (seq, id, role, text, timestamp) VALUES (1, 'e', 'user', 'hi', 1)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO entry_blobs (entry_seq, idx, hash) VALUES (1, 0, ?1)",
+            params![referenced.hash],
+        )
+        .unwrap();
+
+        assert_eq!(store.evict_over_cap(&conn).unwrap(), 1);
+        assert_eq!(blob_state(&conn, &old.hash), None);
+        assert!(!store.thumbnail_path(&old.hash).exists());
+        assert_eq!(blob_state(&conn, &referenced.hash), Some(false));
+        assert_eq!(blob_state(&conn, &recent.hash), Some(false));
+
+        // With no room at all, the referenced blob loses its original but keeps the thumbnail
+        let tight = AttachmentStore::new(&dir, 0);
+        assert_eq!(tight.evict_over_cap(&conn).unwrap(), 2);
+        assert_eq!(blob_state(&conn, &recent.hash), None);
+        assert_eq!(blob_state(&conn, &referenced.hash), Some(true));
+        assert!(tight.read_original(&conn, &referenced.hash).is_err());
+        assert!(tight.read_thumbnail(&conn, &referenced.hash).is_ok());
+
+        // Re-adding the evicted bytes restores the original and takes the new MIME type
+        tight.put(&conn, &a, "image/x-png").unwrap();
+        assert_eq!(blob_state(&conn, &referenced.hash), Some(false));
+        assert_eq!(tight.read_original(&conn, &referenced.hash).unwrap(), (a, "image/x-png".to_string()));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn sealing_rewrites_plaintext_files_once() {
+        let dir = temp_dir("seal");
+        let conn = memory_db();
+        let store = AttachmentStore::new(&dir, DEFAULT_ATTACHMENT_CAP_BYTES);
+        let stored = store.put(&conn, &png(4), "image/png").unwrap();
+        assert!(!history_crypto::is_sealed(&fs::read(store.object_path(&stored.hash)).unwrap()));
+
+        let (_, keys) = history_crypto::create_keys("correct horse battery").unwrap();
+        store.set_cipher(Some(keys.file_cipher.clone()));
+        // Original and thumbnail
+        assert_eq!(store.seal_existing().unwrap(), 2);
+        assert_eq!(store.seal_existing().unwrap(), 0);
+        assert!(history_crypto::is_sealed(&fs::read(store.object_path(&stored.hash)).unwrap()));
+        assert!(history_crypto::is_sealed(&fs::read(store.thumbnail_path(&stored.hash)).unwrap()));
+        assert_eq!(store.read_original(&conn, &stored.hash).unwrap().0, png(4));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
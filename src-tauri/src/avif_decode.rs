@@ -0,0 +1,190 @@
+use std::io::Cursor;
+
+/// Decoded pixel cap, the same ~120MP budget the image pipeline gives other formats.
+const MAX_FRAME_PIXELS: u32 = 120_000_000;
+
+/// One decoded AV1 image, samples widened to u16 so 8-bit and 10/12-bit share a path.
+struct Planes {
+    width: u32,
+    height: u32,
+    bit_depth: u32,
+    /// Chroma subsampling as right shifts on x and y (4:2:0 is `(1, 1)`).
+    chroma_shift: (u32, u32),
+    y: Vec<u16>,
+    /// U and V, `None` for monochrome images.
+    uv: Option<(Vec<u16>, Vec<u16>)>,
+}
+
+impl Planes {
+    fn max_value(&self) -> f32 {
+        ((1u32 << self.bit_depth) - 1) as f32
+    }
+
+    fn chroma_mid(&self) -> f32 {
+        (1u32 << (self.bit_depth - 1)) as f32
+    }
+
+    fn chroma_width(&self) -> u32 {
+        (self.width + (1 << self.chroma_shift.0) - 1) >> self.chroma_shift.0
+    }
+}
+
+// ---------- AV1 ----------
+
+fn samples(
+    picture: &dav1d::Picture,
+    component: dav1d::PlanarImageComponent,
+    width: u32,
+    height: u32,
+) -> Vec<u16> {
+    let plane = picture.plane(component);
+    let stride = picture.stride(component) as usize;
+    let wide = picture.bit_depth() > 8;
+    let mut out = Vec::with_capacity(width as usize * height as usize);
+    for row in 0..height as usize {
+        let line = &plane[row * stride..];
+        for col in 0..width as usize {
+            out.push(if wide {
+                u16::from_ne_bytes([line[col * 2], line[col * 2 + 1]])
+            } else {
+                line[col] as u16
+            });
+        }
+    }
+    out
+}
+
+fn decode_av1(data: &[u8]) -> Result<Planes, String> {
+    let mut settings = dav1d::Settings::new();
+    settings.set_n_threads(1);
+    settings.set_max_frame_delay(1);
+    settings.set_frame_size_limit(MAX_FRAME_PIXELS);
+    let mut decoder = dav1d::Decoder::with_settings(&settings)
+        .map_err(|e| format!("Failed to decode image: {:?}", e))?;
+    decoder
+        .send_data(data.to_vec(), None, None, None)
+        .map_err(|e| format!("Failed to decode image: {:?}", e))?;
+    let picture = decoder
+        .get_picture()
+        .map_err(|e| format!("Failed to decode image: {:?}", e))?;
+
+    let (width, height) = (picture.width(), picture.height());
+    let chroma_shift = match picture.pixel_layout() {
+        dav1d::PixelLayout::I420 => (1, 1),
+        dav1d::PixelLayout::I422 => (1, 0),
+        dav1d::PixelLayout::I444 | dav1d::PixelLayout::I400 => (0, 0),
+    };
+    let uv = (!matches!(picture.pixel_layout(), dav1d::PixelLayout::I400)).then(|| {
+        let chroma_w = (width + (1 << chroma_shift.0) - 1) >> chroma_shift.0;
+        let chroma_h = (height + (1 << chroma_shift.1) - 1) >> chroma_shift.1;
+        (
+            samples(&picture, dav1d::PlanarImageComponent::U, chroma_w, chroma_h),
+            samples(&picture, dav1d::PlanarImageComponent::V, chroma_w, chroma_h),
+        )
+    });
+    Ok(Planes {
+        width,
+        height,
+        bit_depth: picture.bit_depth() as u32,
+        chroma_shift,
+        y: samples(&picture, dav1d::PlanarImageComponent::Y, width, height),
+        uv,
+    })
+}
+
+// ---------- Color ----------
+
+/// Full-range BT.601, which is what libavif and most encoders write for stills.
+fn to_rgba(color: &Planes, alpha: Option<&Planes>, premultiplied: bool) -> image::RgbaImage {
+    let max = color.max_value();
+    let mid = color.chroma_mid();
+    let chroma_w = color.chroma_width() as usize;
+    image::RgbaImage::from_fn(color.width, color.height, |x, y| {
+        let luma = color.y[(y * color.width + x) as usize] as f32 / max;
+        let (r, g, b) = match &color.uv {
+            Some((u, v)) => {
+                let at = (y >> color.chroma_shift.1) as usize * chroma_w
+                    + (x >> color.chroma_shift.0) as usize;
+                let cb = (u[at] as f32 - mid) / max;
+                let cr = (v[at] as f32 - mid) / max;
+                (
+                    luma + 1.402 * cr,
+                    luma - 0.344_136 * cb - 0.714_136 * cr,
+                    luma + 1.772 * cb,
+                )
+            }
+            None => (luma, luma, luma),
+        };
+        let a = alpha.map_or(1.0, |alpha| {
+            alpha.y[(y * alpha.width + x) as usize] as f32 / alpha.max_value()
+        });
+        // Straight alpha everywhere else in the pipeline
+        let unmultiply = if premultiplied && a > 0.0 { 1.0 / a } else { 1.0 };
+        let channel = |value: f32| ((value * unmultiply).clamp(0.0, 1.0) * 255.0).round() as u8;
+        image::Rgba([channel(r), channel(g), channel(b), (a * 255.0).round() as u8])
+    })
+}
+
+/// Decode the primary image of an AVIF file (and its alpha plane) to straight-alpha RGBA.
+pub fn decode(bytes: &[u8]) -> Result<image::RgbaImage, String> {
+    let avif = avif_parse::read_avif(&mut Cursor::new(bytes))
+        .map_err(|e| format!("Failed to decode image: {:?}", e))?;
+    let color = decode_av1(&avif.primary_item)?;
+    let alpha = match &avif.alpha_item {
+        Some(item) => Some(decode_av1(item)?),
+        None => None,
+    };
+    let alpha = alpha.filter(|alpha| (alpha.width, alpha.height) == (color.width, color.height));
+    Ok(to_rgba(&color, alpha.as_ref(), avif.premultiplied_alpha))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn planes(
+        width: u32,
+        height: u32,
+        bit_depth: u32,
+        y: Vec<u16>,
+        uv: Option<(u16, u16)>,
+    ) -> Planes {
+        let chroma = (width.div_ceil(2) * height.div_ceil(2)) as usize;
+        Planes {
+            width,
+            height,
+            bit_depth,
+            chroma_shift: (1, 1),
+            y,
+            uv: uv.map(|(u, v)| (vec![u; chroma], vec![v; chroma])),
+        }
+    }
+
+    #[test]
+    fn yuv_is_converted_to_rgb() {
+        // Neutral chroma is gray; 10-bit samples land on the same 8-bit values
+        let gray = planes(2, 2, 8, vec![0, 128, 255, 255], Some((128, 128)));
+        let image = to_rgba(&gray, None, false);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(0, 1).0, [255, 255, 255, 255]);
+        let deep = planes(2, 2, 10, vec![0, 512, 1023, 1023], Some((512, 512)));
+        assert_eq!(to_rgba(&deep, None, false), image);
+
+        // Strong Cr is red, strong Cb is blue; one chroma sample covers a 2x2 block
+        let red = to_rgba(&planes(2, 2, 8, vec![76; 4], Some((85, 255))), None, false);
+        assert!(red.pixels().all(|p| p[0] > 240 && p[1] < 10 && p[2] < 10));
+        let blue = to_rgba(&planes(2, 2, 8, vec![29; 4], Some((255, 107))), None, false);
+        assert!(blue.pixels().all(|p| p[2] > 240 && p[0] < 10 && p[1] < 10));
+
+        let mono = planes(1, 1, 8, vec![200], None);
+        assert_eq!(to_rgba(&mono, None, false).get_pixel(0, 0).0, [200, 200, 200, 255]);
+    }
+
+    #[test]
+    fn alpha_plane_is_applied_and_unmultiplied() {
+        let color = planes(1, 1, 8, vec![64], Some((128, 128)));
+        let alpha = planes(1, 1, 8, vec![128], None);
+        assert_eq!(to_rgba(&color, Some(&alpha), false).get_pixel(0, 0).0, [64, 64, 64, 128]);
+        assert_eq!(to_rgba(&color, Some(&alpha), true).get_pixel(0, 0).0, [128, 128, 128, 128]);
+    }
+}
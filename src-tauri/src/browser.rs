@@ -1,3 +1,4 @@
+use crate::http_client::{self, Route};
 use futures_util::{SinkExt, StreamExt};
 use regex::Regex;
 use serde::Deserialize;
@@ -7,7 +8,6 @@ use tokio_tungstenite::{connect_async, tungstenite::Message};
 
 const HTML_MAX_BYTES: usize = 100 * 1024; // 100KB
 const CDP_TIMEOUT_SECS: u64 = 15;
-const MAX_SCREENSHOT_BASE64: usize = 6_400_000; // ~4.8MB decoded
 
 // ---------- Data types ----------
 
@@ -177,47 +177,14 @@ fn compress_screenshot_base64(raw_base64: &str) -> Result<String, String> {
         return Ok(String::new());
     }
 
-    // If already within limits, return as-is
-    if raw_base64.len() <= MAX_SCREENSHOT_BASE64 {
-        return Ok(raw_base64.to_string());
-    }
-
-    // Decode base64 → raw JPEG bytes → re-encode at lower quality/size
+    // Same size rules as every other chat attachment
     use base64::Engine;
     let jpeg_bytes = base64::engine::general_purpose::STANDARD
         .decode(raw_base64)
         .map_err(|e| format!("Failed to decode screenshot: {}", e))?;
+    let normalized = crate::image_pipeline::normalize_bytes(&jpeg_bytes)?;
 
-    let img = image::load_from_memory_with_format(&jpeg_bytes, image::ImageFormat::Jpeg)
-        .map_err(|e| format!("Failed to parse screenshot image: {}", e))?;
-
-    let qualities = [70u8, 60, 50, 40];
-    let mut current = img;
-
-    for _ in 0..4 {
-        for quality in qualities {
-            let mut buf = Vec::new();
-            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, quality);
-            encoder
-                .encode_image(&current)
-                .map_err(|e| format!("Failed to re-encode screenshot: {}", e))?;
-
-            let b64 = base64::engine::general_purpose::STANDARD.encode(&buf);
-            if b64.len() <= MAX_SCREENSHOT_BASE64 {
-                return Ok(b64);
-            }
-        }
-
-        // Downscale by 80%
-        let next_w = ((current.width() as f32) * 0.8).round() as u32;
-        let next_h = ((current.height() as f32) * 0.8).round() as u32;
-        if next_w < 640 || next_h < 360 {
-            break;
-        }
-        current = current.resize(next_w, next_h, image::imageops::FilterType::Triangle);
-    }
-
-    Err("Screenshot too large to attach".to_string())
+    Ok(base64::engine::general_purpose::STANDARD.encode(&normalized.bytes))
 }
 
 // ---------- Multi-browser discovery (monitor-aware) ----------
@@ -226,13 +193,12 @@ const CDP_PORTS: &[u16] = &[9222, 9223, 9224];
 
 /// Find which monitor contains the given point.
 fn monitor_index_for_point(x: i32, y: i32) -> Option<usize> {
-    let screens = screenshots::Screen::all().ok()?;
-    screens.iter().position(|s| {
-        let info = s.display_info;
-        x >= info.x
-            && x < info.x + info.width as i32
-            && y >= info.y
-            && y < info.y + info.height as i32
+    let displays = crate::capture_backend::active().displays().ok()?;
+    displays.iter().position(|d| {
+        x >= d.x
+            && x < d.x + d.width as i32
+            && y >= d.y
+            && y < d.y + d.height as i32
     })
 }
 
@@ -276,11 +242,12 @@ pub(crate) async fn discover_tab_near_position(pet_x: i32, pet_y: i32) -> Result
     }
 
     let mut candidates: Vec<Candidate> = Vec::new();
+    let client = http_client::client(Route::Loopback)?;
 
     for &port in CDP_PORTS {
         // Try to connect to this port
         let version_url = format!("http://127.0.0.1:{}/json/version", port);
-        let version_resp = match reqwest::get(&version_url).await {
+        let version_resp = match client.get(&version_url).send().await {
             Ok(r) => r,
             Err(_) => continue,
         };
@@ -298,7 +265,7 @@ pub(crate) async fn discover_tab_near_position(pet_x: i32, pet_y: i32) -> Result
 
         // Get page targets
         let targets_url = format!("http://127.0.0.1:{}/json", port);
-        let targets: Vec<CdpTarget> = match reqwest::get(&targets_url).await {
+        let targets: Vec<CdpTarget> = match client.get(&targets_url).send().await {
             Ok(r) => match r.json().await {
                 Ok(t) => t,
                 Err(_) => continue,
@@ -0,0 +1,413 @@
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+const DEFAULT_COLOR: Rgba<u8> = Rgba([255, 59, 48, 255]);
+const MAX_LABEL_CHARS: usize = 60;
+
+/// The pet's pixel font; it only has Latin glyphs, others are drawn as `?`.
+static FONT: LazyLock<FontRef<'static>> = LazyLock::new(|| {
+    FontRef::try_from_slice(include_bytes!("../../public/fonts/PressStart2P-Regular.ttf"))
+        .expect("bundled font is valid")
+});
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// A shape drawn on a capture, in pixels of the captured image (before it is scaled for
+/// chat). `color` is `#rgb` or `#rrggbb`, red when missing.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Annotation {
+    Arrow {
+        from: Point,
+        to: Point,
+        #[serde(default)]
+        color: Option<String>,
+    },
+    #[serde(rename = "box")]
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        #[serde(default)]
+        color: Option<String>,
+    },
+    /// Numbered circle; numbers count up from 1 when not given.
+    Marker {
+        x: f32,
+        y: f32,
+        #[serde(default)]
+        number: Option<u32>,
+        #[serde(default)]
+        color: Option<String>,
+    },
+    Label {
+        x: f32,
+        y: f32,
+        text: String,
+        #[serde(default)]
+        color: Option<String>,
+    },
+}
+
+fn parse_color(color: Option<&str>) -> Rgba<u8> {
+    let Some(hex) = color.map(|c| c.trim().trim_start_matches('#')) else {
+        return DEFAULT_COLOR;
+    };
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    let parsed = match hex.len() {
+        3 => hex
+            .chars()
+            .map(|c| channel(&c.to_string()).map(|v| v * 17))
+            .collect::<Option<Vec<u8>>>(),
+        6 => (0..3)
+            .map(|i| hex.get(i * 2..i * 2 + 2).and_then(channel))
+            .collect::<Option<Vec<u8>>>(),
+        _ => None,
+    };
+    match parsed.as_deref() {
+        Some([r, g, b]) => Rgba([*r, *g, *b, 255]),
+        _ => DEFAULT_COLOR,
+    }
+}
+
+/// Black or white, whichever reads better on `background`.
+fn contrast(background: Rgba<u8>) -> Rgba<u8> {
+    let [r, g, b, _] = background.0;
+    let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    if luminance > 160.0 {
+        Rgba([0, 0, 0, 255])
+    } else {
+        Rgba([255, 255, 255, 255])
+    }
+}
+
+// ---------- Primitives ----------
+
+fn blend(image: &mut RgbaImage, x: i64, y: i64, color: Rgba<u8>, coverage: f32) {
+    if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+        return;
+    }
+    let alpha = coverage.clamp(0.0, 1.0) * color.0[3] as f32 / 255.0;
+    if alpha <= 0.0 {
+        return;
+    }
+    let pixel = image.get_pixel_mut(x as u32, y as u32);
+    for channel in 0..3 {
+        let mixed = color.0[channel] as f32 * alpha + pixel.0[channel] as f32 * (1.0 - alpha);
+        pixel.0[channel] = mixed.round() as u8;
+    }
+    pixel.0[3] = pixel.0[3].max((alpha * 255.0) as u8);
+}
+
+/// Pixels within `pad` of a rectangle, clipped to the image.
+fn span(image: &RgbaImage, min: Point, max: Point, pad: f32) -> (i64, i64, i64, i64) {
+    (
+        ((min.x - pad).floor() as i64).max(0),
+        ((min.y - pad).floor() as i64).max(0),
+        ((max.x + pad).ceil() as i64).min(image.width() as i64),
+        ((max.y + pad).ceil() as i64).min(image.height() as i64),
+    )
+}
+
+/// Anti-aliased line with round caps.
+fn stroke(image: &mut RgbaImage, from: Point, to: Point, width: f32, color: Rgba<u8>) {
+    let radius = width / 2.0;
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let length_sq = (dx * dx + dy * dy).max(f32::EPSILON);
+    let min = Point { x: from.x.min(to.x), y: from.y.min(to.y) };
+    let max = Point { x: from.x.max(to.x), y: from.y.max(to.y) };
+    let (left, top, right, bottom) = span(image, min, max, radius + 1.0);
+    for y in top..bottom {
+        for x in left..right {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let t = (((px - from.x) * dx + (py - from.y) * dy) / length_sq).clamp(0.0, 1.0);
+            let distance = ((px - from.x - t * dx).powi(2) + (py - from.y - t * dy).powi(2)).sqrt();
+            blend(image, x, y, color, radius + 0.5 - distance);
+        }
+    }
+}
+
+fn fill_circle(image: &mut RgbaImage, center: Point, radius: f32, color: Rgba<u8>) {
+    let (left, top, right, bottom) = span(image, center, center, radius + 1.0);
+    for y in top..bottom {
+        for x in left..right {
+            let distance = ((x as f32 + 0.5 - center.x).powi(2) + (y as f32 + 0.5 - center.y).powi(2)).sqrt();
+            blend(image, x, y, color, radius + 0.5 - distance);
+        }
+    }
+}
+
+fn fill_rect(image: &mut RgbaImage, min: Point, max: Point, color: Rgba<u8>) {
+    let (left, top, right, bottom) = span(image, min, max, 0.0);
+    for y in top..bottom {
+        for x in left..right {
+            blend(image, x, y, color, 1.0);
+        }
+    }
+}
+
+/// Even-odd fill of a closed polygon (no anti-aliasing; used for the small cursor).
+fn fill_polygon(image: &mut RgbaImage, points: &[Point], color: Rgba<u8>) {
+    let min = Point {
+        x: points.iter().map(|p| p.x).fold(f32::MAX, f32::min),
+        y: points.iter().map(|p| p.y).fold(f32::MAX, f32::min),
+    };
+    let max = Point {
+        x: points.iter().map(|p| p.x).fold(f32::MIN, f32::max),
+        y: points.iter().map(|p| p.y).fold(f32::MIN, f32::max),
+    };
+    let (left, top, right, bottom) = span(image, min, max, 0.0);
+    for y in top..bottom {
+        for x in left..right {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let mut inside = false;
+            for (i, a) in points.iter().enumerate() {
+                let b = points[(i + 1) % points.len()];
+                if (a.y > py) != (b.y > py) && px < a.x + (py - a.y) * (b.x - a.x) / (b.y - a.y) {
+                    inside = !inside;
+                }
+            }
+            if inside {
+                blend(image, x, y, color, 1.0);
+            }
+        }
+    }
+}
+
+fn text_width(text: &str, px: f32) -> f32 {
+    let font = FONT.as_scaled(PxScale::from(px));
+    text.chars().map(|c| font.h_advance(font.glyph_id(c))).sum()
+}
+
+/// Draw one line of text with its top-left corner at `origin`.
+fn draw_text(image: &mut RgbaImage, origin: Point, text: &str, px: f32, color: Rgba<u8>) {
+    let font = FONT.as_scaled(PxScale::from(px));
+    let mut caret = origin.x;
+    for c in text.chars() {
+        let mut glyph = font.scaled_glyph(c);
+        glyph.position = point(caret, origin.y + font.ascent());
+        caret += font.h_advance(glyph.id);
+        if let Some(outlined) = FONT.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                blend(
+                    image,
+                    bounds.min.x as i64 + gx as i64,
+                    bounds.min.y as i64 + gy as i64,
+                    color,
+                    coverage,
+                );
+            });
+        }
+    }
+}
+
+// ---------- Annotations ----------
+
+/// Line widths and text sizes grow with the capture so they stay visible once the image
+/// is scaled down for chat. The pixel font stays sharp at multiples of 8px.
+struct Metrics {
+    unit: f32,
+    stroke: f32,
+    font_px: f32,
+}
+
+impl Metrics {
+    fn for_image(image: &RgbaImage) -> Self {
+        let unit = (image.width().min(image.height()) as f32 / 1080.0).max(1.0);
+        Self {
+            unit,
+            stroke: 4.0 * unit,
+            font_px: 16.0 * unit.round(),
+        }
+    }
+}
+
+fn draw_arrow(image: &mut RgbaImage, metrics: &Metrics, from: Point, to: Point, color: Rgba<u8>) {
+    stroke(image, from, to, metrics.stroke, color);
+    let angle = (from.y - to.y).atan2(from.x - to.x);
+    let head = 18.0 * metrics.unit;
+    for side in [-0.5_f32, 0.5] {
+        let tip = Point {
+            x: to.x + head * (angle + side).cos(),
+            y: to.y + head * (angle + side).sin(),
+        };
+        stroke(image, to, tip, metrics.stroke, color);
+    }
+}
+
+fn draw_box(image: &mut RgbaImage, metrics: &Metrics, min: Point, max: Point, color: Rgba<u8>) {
+    let corners = [min, Point { x: max.x, y: min.y }, max, Point { x: min.x, y: max.y }];
+    for i in 0..4 {
+        stroke(image, corners[i], corners[(i + 1) % 4], metrics.stroke, color);
+    }
+}
+
+fn draw_marker(image: &mut RgbaImage, metrics: &Metrics, center: Point, number: u32, color: Rgba<u8>) {
+    let text = number.to_string();
+    let px = metrics.font_px;
+    let radius = (14.0 * metrics.unit).max(text_width(&text, px) / 2.0 + 6.0 * metrics.unit);
+    fill_circle(image, center, radius + metrics.unit * 2.0, Rgba([255, 255, 255, 255]));
+    fill_circle(image, center, radius, color);
+    let origin = Point {
+        x: center.x - text_width(&text, px) / 2.0,
+        y: center.y - px / 2.0,
+    };
+    draw_text(image, origin, &text, px, contrast(color));
+}
+
+fn draw_label(image: &mut RgbaImage, metrics: &Metrics, at: Point, text: &str, color: Rgba<u8>) {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_LABEL_CHARS)
+        .map(|c| if FONT.glyph_id(c).0 == 0 { '?' } else { c })
+        .collect();
+    if text.trim().is_empty() {
+        return;
+    }
+    let px = metrics.font_px;
+    let pad = px / 2.0;
+    let width = text_width(&text, px) + pad * 2.0;
+    let height = px + pad * 2.0;
+    // Keep the label on the image when it is placed near the right or bottom edge
+    let x = at.x.min(image.width() as f32 - width).max(0.0);
+    let y = at.y.min(image.height() as f32 - height).max(0.0);
+    fill_rect(image, Point { x, y }, Point { x: x + width, y: y + height }, color);
+    draw_text(image, Point { x: x + pad, y: y + pad }, &text, px, contrast(color));
+}
+
+/// Standard arrow pointer with its tip at `tip`.
+pub fn draw_cursor(image: &mut RgbaImage, tip: Point) {
+    let unit = Metrics::for_image(image).unit * 1.5;
+    let shape: Vec<Point> = [(0.0, 0.0), (0.0, 17.0), (4.0, 13.0), (7.0, 19.0), (9.0, 18.0), (6.0, 12.0), (12.0, 12.0)]
+        .iter()
+        .map(|(x, y)| Point {
+            x: tip.x + x * unit,
+            y: tip.y + y * unit,
+        })
+        .collect();
+    fill_polygon(image, &shape, Rgba([255, 255, 255, 255]));
+    for i in 0..shape.len() {
+        stroke(image, shape[i], shape[(i + 1) % shape.len()], unit, Rgba([0, 0, 0, 255]));
+    }
+}
+
+/// Draw the annotations in order, so later ones sit on top.
+pub fn apply(image: &mut RgbaImage, annotations: &[Annotation]) {
+    let metrics = Metrics::for_image(image);
+    let mut next_marker = 1;
+    for annotation in annotations {
+        match annotation {
+            Annotation::Arrow { from, to, color } => {
+                draw_arrow(image, &metrics, *from, *to, parse_color(color.as_deref()))
+            }
+            Annotation::Rect { x, y, width, height, color } => draw_box(
+                image,
+                &metrics,
+                Point { x: *x, y: *y },
+                Point { x: x + width, y: y + height },
+                parse_color(color.as_deref()),
+            ),
+            Annotation::Marker { x, y, number, color } => {
+                let number = number.unwrap_or(next_marker);
+                next_marker = number.saturating_add(1);
+                draw_marker(image, &metrics, Point { x: *x, y: *y }, number, parse_color(color.as_deref()));
+            }
+            Annotation::Label { x, y, text, color } => {
+                draw_label(image, &metrics, Point { x: *x, y: *y }, text, parse_color(color.as_deref()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank() -> RgbaImage {
+        RgbaImage::from_pixel(400, 300, Rgba([255, 255, 255, 255]))
+    }
+
+    fn touched(image: &RgbaImage, left: u32, top: u32, right: u32, bottom: u32) -> bool {
+        (top..bottom).any(|y| (left..right).any(|x| image.get_pixel(x, y) != &Rgba([255, 255, 255, 255])))
+    }
+
+    #[test]
+    fn annotations_deserialize_from_the_overlay_format() {
+        let annotations: Vec<Annotation> = serde_json::from_str(
+            r##"[
+                {"kind": "arrow", "from": {"x": 10, "y": 10}, "to": {"x": 100, "y": 80}},
+                {"kind": "box", "x": 5, "y": 5, "width": 50, "height": 20, "color": "#0f0"},
+                {"kind": "marker", "x": 200, "y": 150},
+                {"kind": "label", "x": 20, "y": 200, "text": "Broken button"}
+            ]"##,
+        )
+        .unwrap();
+        assert_eq!(annotations.len(), 4);
+        assert!(matches!(annotations[1], Annotation::Rect { width, .. } if width == 50.0));
+    }
+
+    #[test]
+    fn shapes_are_drawn_where_asked() {
+        let mut image = blank();
+        apply(
+            &mut image,
+            &[
+                Annotation::Rect { x: 20.0, y: 20.0, width: 100.0, height: 60.0, color: Some("#00f".into()) },
+                Annotation::Marker { x: 300.0, y: 60.0, number: None, color: None },
+                Annotation::Label { x: 390.0, y: 290.0, text: "잘못된 Label".into(), color: Some("#ffff00".into()) },
+            ],
+        );
+        // Box outline, but not its inside
+        assert_eq!(image.get_pixel(20, 50), &Rgba([0, 0, 255, 255]));
+        assert!(!touched(&image, 40, 40, 100, 60));
+        // Marker fill at its center
+        assert_eq!(image.get_pixel(300, 50), &DEFAULT_COLOR);
+        // Label pushed back inside the bottom-right corner
+        assert!(touched(&image, 300, 270, 400, 300));
+        assert!(!touched(&image, 0, 150, 200, 250));
+    }
+
+    #[test]
+    fn arrow_and_cursor_stay_within_the_image() {
+        let mut image = blank();
+        apply(
+            &mut image,
+            &[Annotation::Arrow { from: Point { x: -50.0, y: 150.0 }, to: Point { x: 390.0, y: 150.0 }, color: None }],
+        );
+        draw_cursor(&mut image, Point { x: 395.0, y: 295.0 });
+        assert_eq!(image.get_pixel(200, 150), &DEFAULT_COLOR);
+        assert!(!touched(&image, 0, 0, 400, 100));
+        assert_eq!(image.get_pixel(396, 299), &Rgba([255, 255, 255, 255]));
+        assert!(touched(&image, 394, 294, 400, 300));
+    }
+
+    #[test]
+    fn marker_numbers_continue_from_the_last_one_without_overflowing() {
+        let mut image = blank();
+        apply(
+            &mut image,
+            &[
+                Annotation::Marker { x: 50.0, y: 50.0, number: Some(u32::MAX), color: None },
+                Annotation::Marker { x: 150.0, y: 50.0, number: None, color: None },
+            ],
+        );
+        assert!(touched(&image, 140, 40, 160, 60));
+    }
+
+    #[test]
+    fn colors_fall_back_to_red() {
+        assert_eq!(parse_color(Some("#1e90ff")), Rgba([30, 144, 255, 255]));
+        assert_eq!(parse_color(Some("fff")), Rgba([255, 255, 255, 255]));
+        assert_eq!(parse_color(Some("blue")), DEFAULT_COLOR);
+        assert_eq!(parse_color(None), DEFAULT_COLOR);
+    }
+}
@@ -0,0 +1,884 @@
+use crate::capture_geometry::{self, DisplayGeometry, DisplayUnits};
+use image::{imageops::FilterType, Rgba, RgbaImage};
+use std::sync::{Arc, LazyLock, RwLock};
+use tauri::menu::{CheckMenuItem, Submenu};
+use tauri::{AppHandle, Manager, Wry};
+
+/// Longest side of a stitched all-displays canvas, before the chat size ladder.
+const MAX_STITCHED_SIDE: f64 = 16_384.0;
+/// Fill for desktop areas no display covers.
+const GAP_FILL: Rgba<u8> = Rgba([32, 32, 32, 255]);
+
+/// A display as seen by a capture backend. Bounds are in the backend's display units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Display {
+    pub id: u32,
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub rotation: f32,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+impl Display {
+    pub fn geometry(&self) -> DisplayGeometry {
+        DisplayGeometry {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// A top-level application window. Bounds are in display units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Window {
+    pub id: u32,
+    pub title: String,
+    pub app_name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub is_minimized: bool,
+}
+
+pub trait CaptureBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Units of `Display` bounds and capture areas.
+    fn units(&self) -> DisplayUnits {
+        DisplayUnits::native()
+    }
+
+    fn displays(&self) -> Result<Vec<Display>, String>;
+
+    fn capture_display(&self, id: u32) -> Result<RgbaImage, String>;
+
+    /// Area relative to the display's top-left corner, in display units.
+    fn capture_area(&self, id: u32, x: i32, y: i32, width: u32, height: u32) -> Result<RgbaImage, String>;
+
+    /// Top-level windows, frontmost first.
+    fn windows(&self) -> Result<Vec<Window>, String>;
+
+    /// Capture one window. By default this is the window's rectangle on the screen, so it
+    /// must not be covered by other windows.
+    fn capture_window(&self, id: u32) -> Result<RgbaImage, String> {
+        let window = find_window(self, id)?;
+        let displays = self.displays()?;
+        let geometry: Vec<DisplayGeometry> = displays.iter().map(Display::geometry).collect();
+        // Window bounds are already in display units, so no source scale applies
+        let area = capture_geometry::map_region(
+            &geometry,
+            self.units(),
+            (window.x, window.y, window.width, window.height),
+            1.0,
+        )?;
+        self.capture_area(displays[area.display].id, area.x, area.y, area.width, area.height)
+    }
+}
+
+pub fn find_window<B: CaptureBackend + ?Sized>(backend: &B, id: u32) -> Result<Window, String> {
+    let window = backend
+        .windows()?
+        .into_iter()
+        .find(|window| window.id == id)
+        .ok_or_else(|| "Selected window not found".to_string())?;
+    if window.is_minimized {
+        return Err("Selected window is minimized".to_string());
+    }
+    Ok(window)
+}
+
+/// Crop a full-display capture to an area given in display units. Captures are in
+/// physical pixels, so on displays measured in points the area is scaled first.
+fn crop_to_area(image: &RgbaImage, display: &Display, x: i32, y: i32, width: u32, height: u32) -> Result<RgbaImage, String> {
+    let scale = image.width() as f64 / display.width.max(1) as f64;
+    let left = ((x.max(0) as f64) * scale).floor() as u32;
+    let top = ((y.max(0) as f64) * scale).floor() as u32;
+    let right = (((x.max(0) as f64) + width as f64) * scale).ceil().min(image.width() as f64) as u32;
+    let bottom = (((y.max(0) as f64) + height as f64) * scale).ceil().min(image.height() as f64) as u32;
+    if right <= left || bottom <= top {
+        return Err("Capture area is outside of the display".to_string());
+    }
+    Ok(image::imageops::crop_imm(image, left, top, right - left, bottom - top).to_image())
+}
+
+// ---------- screenshots ----------
+
+pub struct ScreenshotsBackend;
+
+impl ScreenshotsBackend {
+    fn screen(&self, id: u32) -> Result<screenshots::Screen, String> {
+        screenshots::Screen::all()
+            .map_err(|e| format!("Failed to list screens: {e}"))?
+            .into_iter()
+            .find(|screen| screen.display_info.id == id)
+            .ok_or_else(|| "Selected display not found".to_string())
+    }
+}
+
+impl CaptureBackend for ScreenshotsBackend {
+    fn name(&self) -> &'static str {
+        "screenshots"
+    }
+
+    fn displays(&self) -> Result<Vec<Display>, String> {
+        let screens = screenshots::Screen::all().map_err(|e| format!("Failed to list screens: {e}"))?;
+        Ok(screens
+            .iter()
+            .map(|screen| {
+                let info = screen.display_info;
+                Display {
+                    id: info.id,
+                    name: None,
+                    x: info.x,
+                    y: info.y,
+                    width: info.width,
+                    height: info.height,
+                    rotation: info.rotation,
+                    scale_factor: info.scale_factor,
+                    is_primary: info.is_primary,
+                }
+            })
+            .collect())
+    }
+
+    fn capture_display(&self, id: u32) -> Result<RgbaImage, String> {
+        self.screen(id)?
+            .capture()
+            .map_err(|e| format!("Failed to capture screen: {e}"))
+    }
+
+    fn capture_area(&self, id: u32, x: i32, y: i32, width: u32, height: u32) -> Result<RgbaImage, String> {
+        self.screen(id)?
+            .capture_area(x, y, width, height)
+            .map_err(|e| format!("Failed to capture screen: {e}"))
+    }
+
+    /// The screenshots crate can't enumerate windows; xcap's list is used, and windows are
+    /// cut out of the screen.
+    fn windows(&self) -> Result<Vec<Window>, String> {
+        XcapBackend.windows()
+    }
+}
+
+// ---------- xcap ----------
+
+/// xcap is built on a newer `image`; move the pixels over as raw RGBA.
+fn convert_image(captured: xcap::image::RgbaImage) -> Result<RgbaImage, String> {
+    let (width, height) = (captured.width(), captured.height());
+    RgbaImage::from_raw(width, height, captured.into_raw())
+        .ok_or_else(|| "Failed to capture screen: invalid image".to_string())
+}
+
+pub struct XcapBackend;
+
+impl XcapBackend {
+    fn monitor(&self, id: u32) -> Result<xcap::Monitor, String> {
+        xcap::Monitor::all()
+            .map_err(|e| format!("Failed to list screens: {e}"))?
+            .into_iter()
+            .find(|monitor| monitor.id() == id)
+            .ok_or_else(|| "Selected display not found".to_string())
+    }
+
+    fn display(monitor: &xcap::Monitor) -> Display {
+        Display {
+            id: monitor.id(),
+            name: Some(monitor.name().to_string()).filter(|name| !name.is_empty()),
+            x: monitor.x(),
+            y: monitor.y(),
+            width: monitor.width(),
+            height: monitor.height(),
+            rotation: monitor.rotation(),
+            scale_factor: monitor.scale_factor(),
+            is_primary: monitor.is_primary(),
+        }
+    }
+
+    fn capture(monitor: &xcap::Monitor) -> Result<RgbaImage, String> {
+        convert_image(
+            monitor
+                .capture_image()
+                .map_err(|e| format!("Failed to capture screen: {e}"))?,
+        )
+    }
+}
+
+impl CaptureBackend for XcapBackend {
+    fn name(&self) -> &'static str {
+        "xcap"
+    }
+
+    fn displays(&self) -> Result<Vec<Display>, String> {
+        let monitors = xcap::Monitor::all().map_err(|e| format!("Failed to list screens: {e}"))?;
+        Ok(monitors.iter().map(Self::display).collect())
+    }
+
+    fn capture_display(&self, id: u32) -> Result<RgbaImage, String> {
+        Self::capture(&self.monitor(id)?)
+    }
+
+    fn capture_area(&self, id: u32, x: i32, y: i32, width: u32, height: u32) -> Result<RgbaImage, String> {
+        let monitor = self.monitor(id)?;
+        crop_to_area(&Self::capture(&monitor)?, &Self::display(&monitor), x, y, width, height)
+    }
+
+    fn windows(&self) -> Result<Vec<Window>, String> {
+        let windows = xcap::Window::all().map_err(|e| format!("Failed to list windows: {e}"))?;
+        Ok(windows
+            .iter()
+            .map(|window| Window {
+                id: window.id(),
+                title: window.title().to_string(),
+                app_name: window.app_name().to_string(),
+                x: window.x(),
+                y: window.y(),
+                width: window.width(),
+                height: window.height(),
+                is_minimized: window.is_minimized(),
+            })
+            .collect())
+    }
+
+    /// xcap reads the window's own contents, so it works even when the window is covered.
+    fn capture_window(&self, id: u32) -> Result<RgbaImage, String> {
+        let window = xcap::Window::all()
+            .map_err(|e| format!("Failed to list windows: {e}"))?
+            .into_iter()
+            .find(|window| window.id() == id)
+            .ok_or_else(|| "Selected window not found".to_string())?;
+        if window.is_minimized() {
+            return Err("Selected window is minimized".to_string());
+        }
+        convert_image(
+            window
+                .capture_image()
+                .map_err(|e| format!("Failed to capture window: {e}"))?,
+        )
+    }
+}
+
+// ---------- Fake ----------
+
+/// Displays backed by fixed images, for headless runs and tests. Images are the physical
+/// pixels of each display, so a 2x display in logical units has an image twice its size.
+pub struct FakeBackend {
+    units: DisplayUnits,
+    screens: Vec<(Display, RgbaImage)>,
+    windows: Vec<Window>,
+}
+
+impl FakeBackend {
+    pub fn new(units: DisplayUnits, screens: Vec<(Display, RgbaImage)>) -> Self {
+        Self {
+            units,
+            screens,
+            windows: Vec::new(),
+        }
+    }
+
+    /// Windows to report, frontmost first. They are captured from the display images.
+    pub fn with_windows(mut self, windows: Vec<Window>) -> Self {
+        self.windows = windows;
+        self
+    }
+
+    /// Two side-by-side displays with distinct gradients: a 1600x900 primary and an
+    /// 800x600 secondary, with an editor window on the first and a browser on the second.
+    pub fn synthetic() -> Self {
+        let screen = |id: u32, x: i32, width: u32, height: u32, tint: u8| {
+            let display = Display {
+                id,
+                name: Some(format!("Fake display {}", id)),
+                x,
+                y: 0,
+                width,
+                height,
+                rotation: 0.0,
+                scale_factor: 1.0,
+                is_primary: id == 1,
+            };
+            let image = RgbaImage::from_fn(width, height, |px, py| {
+                Rgba([(px % 256) as u8, (py % 256) as u8, tint, 255])
+            });
+            (display, image)
+        };
+        let window = |id: u32, title: &str, app_name: &str, x: i32, y: i32, width: u32, height: u32| Window {
+            id,
+            title: title.to_string(),
+            app_name: app_name.to_string(),
+            x,
+            y,
+            width,
+            height,
+            is_minimized: false,
+        };
+        Self::new(
+            DisplayUnits::Physical,
+            vec![screen(1, 0, 1600, 900, 0), screen(2, 1600, 800, 600, 255)],
+        )
+        .with_windows(vec![
+            window(10, "main.rs - editor", "Editor", 100, 100, 1200, 700),
+            window(11, "Docs - Browser", "Browser", 1700, 50, 600, 500),
+        ])
+    }
+
+    fn screen(&self, id: u32) -> Result<&(Display, RgbaImage), String> {
+        self.screens
+            .iter()
+            .find(|(display, _)| display.id == id)
+            .ok_or_else(|| "Selected display not found".to_string())
+    }
+}
+
+impl CaptureBackend for FakeBackend {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn units(&self) -> DisplayUnits {
+        self.units
+    }
+
+    fn displays(&self) -> Result<Vec<Display>, String> {
+        Ok(self.screens.iter().map(|(display, _)| display.clone()).collect())
+    }
+
+    fn capture_display(&self, id: u32) -> Result<RgbaImage, String> {
+        Ok(self.screen(id)?.1.clone())
+    }
+
+    fn capture_area(&self, id: u32, x: i32, y: i32, width: u32, height: u32) -> Result<RgbaImage, String> {
+        let (display, image) = self.screen(id)?;
+        crop_to_area(image, display, x, y, width, height)
+    }
+
+    fn windows(&self) -> Result<Vec<Window>, String> {
+        Ok(self.windows.clone())
+    }
+}
+
+// ---------- Selection ----------
+
+/// Backends offered in the tray menu; `fake` is only reachable by name.
+const MENU_BACKENDS: [&str; 2] = ["screenshots", "xcap"];
+const BACKEND_MENU_PREFIX: &str = "capture-backend:";
+
+fn backend_named(name: &str) -> Option<Arc<dyn CaptureBackend>> {
+    match name.trim().to_lowercase().as_str() {
+        "" | "screenshots" => Some(Arc::new(ScreenshotsBackend)),
+        "xcap" => Some(Arc::new(XcapBackend)),
+        "fake" => Some(Arc::new(FakeBackend::synthetic())),
+        _ => None,
+    }
+}
+
+/// Starts as `CLAWPET_CAPTURE_BACKEND`: `screenshots` (default), `xcap`, or `fake` for
+/// synthetic displays without a real screen. Switched from the tray or `set_capture_backend`.
+static BACKEND: LazyLock<RwLock<Arc<dyn CaptureBackend>>> = LazyLock::new(|| {
+    let choice = std::env::var("CLAWPET_CAPTURE_BACKEND").unwrap_or_default();
+    let backend = backend_named(&choice).unwrap_or_else(|| {
+        eprintln!("[capture] Unknown capture backend '{}', using screenshots", choice.trim());
+        Arc::new(ScreenshotsBackend)
+    });
+    eprintln!("[capture] Using {} backend", backend.name());
+    RwLock::new(backend)
+});
+
+/// The backend captures go through. A capture that is already running keeps the one it
+/// started with.
+pub fn active() -> Arc<dyn CaptureBackend> {
+    BACKEND.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn switch_to(backend: Arc<dyn CaptureBackend>) {
+    eprintln!("[capture] Switching to {} backend", backend.name());
+    *BACKEND.write().unwrap_or_else(|e| e.into_inner()) = backend;
+}
+
+/// Switch by name; unknown names leave the current backend in place.
+pub fn select(name: &str) -> Result<&'static str, String> {
+    let backend = backend_named(name).ok_or_else(|| format!("Unknown capture backend: {}", name.trim()))?;
+    let name = backend.name();
+    switch_to(backend);
+    Ok(name)
+}
+
+pub struct CaptureBackendMenu(Vec<(&'static str, CheckMenuItem<Wry>)>);
+
+/// Tray submenu with one checked entry per backend.
+pub fn tray_submenu(app: &AppHandle) -> tauri::Result<Submenu<Wry>> {
+    let submenu = Submenu::with_id(app, "capture-backend", "Capture backend", true)?;
+    let current = active().name();
+    let mut items = Vec::new();
+    for name in MENU_BACKENDS {
+        let id = format!("{}{}", BACKEND_MENU_PREFIX, name);
+        let item = CheckMenuItem::with_id(app, id, name, true, name == current, None::<&str>)?;
+        submenu.append(&item)?;
+        items.push((name, item));
+    }
+    app.manage(CaptureBackendMenu(items));
+    Ok(submenu)
+}
+
+fn refresh_tray(app: &AppHandle) {
+    let Some(menu) = app.try_state::<CaptureBackendMenu>() else {
+        return;
+    };
+    let current = active().name();
+    for (name, item) in &menu.inner().0 {
+        let _ = item.set_checked(*name == current);
+    }
+}
+
+pub fn handle_tray_event(app: &AppHandle, id: &str) {
+    let Some(name) = id.strip_prefix(BACKEND_MENU_PREFIX) else {
+        return;
+    };
+    if let Err(e) = select(name) {
+        eprintln!("[capture] {}", e);
+    }
+    refresh_tray(app);
+}
+
+/// Switch the capture backend by name and return the one now in use.
+#[tauri::command]
+pub async fn set_capture_backend(app: AppHandle, name: String) -> Result<String, String> {
+    let selected = select(&name)?;
+    refresh_tray(&app);
+    Ok(selected.to_string())
+}
+
+// ---------- Targets ----------
+
+/// What a capture command will grab, resolved before ClawPet's windows are hidden.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptureTarget {
+    pub display: Display,
+    /// `(x, y, width, height)` relative to the display; `None` for the whole display.
+    pub area: Option<(i32, i32, u32, u32)>,
+}
+
+fn all_displays(backend: &dyn CaptureBackend) -> Result<Vec<Display>, String> {
+    let displays = backend.displays()?;
+    if displays.is_empty() {
+        return Err("No display found".to_string());
+    }
+    Ok(displays)
+}
+
+/// Physical webview region (capture overlay) to the display under its center.
+pub fn region_target(
+    backend: &dyn CaptureBackend,
+    region: (i32, i32, u32, u32),
+    source_scale: f64,
+) -> Result<CaptureTarget, String> {
+    let displays = all_displays(backend)?;
+    let geometry: Vec<DisplayGeometry> = displays.iter().map(Display::geometry).collect();
+    let area = capture_geometry::map_region(&geometry, backend.units(), region, source_scale)?;
+    Ok(CaptureTarget {
+        display: displays[area.display].clone(),
+        area: Some((area.x, area.y, area.width, area.height)),
+    })
+}
+
+/// Whole display under a physical webview point.
+pub fn point_target(
+    backend: &dyn CaptureBackend,
+    x: i32,
+    y: i32,
+    source_scale: f64,
+) -> Result<CaptureTarget, String> {
+    let displays = all_displays(backend)?;
+    let geometry: Vec<DisplayGeometry> = displays.iter().map(Display::geometry).collect();
+    let index = capture_geometry::display_for_point(&geometry, backend.units(), x, y, source_scale)
+        .ok_or_else(|| "No display found".to_string())?;
+    Ok(CaptureTarget {
+        display: displays[index].clone(),
+        area: None,
+    })
+}
+
+pub fn display_target(backend: &dyn CaptureBackend, id: u32) -> Result<CaptureTarget, String> {
+    let display = all_displays(backend)?
+        .into_iter()
+        .find(|display| display.id == id)
+        .ok_or_else(|| "Selected display not found".to_string())?;
+    Ok(CaptureTarget { display, area: None })
+}
+
+/// Display a window is on: the one under its center.
+pub fn display_for_window(displays: &[Display], units: DisplayUnits, window: &Window) -> Option<usize> {
+    let geometry: Vec<DisplayGeometry> = displays.iter().map(Display::geometry).collect();
+    capture_geometry::display_for_point(
+        &geometry,
+        units,
+        window.x + (window.width / 2) as i32,
+        window.y + (window.height / 2) as i32,
+        1.0,
+    )
+}
+
+/// Frontmost window that is worth capturing, skipping `is_own` (ClawPet's windows),
+/// minimized windows and untitled slivers such as taskbars and menu bars. ClawPet itself
+/// is focused once the pet is clicked, so this is the window that had focus before.
+pub fn active_window(
+    backend: &dyn CaptureBackend,
+    is_own: impl Fn(&Window) -> bool,
+) -> Result<Window, String> {
+    backend
+        .windows()?
+        .into_iter()
+        .find(|window| {
+            !window.is_minimized
+                && !window.title.trim().is_empty()
+                && window.width >= 64
+                && window.height >= 64
+                && !is_own(window)
+        })
+        .ok_or_else(|| "No active window found".to_string())
+}
+
+// ---------- Frames ----------
+
+/// Desktop rectangle a capture shows, in display units, for placing desktop points such
+/// as the mouse pointer on the captured pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CaptureFrame {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl CaptureFrame {
+    /// Pixel position of a desktop point in a `(width, height)` capture of this frame,
+    /// `None` when it falls outside.
+    pub fn pixel(&self, (x, y): (f64, f64), (width, height): (u32, u32)) -> Option<(f32, f32)> {
+        let px = (x - self.x) * width as f64 / self.width.max(1.0);
+        let py = (y - self.y) * height as f64 / self.height.max(1.0);
+        (px >= 0.0 && py >= 0.0 && px < width as f64 && py < height as f64).then_some((px as f32, py as f32))
+    }
+}
+
+impl CaptureTarget {
+    pub fn frame(&self) -> CaptureFrame {
+        let (x, y, width, height) = self
+            .area
+            .unwrap_or((0, 0, self.display.width, self.display.height));
+        CaptureFrame {
+            x: (self.display.x + x) as f64,
+            y: (self.display.y + y) as f64,
+            width: width as f64,
+            height: height as f64,
+        }
+    }
+}
+
+impl Window {
+    pub fn frame(&self) -> CaptureFrame {
+        CaptureFrame {
+            x: self.x as f64,
+            y: self.y as f64,
+            width: self.width as f64,
+            height: self.height as f64,
+        }
+    }
+}
+
+/// Bounding box of all displays, the frame of a stitched capture.
+pub fn desktop_frame(displays: &[Display]) -> CaptureFrame {
+    let left = displays.iter().map(|d| d.x).min().unwrap_or(0);
+    let top = displays.iter().map(|d| d.y).min().unwrap_or(0);
+    let right = displays.iter().map(|d| d.x + d.width as i32).max().unwrap_or(0);
+    let bottom = displays.iter().map(|d| d.y + d.height as i32).max().unwrap_or(0);
+    CaptureFrame {
+        x: left as f64,
+        y: top as f64,
+        width: (right - left) as f64,
+        height: (bottom - top) as f64,
+    }
+}
+
+/// Physical desktop point (as the window system reports the mouse pointer) in display
+/// units. Points on a Mac are divided by the scale of the display they fall on.
+pub fn physical_to_display_units(displays: &[Display], units: DisplayUnits, x: f64, y: f64) -> (f64, f64) {
+    if units == DisplayUnits::Physical {
+        return (x, y);
+    }
+    let scale = displays
+        .iter()
+        .find(|d| {
+            let scale = d.scale_factor.max(0.1) as f64;
+            let (px, py) = (x / scale, y / scale);
+            px >= d.x as f64 && px < (d.x + d.width as i32) as f64 && py >= d.y as f64 && py < (d.y + d.height as i32) as f64
+        })
+        .or_else(|| displays.iter().find(|d| d.is_primary))
+        .map(|d| d.scale_factor.max(0.1) as f64)
+        .unwrap_or(1.0);
+    (x / scale, y / scale)
+}
+
+pub fn capture(backend: &dyn CaptureBackend, target: &CaptureTarget) -> Result<RgbaImage, String> {
+    match target.area {
+        Some((x, y, width, height)) => backend.capture_area(target.display.id, x, y, width, height),
+        None => backend.capture_display(target.display.id),
+    }
+}
+
+// ---------- All displays ----------
+
+/// Composite display captures at their virtual-desktop positions. The canvas uses the
+/// highest pixel density among the captures (points are 2x pixels on a Retina Mac), so
+/// lower-density displays are scaled up to line up with their neighbours.
+pub fn stitch(parts: &[(Display, RgbaImage)]) -> Result<RgbaImage, String> {
+    if parts.is_empty() {
+        return Err("No display found".to_string());
+    }
+    let displays: Vec<Display> = parts.iter().map(|(d, _)| d.clone()).collect();
+    let frame = desktop_frame(&displays);
+    let (left, top) = (frame.x as i32, frame.y as i32);
+    let (extent_x, extent_y) = (frame.width.max(1.0), frame.height.max(1.0));
+
+    let density = parts
+        .iter()
+        .map(|(d, image)| image.width() as f64 / d.width.max(1) as f64)
+        .fold(1.0_f64, f64::max);
+    let scale = density.min(MAX_STITCHED_SIDE / extent_x.max(extent_y));
+
+    let mut canvas = RgbaImage::from_pixel(
+        (extent_x * scale).round() as u32,
+        (extent_y * scale).round() as u32,
+        GAP_FILL,
+    );
+    for (display, image) in parts {
+        let width = ((display.width as f64 * scale).round() as u32).max(1);
+        let height = ((display.height as f64 * scale).round() as u32).max(1);
+        let x = ((display.x - left) as f64 * scale).round() as i64;
+        let y = ((display.y - top) as f64 * scale).round() as i64;
+        if image.dimensions() == (width, height) {
+            image::imageops::replace(&mut canvas, image, x, y);
+        } else {
+            let resized = image::imageops::resize(image, width, height, FilterType::Triangle);
+            image::imageops::replace(&mut canvas, &resized, x, y);
+        }
+    }
+    Ok(canvas)
+}
+
+/// Capture every display and stitch them into one image.
+pub fn capture_all(backend: &dyn CaptureBackend) -> Result<RgbaImage, String> {
+    let parts = all_displays(backend)?
+        .into_iter()
+        .map(|display| backend.capture_display(display.id).map(|image| (display, image)))
+        .collect::<Result<Vec<_>, String>>()?;
+    stitch(&parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_pipeline;
+
+    /// Retina primary (1440x900 points, 2880x1800 pixels) with a 1x display to its left.
+    fn logical_backend() -> FakeBackend {
+        let display = |id: u32, x: i32, width: u32, height: u32, scale: f32| Display {
+            id,
+            name: None,
+            x,
+            y: 0,
+            width,
+            height,
+            rotation: 0.0,
+            scale_factor: scale,
+            is_primary: id == 1,
+        };
+        let pixels = |width: u32, height: u32| {
+            RgbaImage::from_fn(width, height, |x, y| Rgba([(x / 16) as u8, (y / 16) as u8, 0, 255]))
+        };
+        FakeBackend::new(
+            DisplayUnits::Logical,
+            vec![
+                (display(1, 0, 1440, 900, 2.0), pixels(2880, 1800)),
+                (display(2, -1280, 1280, 1024, 1.0), pixels(1280, 1024)),
+            ],
+        )
+    }
+
+    #[test]
+    fn region_selects_display_and_crops_pixels() {
+        let backend = FakeBackend::synthetic();
+        let target = region_target(&backend, (1700, 50, 200, 100), 1.0).unwrap();
+        assert_eq!(target.display.id, 2);
+        assert_eq!(target.area, Some((100, 50, 200, 100)));
+
+        let image = capture(&backend, &target).unwrap();
+        assert_eq!(image.dimensions(), (200, 100));
+        // Secondary display gradient: x/y of the display pixel, blue tint
+        assert_eq!(image.get_pixel(0, 0), &Rgba([100, 50, 255, 255]));
+    }
+
+    #[test]
+    fn region_over_the_edge_is_clamped() {
+        let backend = FakeBackend::synthetic();
+        let target = region_target(&backend, (2300, 500, 300, 300), 1.0).unwrap();
+        assert_eq!(target.display.id, 2);
+        assert_eq!(target.area, Some((700, 500, 100, 100)));
+        assert_eq!(capture(&backend, &target).unwrap().dimensions(), (100, 100));
+
+        assert!(region_target(&backend, (2300, 700, 100, 100), 1.0).is_err());
+        assert!(region_target(&backend, (0, 0, 0, 0), 1.0).is_err());
+    }
+
+    #[test]
+    fn logical_area_is_scaled_to_physical_pixels() {
+        let backend = logical_backend();
+        // Drawn on the Retina display: physical (400, 200, 640, 320) is (200, 100, 320, 160) points
+        let target = region_target(&backend, (400, 200, 640, 320), 2.0).unwrap();
+        assert_eq!(target.display.id, 1);
+        assert_eq!(target.area, Some((200, 100, 320, 160)));
+        let image = capture(&backend, &target).unwrap();
+        assert_eq!(image.dimensions(), (640, 320));
+        assert_eq!(image.get_pixel(0, 0), &Rgba([25, 12, 0, 255]));
+    }
+
+    #[test]
+    fn point_and_display_targets() {
+        let backend = logical_backend();
+        assert_eq!(point_target(&backend, -100, 300, 1.0).unwrap().display.id, 2);
+        assert_eq!(point_target(&backend, 2000, 1000, 2.0).unwrap().display.id, 1);
+        assert_eq!(display_target(&backend, 2).unwrap().display.width, 1280);
+        assert!(display_target(&backend, 9).is_err());
+        assert!(point_target(&FakeBackend::new(DisplayUnits::Physical, vec![]), 0, 0, 1.0).is_err());
+    }
+
+    #[test]
+    fn displays_are_stitched_at_their_positions() {
+        let backend = FakeBackend::synthetic();
+        let image = capture_all(&backend).unwrap();
+        assert_eq!(image.dimensions(), (2400, 900));
+        assert_eq!(image.get_pixel(10, 20), &Rgba([10, 20, 0, 255]));
+        assert_eq!(image.get_pixel(1700, 50), &Rgba([100, 50, 255, 255]));
+        // Below the shorter secondary display
+        assert_eq!(image.get_pixel(2000, 800), &GAP_FILL);
+    }
+
+    #[test]
+    fn stitching_scales_to_the_densest_display() {
+        let backend = logical_backend();
+        let image = capture_all(&backend).unwrap();
+        // 2720x1024 points at 2x
+        assert_eq!(image.dimensions(), (5440, 2048));
+        // Retina pixel (32, 16) sits right of the 1x display, which was scaled up
+        assert_eq!(image.get_pixel(2560 + 32, 16), &Rgba([2, 1, 0, 255]));
+        assert_eq!(image.get_pixel(2559, 2047), &Rgba([79, 63, 0, 255]));
+        assert_eq!(image.get_pixel(5000, 1900), &GAP_FILL);
+    }
+
+    #[test]
+    fn desktop_points_map_into_capture_pixels() {
+        let backend = FakeBackend::synthetic();
+        let target = region_target(&backend, (1700, 50, 200, 100), 1.0).unwrap();
+        assert_eq!(target.frame().pixel((1750.0, 60.0), (200, 100)), Some((50.0, 10.0)));
+        assert_eq!(target.frame().pixel((1600.0, 60.0), (200, 100)), None);
+
+        // Stitched Retina desktop: the pointer at physical (400, 200) is (200, 100) points
+        let backend = logical_backend();
+        let displays = backend.displays().unwrap();
+        let point = physical_to_display_units(&displays, backend.units(), 400.0, 200.0);
+        assert_eq!(point, (200.0, 100.0));
+        assert_eq!(desktop_frame(&displays).pixel(point, (5440, 2048)), Some((2960.0, 200.0)));
+        assert_eq!(find_window(&FakeBackend::synthetic(), 11).unwrap().frame().width, 600.0);
+    }
+
+    #[test]
+    fn window_is_cut_from_its_display() {
+        let backend = FakeBackend::synthetic();
+        let image = backend.capture_window(11).unwrap();
+        assert_eq!(image.dimensions(), (600, 500));
+        assert_eq!(image.get_pixel(0, 0), &Rgba([100, 50, 255, 255]));
+        assert!(backend.capture_window(99).is_err());
+
+        let displays = backend.displays().unwrap();
+        let windows = backend.windows().unwrap();
+        assert_eq!(display_for_window(&displays, backend.units(), &windows[0]), Some(0));
+        assert_eq!(display_for_window(&displays, backend.units(), &windows[1]), Some(1));
+    }
+
+    #[test]
+    fn active_window_skips_own_and_minimized_windows() {
+        let own = Window {
+            id: 1,
+            title: "ClawGotchi".to_string(),
+            app_name: "clawpet".to_string(),
+            x: 1400,
+            y: 700,
+            width: 128,
+            height: 160,
+            is_minimized: false,
+        };
+        let minimized = Window {
+            id: 2,
+            title: "Mail".to_string(),
+            app_name: "Mail".to_string(),
+            is_minimized: true,
+            ..own.clone()
+        };
+        let taskbar = Window {
+            id: 3,
+            title: String::new(),
+            app_name: "Shell".to_string(),
+            width: 1600,
+            height: 40,
+            ..own.clone()
+        };
+        let mut windows = vec![own, minimized, taskbar];
+        windows.extend(FakeBackend::synthetic().windows().unwrap());
+        let backend = FakeBackend::synthetic().with_windows(windows);
+
+        let active = active_window(&backend, |window| window.app_name == "clawpet").unwrap();
+        assert_eq!(active.id, 10);
+        assert!(active_window(&backend, |_| true).is_err());
+    }
+
+    #[test]
+    fn captures_encode_within_the_attachment_limit() {
+        let backend = FakeBackend::synthetic();
+        let target = point_target(&backend, 10, 10, 1.0).unwrap();
+        let image = capture(&backend, &target).unwrap();
+        let normalized = image_pipeline::normalize_capture(image, false).unwrap();
+        assert!(normalized.bytes.len() <= image_pipeline::MAX_CHAT_ATTACHMENT_BYTES);
+        let decoded = image::load_from_memory(&normalized.bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (1600, 900));
+
+        let target = region_target(&backend, (10, 10, 64, 48), 1.0).unwrap();
+        let region = image_pipeline::normalize_capture(capture(&backend, &target).unwrap(), true).unwrap();
+        assert_eq!(region.mime_type, "image/png");
+        assert_eq!((region.info.width, region.info.height), (64, 48));
+    }
+
+    #[test]
+    fn backends_can_be_switched_at_runtime() {
+        let original = active();
+        assert!(select("Hyperspeed").is_err());
+        assert_eq!(active().name(), original.name());
+
+        assert_eq!(select(" FAKE ").unwrap(), "fake");
+        let backend = active();
+        assert_eq!(backend.name(), "fake");
+        assert_eq!(backend.displays().unwrap().len(), 2);
+
+        // A backend taken before the switch keeps working
+        assert_eq!(select("xcap").unwrap(), "xcap");
+        assert_eq!(active().name(), "xcap");
+        assert_eq!(capture_all(&*backend).unwrap().dimensions(), (2400, 900));
+
+        switch_to(original);
+    }
+}
@@ -0,0 +1,223 @@
+/// Units the capture backend reports display bounds in, and expects capture areas in.
+/// Windows and X11 use physical pixels of the virtual desktop; macOS uses points.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DisplayUnits {
+    Physical,
+    Logical,
+}
+
+impl DisplayUnits {
+    pub fn native() -> Self {
+        if cfg!(target_os = "macos") {
+            DisplayUnits::Logical
+        } else {
+            DisplayUnits::Physical
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DisplayGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl DisplayGeometry {
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x as f64
+            && x < self.x as f64 + self.width as f64
+            && y >= self.y as f64
+            && y < self.y as f64 + self.height as f64
+    }
+
+    /// Squared distance from a point to the display's rectangle (0 inside).
+    fn distance_sq(&self, x: f64, y: f64) -> f64 {
+        let dx = (self.x as f64 - x).max(x - (self.x as f64 + self.width as f64)).max(0.0);
+        let dy = (self.y as f64 - y).max(y - (self.y as f64 + self.height as f64)).max(0.0);
+        dx * dx + dy * dy
+    }
+}
+
+/// Area to capture on one display, relative to its top-left corner, in display units.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DisplayArea {
+    pub display: usize,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Webview coordinates are physical pixels (`outerPosition() + css * scaleFactor`). In
+/// logical display units they are divided by the scale factor of the window they came
+/// from, which on a mixed-DPI desktop differs per monitor.
+fn to_display_units(units: DisplayUnits, value: f64, source_scale: f64) -> f64 {
+    match units {
+        DisplayUnits::Physical => value,
+        DisplayUnits::Logical => value / source_scale.max(0.1),
+    }
+}
+
+/// Display containing a point (in display units), or the nearest one when it falls in a
+/// gap between displays.
+fn display_at(displays: &[DisplayGeometry], x: f64, y: f64) -> Option<usize> {
+    displays.iter().position(|d| d.contains(x, y)).or_else(|| {
+        (0..displays.len()).min_by(|a, b| {
+            displays[*a]
+                .distance_sq(x, y)
+                .total_cmp(&displays[*b].distance_sq(x, y))
+        })
+    })
+}
+
+/// Display under a webview point, such as the pet's center.
+pub fn display_for_point(
+    displays: &[DisplayGeometry],
+    units: DisplayUnits,
+    x: i32,
+    y: i32,
+    source_scale: f64,
+) -> Option<usize> {
+    display_at(
+        displays,
+        to_display_units(units, x as f64, source_scale),
+        to_display_units(units, y as f64, source_scale),
+    )
+}
+
+/// Map a webview region to the display under its center, clamped to that display.
+pub fn map_region(
+    displays: &[DisplayGeometry],
+    units: DisplayUnits,
+    (x, y, width, height): (i32, i32, u32, u32),
+    source_scale: f64,
+) -> Result<DisplayArea, String> {
+    if width == 0 || height == 0 {
+        return Err("Capture area is empty".to_string());
+    }
+    let left = to_display_units(units, x as f64, source_scale);
+    let top = to_display_units(units, y as f64, source_scale);
+    let right = to_display_units(units, x as f64 + width as f64, source_scale);
+    let bottom = to_display_units(units, y as f64 + height as f64, source_scale);
+
+    let index = display_at(displays, (left + right) / 2.0, (top + bottom) / 2.0)
+        .ok_or_else(|| "No display found".to_string())?;
+    let display = displays[index];
+
+    // Round outwards so a fractional logical region never loses its edge pixels
+    let display_right = display.x as f64 + display.width as f64;
+    let display_bottom = display.y as f64 + display.height as f64;
+    let left = left.floor().max(display.x as f64);
+    let top = top.floor().max(display.y as f64);
+    let right = right.ceil().min(display_right);
+    let bottom = bottom.ceil().min(display_bottom);
+    if right <= left || bottom <= top {
+        return Err("Capture area is outside of the display".to_string());
+    }
+
+    Ok(DisplayArea {
+        display: index,
+        x: (left - display.x as f64) as i32,
+        y: (top - display.y as f64) as i32,
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+    })
+}
+
+/// Position of a monitor reported by the window system (always physical) in display units,
+/// for matching it to a backend display.
+pub fn monitor_origin(units: DisplayUnits, x: i32, y: i32, monitor_scale: f64) -> (i32, i32) {
+    (
+        to_display_units(units, x as f64, monitor_scale).round() as i32,
+        to_display_units(units, y as f64, monitor_scale).round() as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(x: i32, y: i32, width: u32, height: u32) -> DisplayGeometry {
+        DisplayGeometry { x, y, width, height }
+    }
+
+    /// 4K laptop panel at 200% with a 1080p monitor at 100% to its right, as Windows
+    /// reports them (physical pixels).
+    fn windows_mixed() -> Vec<DisplayGeometry> {
+        vec![display(0, 0, 3840, 2160), display(3840, 0, 1920, 1080)]
+    }
+
+    /// The same desktop on macOS (points); the secondary monitor sits above the primary.
+    fn mac_mixed() -> Vec<DisplayGeometry> {
+        vec![display(0, 0, 1920, 1080), display(-200, -1080, 1920, 1080)]
+    }
+
+    #[test]
+    fn physical_region_maps_to_the_display_under_its_center() {
+        let area = map_region(&windows_mixed(), DisplayUnits::Physical, (4000, 100, 400, 300), 1.0)
+            .unwrap();
+        assert_eq!(area, DisplayArea { display: 1, x: 160, y: 100, width: 400, height: 300 });
+
+        // Selection on the HiDPI panel: physical pixels pass through unscaled
+        let area = map_region(&windows_mixed(), DisplayUnits::Physical, (200, 200, 800, 600), 2.0)
+            .unwrap();
+        assert_eq!(area, DisplayArea { display: 0, x: 200, y: 200, width: 800, height: 600 });
+    }
+
+    #[test]
+    fn logical_region_is_divided_by_the_source_window_scale() {
+        // Overlay on the Retina display: physical (200, 200) at 2x is (100, 100) points
+        let area = map_region(&mac_mixed(), DisplayUnits::Logical, (200, 200, 800, 600), 2.0)
+            .unwrap();
+        assert_eq!(area, DisplayArea { display: 0, x: 100, y: 100, width: 400, height: 300 });
+
+        // Overlay on the 1x monitor above it: negative coordinates, no scaling
+        let area = map_region(&mac_mixed(), DisplayUnits::Logical, (0, -1000, 300, 200), 1.0)
+            .unwrap();
+        assert_eq!(area, DisplayArea { display: 1, x: 200, y: 80, width: 300, height: 200 });
+    }
+
+    #[test]
+    fn fractional_logical_region_rounds_outwards() {
+        let area = map_region(&mac_mixed(), DisplayUnits::Logical, (3, 3, 5, 5), 2.0).unwrap();
+        assert_eq!(area, DisplayArea { display: 0, x: 1, y: 1, width: 3, height: 3 });
+    }
+
+    #[test]
+    fn region_crossing_displays_is_clamped_to_one() {
+        let area = map_region(&windows_mixed(), DisplayUnits::Physical, (3700, 500, 400, 200), 1.0)
+            .unwrap();
+        assert_eq!(area, DisplayArea { display: 1, x: 0, y: 500, width: 260, height: 200 });
+    }
+
+    #[test]
+    fn empty_and_offscreen_regions_are_rejected() {
+        assert!(map_region(&windows_mixed(), DisplayUnits::Physical, (0, 0, 0, 10), 1.0).is_err());
+        // Right of the 4K panel, below the 1080p monitor: nothing left after clamping
+        assert!(
+            map_region(&windows_mixed(), DisplayUnits::Physical, (4000, 1500, 100, 100), 1.0)
+                .is_err()
+        );
+        assert!(map_region(&[], DisplayUnits::Physical, (0, 0, 10, 10), 1.0).is_err());
+    }
+
+    #[test]
+    fn point_selects_containing_or_nearest_display() {
+        let displays = windows_mixed();
+        assert_eq!(display_for_point(&displays, DisplayUnits::Physical, 3900, 50, 1.0), Some(1));
+        assert_eq!(display_for_point(&displays, DisplayUnits::Physical, 3839, 2000, 2.0), Some(0));
+        // In the gap below the smaller monitor
+        assert_eq!(display_for_point(&displays, DisplayUnits::Physical, 5000, 1500, 1.0), Some(1));
+        // Retina pet at physical (3000, 1000) is (1500, 500) points
+        assert_eq!(display_for_point(&mac_mixed(), DisplayUnits::Logical, 3000, 1000, 2.0), Some(0));
+        assert_eq!(display_for_point(&[], DisplayUnits::Physical, 0, 0, 1.0), None);
+    }
+
+    #[test]
+    fn monitor_origin_matches_backend_units() {
+        assert_eq!(monitor_origin(DisplayUnits::Physical, 3840, 0, 1.0), (3840, 0));
+        assert_eq!(monitor_origin(DisplayUnits::Logical, -400, -2160, 2.0), (-200, -1080));
+    }
+}
@@ -0,0 +1,154 @@
+use image::{imageops::FilterType, Rgba, RgbaImage};
+use serde::Deserialize;
+
+/// Pixelate blocks are at least this big, so a line of text can't be read back.
+const MIN_PIXEL_BLOCK: u32 = 16;
+/// Blur shrinks the area by this factor and scales it back up.
+const BLUR_FACTOR: u32 = 12;
+const SOLID_FILL: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionMode {
+    #[default]
+    Solid,
+    Pixelate,
+    Blur,
+}
+
+/// Area to hide, in pixels of the captured image (before it is scaled for chat).
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Redaction {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub mode: RedactionMode,
+}
+
+impl Redaction {
+    /// The part of the rectangle inside the image, as `(x, y, width, height)`.
+    fn clamped(&self, image: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+        let left = self.x.max(0) as i64;
+        let top = self.y.max(0) as i64;
+        let right = (self.x as i64 + self.width as i64).min(image.width() as i64);
+        let bottom = (self.y as i64 + self.height as i64).min(image.height() as i64);
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some((left as u32, top as u32, (right - left) as u32, (bottom - top) as u32))
+    }
+}
+
+fn pixelate(image: &mut RgbaImage, (x, y, width, height): (u32, u32, u32, u32)) {
+    let block = (width.min(height) / 4).max(MIN_PIXEL_BLOCK);
+    for block_y in (y..y + height).step_by(block as usize) {
+        for block_x in (x..x + width).step_by(block as usize) {
+            let block_right = (block_x + block).min(x + width);
+            let block_bottom = (block_y + block).min(y + height);
+            let mut sum = [0u64; 4];
+            for py in block_y..block_bottom {
+                for px in block_x..block_right {
+                    for (total, channel) in sum.iter_mut().zip(image.get_pixel(px, py).0) {
+                        *total += channel as u64;
+                    }
+                }
+            }
+            let count = ((block_right - block_x) * (block_bottom - block_y)) as u64;
+            let average = Rgba(sum.map(|total| (total / count) as u8));
+            for py in block_y..block_bottom {
+                for px in block_x..block_right {
+                    image.put_pixel(px, py, average);
+                }
+            }
+        }
+    }
+}
+
+fn blur(image: &mut RgbaImage, (x, y, width, height): (u32, u32, u32, u32)) {
+    let area = image::imageops::crop_imm(image, x, y, width, height).to_image();
+    let small = image::imageops::resize(
+        &area,
+        (width / BLUR_FACTOR).max(1),
+        (height / BLUR_FACTOR).max(1),
+        FilterType::Triangle,
+    );
+    let smeared = image::imageops::resize(&small, width, height, FilterType::Triangle);
+    image::imageops::replace(image, &smeared, x as i64, y as i64);
+}
+
+/// Hide the redacted areas in place. This runs on the raw capture, before encoding, so
+/// the original pixels never reach the webview, history or the gateway.
+pub fn apply(image: &mut RgbaImage, redactions: &[Redaction]) {
+    for redaction in redactions {
+        let Some(area) = redaction.clamped(image) else {
+            continue;
+        };
+        match redaction.mode {
+            RedactionMode::Solid => {
+                let (x, y, width, height) = area;
+                for py in y..y + height {
+                    for px in x..x + width {
+                        image.put_pixel(px, py, SOLID_FILL);
+                    }
+                }
+            }
+            RedactionMode::Pixelate => pixelate(image, area),
+            RedactionMode::Blur => blur(image, area),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vertical stripes, one pixel wide, like small text.
+    fn stripes() -> RgbaImage {
+        RgbaImage::from_fn(200, 100, |x, _| {
+            if x % 2 == 0 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        })
+    }
+
+    fn redaction(x: i32, y: i32, width: u32, height: u32, mode: RedactionMode) -> Redaction {
+        Redaction { x, y, width, height, mode }
+    }
+
+    #[test]
+    fn every_mode_removes_the_detail_and_leaves_the_rest() {
+        for mode in [RedactionMode::Solid, RedactionMode::Pixelate, RedactionMode::Blur] {
+            let mut image = stripes();
+            apply(&mut image, &[redaction(40, 20, 64, 48, mode)]);
+            // No two neighbouring pixels inside differ like the stripes did
+            for y in 20..68 {
+                for x in 40..103 {
+                    let (a, b) = (image.get_pixel(x, y).0[0], image.get_pixel(x + 1, y).0[0]);
+                    assert!(a.abs_diff(b) < 64, "{mode:?} kept detail at {x},{y}");
+                }
+            }
+            assert_eq!(image.get_pixel(10, 10), &Rgba([255, 255, 255, 255]));
+            assert_eq!(image.get_pixel(150, 90), &Rgba([255, 255, 255, 255]));
+        }
+    }
+
+    #[test]
+    fn redactions_are_clamped_to_the_image() {
+        let mut image = stripes();
+        apply(
+            &mut image,
+            &[
+                redaction(-50, -50, 100, 100, RedactionMode::Solid),
+                redaction(500, 500, 10, 10, RedactionMode::Blur),
+            ],
+        );
+        assert_eq!(image.get_pixel(0, 0), &SOLID_FILL);
+        assert_eq!(image.get_pixel(49, 49), &SOLID_FILL);
+        assert_eq!(image.get_pixel(50, 50), &Rgba([255, 255, 255, 255]));
+    }
+}
@@ -0,0 +1,385 @@
+use crate::{capture_backend, capture_visibility, image_pipeline, obsidian_clipper};
+use image::RgbaImage;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use tauri::menu::{MenuItem, Submenu};
+use tauri::{AppHandle, Manager, Wry};
+
+const DEFAULT_TEMPLATE: &str = "{date}_{time}_{kind}";
+const MAX_NAME_CHARS: usize = 120;
+const RECENT_LIMIT: usize = 10;
+const JPEG_QUALITY: u8 = 90;
+/// Names of the files ClawPet saved in the capture folder, one per line, so a panic wipe
+/// deletes those and nothing else the user keeps there.
+const MANIFEST_NAME: &str = ".clawpet-captures";
+/// Tray menu ids: the submenu's items are `recent-capture:<index>`.
+pub const RECENT_MENU_PREFIX: &str = "recent-capture:";
+pub const REVEAL_LAST_MENU_ID: &str = "reveal-last-capture";
+
+static RECENT: LazyLock<Mutex<VecDeque<PathBuf>>> = LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SaveFormat {
+    Png,
+    Jpeg,
+}
+
+pub struct SaveConfig {
+    pub dir: PathBuf,
+    pub template: String,
+    pub format: SaveFormat,
+}
+
+impl SaveConfig {
+    /// `CLAWPET_CAPTURE_SAVE=true` or a `CLAWPET_CAPTURE_DIR` turns saving on;
+    /// `CLAWPET_CAPTURE_NAME` is the file name template and `CLAWPET_CAPTURE_FORMAT` is
+    /// `png` (default) or `jpeg`.
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("CLAWPET_CAPTURE_DIR")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        let enabled = std::env::var("CLAWPET_CAPTURE_SAVE")
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+            .unwrap_or(dir.is_some());
+        if !enabled {
+            return None;
+        }
+        let dir = dir.map(PathBuf::from).unwrap_or_else(default_dir);
+        let template = std::env::var("CLAWPET_CAPTURE_NAME")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string());
+        let format = match std::env::var("CLAWPET_CAPTURE_FORMAT")
+            .map(|v| v.trim().to_lowercase())
+            .as_deref()
+        {
+            Ok("jpg") | Ok("jpeg") => SaveFormat::Jpeg,
+            _ => SaveFormat::Png,
+        };
+        Some(Self { dir, template, format })
+    }
+
+    /// Whether naming a file needs the frontmost window's app and title.
+    fn needs_window(&self) -> bool {
+        self.template.contains("{app}") || self.template.contains("{title}")
+    }
+}
+
+/// Folder captures go to unless `CLAWPET_CAPTURE_DIR` says otherwise.
+fn default_dir() -> PathBuf {
+    dirs::picture_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join("Pictures")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("ClawPet")
+}
+
+/// What a capture showed, for its file name.
+#[derive(Default, Clone, Debug)]
+pub struct CaptureContext {
+    /// `region`, `display`, `window`, `all` or `browser`.
+    pub kind: &'static str,
+    pub display: Option<String>,
+    pub app: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Keep a template value usable in a file name on every OS.
+fn sanitize(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Fill `{date}`, `{time}`, `{kind}`, `{display}`, `{app}` and `{title}` in the template.
+pub fn file_name(template: &str, context: &CaptureContext, now: chrono::DateTime<chrono::Local>) -> String {
+    let name = template
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{time}", &now.format("%H-%M-%S").to_string())
+        .replace("{kind}", context.kind)
+        .replace("{display}", context.display.as_deref().unwrap_or(""))
+        .replace("{app}", context.app.as_deref().unwrap_or(""))
+        .replace("{title}", context.title.as_deref().unwrap_or(""));
+    let name: String = sanitize(&name).chars().take(MAX_NAME_CHARS).collect();
+    // Values left empty can leave separators dangling
+    let name = name.trim_matches(|c: char| c == '_' || c == '-' || c == '.' || c.is_whitespace());
+    if name.is_empty() {
+        "capture".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn write_image(image: RgbaImage, path: &Path, format: SaveFormat) -> Result<(), String> {
+    let image = image::DynamicImage::ImageRgba8(image);
+    let bytes = match format {
+        SaveFormat::Png => image_pipeline::encode_png(&image)?,
+        SaveFormat::Jpeg => image_pipeline::encode_jpeg(&image, JPEG_QUALITY)?,
+    };
+    std::fs::write(path, bytes).map_err(|e| format!("Failed to save capture: {e}"))
+}
+
+fn record(dir: &Path, path: &Path) {
+    if let Some(name) = path.file_name() {
+        let manifest = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(MANIFEST_NAME));
+        if let Ok(mut manifest) = manifest {
+            let _ = writeln!(manifest, "{}", name.to_string_lossy());
+        }
+    }
+    let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    recent.retain(|p| p != path);
+    recent.push_front(path.to_path_buf());
+    recent.truncate(RECENT_LIMIT);
+}
+
+/// Save a finished capture when saving is on. Encoding and writing happen on a blocking
+/// thread so the chat attachment isn't held up; failures are logged, never returned.
+pub fn save(app: &AppHandle, image: &RgbaImage, mut context: CaptureContext) {
+    let Some(config) = SaveConfig::from_env() else {
+        return;
+    };
+    // Resolved now, while the captured window is still the frontmost one
+    if config.needs_window() && context.app.is_none() {
+        let is_own = capture_visibility::is_own_window(app);
+        if let Ok(window) = capture_backend::active_window(&*capture_backend::active(), is_own) {
+            context.app = Some(window.app_name);
+            context.title = context.title.or(Some(window.title));
+        }
+    }
+
+    let app = app.clone();
+    let image = image.clone();
+    tauri::async_runtime::spawn_blocking(move || match write_capture(&config, image, &context) {
+        Ok(path) => {
+            eprintln!("[capture] Saved {}", path.display());
+            record(&config.dir, &path);
+            refresh_tray(&app);
+        }
+        Err(e) => eprintln!("[capture] {}", e),
+    });
+}
+
+fn write_capture(config: &SaveConfig, image: RgbaImage, context: &CaptureContext) -> Result<PathBuf, String> {
+    let extension = match config.format {
+        SaveFormat::Png => "png",
+        SaveFormat::Jpeg => "jpg",
+    };
+    std::fs::create_dir_all(&config.dir).map_err(|e| format!("Failed to create capture folder: {e}"))?;
+    let name = file_name(&config.template, context, chrono::Local::now());
+    let path = obsidian_clipper::unique_path(&config.dir, &name, extension);
+    write_image(image, &path, config.format)?;
+    Ok(path)
+}
+
+pub fn recent() -> Vec<PathBuf> {
+    RECENT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .filter(|path| path.exists())
+        .cloned()
+        .collect()
+}
+
+/// Show a file selected in the system file manager (its folder on Linux).
+pub fn reveal(path: &Path) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    let result = std::process::Command::new("explorer")
+        .arg(format!("/select,{}", path.display()))
+        .spawn();
+    #[cfg(target_os = "macos")]
+    let result = std::process::Command::new("open").arg("-R").arg(path).spawn();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = std::process::Command::new("xdg-open")
+        .arg(path.parent().unwrap_or(path))
+        .spawn();
+    result
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open file manager: {e}"))
+}
+
+/// Delete the captures ClawPet saved (listed in the folder's manifest) for a panic wipe.
+pub fn wipe(app: &AppHandle) -> Result<(), String> {
+    RECENT.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    refresh_tray(app);
+
+    match SaveConfig::from_env() {
+        Some(config) => delete_listed(&config.dir),
+        None => Ok(()),
+    }
+}
+
+/// Remove the files named in `dir`'s manifest, then the manifest itself.
+fn delete_listed(dir: &Path) -> Result<(), String> {
+    let manifest = dir.join(MANIFEST_NAME);
+    let Ok(names) = std::fs::read_to_string(&manifest) else {
+        return Ok(());
+    };
+    for name in names.lines().map(str::trim).filter(|name| !name.is_empty()) {
+        // Only plain file names are ever written; skip anything that would leave the folder
+        if Path::new(name).components().count() != 1 {
+            continue;
+        }
+        let path = dir.join(name);
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", name, e))?;
+        }
+    }
+    std::fs::remove_file(&manifest).map_err(|e| format!("Failed to delete capture list: {e}"))
+}
+
+// ---------- Tray ----------
+
+/// "Recent captures" submenu of the tray icon, kept in app state to refresh after saving.
+pub struct RecentCapturesMenu(pub Submenu<Wry>);
+
+pub fn tray_submenu(app: &AppHandle) -> tauri::Result<Submenu<Wry>> {
+    let submenu = Submenu::with_id(app, "recent-captures", "Recent captures", false)?;
+    app.manage(RecentCapturesMenu(submenu.clone()));
+    Ok(submenu)
+}
+
+fn refresh_tray(app: &AppHandle) {
+    let Some(menu) = app.try_state::<RecentCapturesMenu>() else {
+        return;
+    };
+    let submenu = &menu.inner().0;
+    while let Ok(Some(_)) = submenu.remove_at(0) {}
+
+    let recent = recent();
+    let _ = submenu.set_enabled(!recent.is_empty());
+    if recent.is_empty() {
+        return;
+    }
+    if let Ok(item) = MenuItem::with_id(app, REVEAL_LAST_MENU_ID, "Show last capture", true, None::<&str>) {
+        let _ = submenu.append(&item);
+    }
+    for (index, path) in recent.iter().enumerate() {
+        let label = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let id = format!("{}{}", RECENT_MENU_PREFIX, index);
+        if let Ok(item) = MenuItem::with_id(app, id, label, true, None::<&str>) {
+            let _ = submenu.append(&item);
+        }
+    }
+}
+
+/// Tray menu clicks on the recent captures submenu.
+pub fn handle_tray_event(id: &str) {
+    let path = if id == REVEAL_LAST_MENU_ID {
+        recent().into_iter().next()
+    } else if let Some(index) = id.strip_prefix(RECENT_MENU_PREFIX) {
+        index.parse::<usize>().ok().and_then(|i| recent().into_iter().nth(i))
+    } else {
+        return;
+    };
+    if let Some(path) = path {
+        if let Err(e) = reveal(&path) {
+            eprintln!("[capture] {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn reveal_last_capture() -> Result<String, String> {
+    let path = recent()
+        .into_iter()
+        .next()
+        .ok_or_else(|| "No capture saved yet".to_string())?;
+    reveal(&path)?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn templates_fill_in_and_stay_valid_file_names() {
+        let now = chrono::Local.with_ymd_and_hms(2026, 3, 7, 9, 5, 30).unwrap();
+        let context = CaptureContext {
+            kind: "window",
+            display: None,
+            app: Some("Code".into()),
+            title: Some("lib.rs — src/capture: main?".into()),
+        };
+        assert_eq!(file_name(DEFAULT_TEMPLATE, &context, now), "2026-03-07_09-05-30_window");
+        assert_eq!(file_name("{app}_{title}", &context, now), "Code_lib.rs — src capture main");
+        // Missing values don't leave separators behind, and an empty name still gets one
+        assert_eq!(file_name("{display}_{kind}", &context, now), "window");
+        assert_eq!(file_name("{display}", &context, now), "capture");
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clawpet-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn saved_captures_are_listed_and_recent_keeps_the_newest() {
+        let dir = temp_dir("capture-record");
+        let paths: Vec<PathBuf> = (0..RECENT_LIMIT + 2)
+            .map(|i| dir.join(format!("capture-{i}.png")))
+            .collect();
+        for path in &paths {
+            record(&dir, path);
+        }
+        let manifest = std::fs::read_to_string(dir.join(MANIFEST_NAME)).unwrap();
+        let expected: Vec<String> = (0..RECENT_LIMIT + 2).map(|i| format!("capture-{i}.png")).collect();
+        assert_eq!(manifest.lines().collect::<Vec<_>>(), expected);
+
+        // Newest first, capped, and saving a path again moves it up instead of repeating it
+        let snapshot = || RECENT.lock().unwrap().iter().cloned().collect::<Vec<_>>();
+        let recent = snapshot();
+        assert_eq!(recent.len(), RECENT_LIMIT);
+        assert_eq!(recent[0], paths[RECENT_LIMIT + 1]);
+        assert_eq!(recent[RECENT_LIMIT - 1], paths[2]);
+        record(&dir, &paths[5]);
+        let recent = snapshot();
+        assert_eq!(recent.len(), RECENT_LIMIT);
+        assert_eq!(recent[0], paths[5]);
+        assert_eq!(recent.iter().filter(|p| **p == paths[5]).count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wipe_deletes_only_the_listed_captures() {
+        let dir = temp_dir("capture-wipe");
+        let outside = dir.with_extension("keep");
+        std::fs::write(&outside, b"keep").unwrap();
+        for name in ["a.png", "b.jpg", "mine.png"] {
+            std::fs::write(dir.join(name), b"x").unwrap();
+        }
+        let escape = format!("../{}", outside.file_name().unwrap().to_string_lossy());
+        std::fs::write(dir.join(MANIFEST_NAME), format!("a.png\n\nb.jpg\ngone.png\n{escape}\n")).unwrap();
+
+        delete_listed(&dir).unwrap();
+        assert!(!dir.join("a.png").exists());
+        assert!(!dir.join("b.jpg").exists());
+        assert!(!dir.join(MANIFEST_NAME).exists());
+        assert!(dir.join("mine.png").exists());
+        assert!(outside.exists());
+        // Nothing saved yet is not an error
+        delete_listed(&dir).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_file(&outside).unwrap();
+    }
+}
@@ -0,0 +1,91 @@
+use crate::capture_backend;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+/// Time for the compositor to repaint the desktop after our windows are hidden.
+const DEFAULT_REPAINT_DELAY_MS: u64 = 150;
+
+/// `CLAWPET_CAPTURE_HIDE_WINDOWS=false` keeps the pet and its windows in captures unless a
+/// capture asks otherwise. On by default.
+pub fn hide_by_default() -> bool {
+    std::env::var("CLAWPET_CAPTURE_HIDE_WINDOWS")
+        .map(|v| !matches!(v.trim().to_lowercase().as_str(), "0" | "false" | "no" | "off"))
+        .unwrap_or(true)
+}
+
+/// `CLAWPET_CAPTURE_HIDE_DELAY_MS`: wait after hiding before capturing.
+fn repaint_delay() -> Duration {
+    let ms = std::env::var("CLAWPET_CAPTURE_HIDE_DELAY_MS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_REPAINT_DELAY_MS);
+    Duration::from_millis(ms.min(2000))
+}
+
+/// ClawPet windows hidden for a capture. They are shown again, and focus handed back,
+/// when this is dropped, so an early `?` return can't leave the pet invisible.
+pub struct HiddenWindows {
+    windows: Vec<WebviewWindow>,
+    focused: Option<WebviewWindow>,
+}
+
+impl Drop for HiddenWindows {
+    fn drop(&mut self) {
+        for window in &self.windows {
+            if let Err(e) = window.show() {
+                eprintln!("[capture] Failed to show window {}: {}", window.label(), e);
+            }
+        }
+        if let Some(window) = &self.focused {
+            let _ = window.set_focus();
+        }
+    }
+}
+
+/// Hide every visible ClawPet window (`hide` falls back to `CLAWPET_CAPTURE_HIDE_WINDOWS`)
+/// and wait for the desktop behind them to repaint.
+pub async fn hide_for_capture(app: &AppHandle, hide: Option<bool>) -> HiddenWindows {
+    let mut hidden = HiddenWindows {
+        windows: Vec::new(),
+        focused: None,
+    };
+    if !hide.unwrap_or_else(hide_by_default) {
+        return hidden;
+    }
+
+    for window in app.webview_windows().into_values() {
+        if !window.is_visible().unwrap_or(false) {
+            continue;
+        }
+        if window.is_focused().unwrap_or(false) {
+            hidden.focused = Some(window.clone());
+        }
+        match window.hide() {
+            Ok(()) => hidden.windows.push(window),
+            Err(e) => eprintln!("[capture] Failed to hide window {}: {}", window.label(), e),
+        }
+    }
+    if !hidden.windows.is_empty() {
+        tokio::time::sleep(repaint_delay()).await;
+    }
+    hidden
+}
+
+/// ClawPet's own windows, matched by title or by the app's executable name.
+pub fn is_own_window(app: &tauri::AppHandle) -> impl Fn(&capture_backend::Window) -> bool {
+    let titles: Vec<String> = app
+        .webview_windows()
+        .values()
+        .filter_map(|window| window.title().ok())
+        .filter(|title| !title.is_empty())
+        .collect();
+    let exe = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_lowercase()));
+    move |window| {
+        titles.contains(&window.title)
+            || exe
+                .as_deref()
+                .is_some_and(|exe| window.app_name.to_lowercase() == exe)
+    }
+}
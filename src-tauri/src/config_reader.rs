@@ -100,6 +100,26 @@ fn parse_openclaw_config(path: &Path) -> Result<ParsedGatewayConfig, String> {
     })
 }
 
+fn parse_openclaw_default_agent(path: &Path) -> Result<Option<String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("failed to read config: {}", e))?;
+    let json: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| format!("failed to parse config: {}", e))?;
+
+    let from_list = json
+        .pointer("/agents/list")
+        .and_then(|v| v.as_array())
+        .and_then(|list| {
+            list.iter()
+                .find(|entry| entry.get("default").and_then(|v| v.as_bool()) == Some(true))
+                .or_else(|| list.first())
+                .and_then(|entry| entry.get("id"))
+                .and_then(|v| v.as_str())
+                .and_then(normalize_non_empty)
+        });
+
+    Ok(from_list)
+}
+
 fn parse_openclaw_workspace(path: &Path) -> Result<Option<String>, String> {
     let content = fs::read_to_string(path).map_err(|e| format!("failed to read config: {}", e))?;
     let json: serde_json::Value =
@@ -177,7 +197,7 @@ fn push_unique(paths: &mut Vec<PathBuf>, candidate: PathBuf) {
     }
 }
 
-fn push_env_path_list(paths: &mut Vec<PathBuf>, var_name: &str) {
+pub(crate) fn push_env_path_list(paths: &mut Vec<PathBuf>, var_name: &str) {
     let Ok(raw) = env::var(var_name) else {
         return;
     };
@@ -432,6 +452,37 @@ fn candidate_identity_paths() -> Vec<PathBuf> {
     paths
 }
 
+/// First discovered `openclaw.json` that parses as a gateway config.
+pub(crate) fn discover_config_path() -> Option<PathBuf> {
+    candidate_config_paths()
+        .into_iter()
+        .find(|path| path.exists() && parse_openclaw_config(path).is_ok())
+}
+
+/// OpenClaw state directory: `OPENCLAW_STATE_DIR`, else the folder holding the discovered config.
+pub(crate) fn discover_state_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var("OPENCLAW_STATE_DIR")
+        .ok()
+        .and_then(|s| normalize_non_empty(&s))
+    {
+        return Some(PathBuf::from(dir));
+    }
+
+    discover_config_path().and_then(|path| path.parent().map(Path::to_path_buf))
+}
+
+/// Id of the default agent (`default: true` in `agents.list`, else the first entry).
+pub(crate) fn discover_default_agent_id() -> Option<String> {
+    if let Some(id) = env::var("OPENCLAW_AGENT_ID")
+        .ok()
+        .and_then(|s| normalize_non_empty(&s))
+    {
+        return Some(id);
+    }
+
+    discover_config_path().and_then(|path| parse_openclaw_default_agent(&path).ok().flatten())
+}
+
 fn parse_identity_name(content: &str) -> Option<String> {
     for line in content.lines() {
         let trimmed = line.trim();
@@ -0,0 +1,508 @@
+use crate::secret_scan::{self, SecretFinding};
+use crate::text_file;
+use regex::Regex;
+use serde::Serialize;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::LazyLock;
+
+pub const MAX_DOCUMENT_BYTES: u64 = 50 * 1024 * 1024; // 50MB
+/// Characters of extracted text sent to the agent; the rest is cut with a note.
+const MAX_DOCUMENT_CHARS: usize = 60_000;
+/// Cap on the decompressed `word/document.xml`, so a zip bomb can't exhaust memory.
+const MAX_DOCX_XML_BYTES: u64 = 64 * 1024 * 1024;
+const CSV_PREVIEW_ROWS: usize = 50;
+const CSV_CELL_CHARS: usize = 60;
+/// PDFs with more pages than this (or without a text layer) get a first-page preview.
+const LARGE_PDF_PAGES: usize = 5;
+
+static RE_XML_TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<(/?)([A-Za-z0-9:]+)([^>]*?)(/?)>|([^<]+)").unwrap());
+static RE_XML_ENTITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"&(#x[0-9A-Fa-f]+|#[0-9]+|amp|lt|gt|quot|apos);").unwrap());
+
+// ---------- Data types ----------
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DocumentKind {
+    Pdf,
+    Docx,
+    Text,
+    Csv,
+}
+
+impl DocumentKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            DocumentKind::Pdf => "pdf",
+            DocumentKind::Docx => "docx",
+            DocumentKind::Text => "text",
+            DocumentKind::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentText {
+    pub file_name: String,
+    pub kind: &'static str,
+    /// Extracted text with page/sheet markers, already cut to the size budget.
+    pub text: String,
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_count: Option<u32>,
+    /// Characters before truncation.
+    pub total_chars: usize,
+    /// Credentials that were masked. Line numbers count within each page or sheet.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<SecretFinding>,
+}
+
+pub struct ExtractedDocument {
+    pub text: DocumentText,
+    /// Set for PDFs that should get a first-page raster next to the text.
+    pub wants_preview: bool,
+}
+
+/// Document type from the extension, confirmed by the signature for binary formats.
+pub fn detect_kind(path: &Path, bytes: &[u8]) -> Option<DocumentKind> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "pdf" if bytes.starts_with(b"%PDF-") => Some(DocumentKind::Pdf),
+        "docx" if bytes.starts_with(b"PK\x03\x04") => Some(DocumentKind::Docx),
+        "csv" | "tsv" => Some(DocumentKind::Csv),
+        "txt" | "md" | "markdown" | "text" => Some(DocumentKind::Text),
+        _ if bytes.starts_with(b"%PDF-") => Some(DocumentKind::Pdf),
+        _ => None,
+    }
+}
+
+// ---------- Size budget ----------
+
+/// Join `(marker, text)` sections and cut the result at `MAX_DOCUMENT_CHARS`, noting how
+/// much was left out.
+fn fit_budget(sections: &[(String, String)], unit: &str) -> (String, bool, usize) {
+    // A marker takes its own line; every section ends with a blank line
+    let marker_len = |marker: &String| if marker.is_empty() { 0 } else { marker.chars().count() + 1 };
+    let section_len = |(marker, text): &(String, String)| marker_len(marker) + text.chars().count() + 2;
+    let total_chars: usize = sections.iter().map(section_len).sum();
+
+    let mut out = String::new();
+    let mut used = 0;
+    for (index, section) in sections.iter().enumerate() {
+        let (marker, text) = section;
+        let remaining = MAX_DOCUMENT_CHARS.saturating_sub(used);
+        if section_len(section) > remaining {
+            // Show the start of the section that doesn't fit, unless only a sliver is left
+            let room = remaining.saturating_sub(marker_len(marker) + 1);
+            let partial = room >= 200;
+            if partial {
+                if !marker.is_empty() {
+                    out.push_str(marker);
+                    out.push('\n');
+                }
+                out.extend(text.chars().take(room));
+                out.push('\n');
+            }
+            let shown = index + usize::from(partial);
+            let note = if sections.len() > 1 {
+                format!(
+                    "\n[Truncated: {} {} of {} shown, {} of {} characters]",
+                    unit,
+                    if shown <= 1 { "1".to_string() } else { format!("1-{}", shown) },
+                    sections.len(),
+                    out.chars().count(),
+                    total_chars
+                )
+            } else {
+                format!(
+                    "\n[Truncated: first {} of {} characters shown]",
+                    out.chars().count(),
+                    total_chars
+                )
+            };
+            out.push_str(&note);
+            return (out, true, total_chars);
+        }
+        if !marker.is_empty() {
+            out.push_str(marker);
+            out.push('\n');
+        }
+        out.push_str(text.trim_end());
+        out.push_str("\n\n");
+        used += section_len(section);
+    }
+    (out.trim_end().to_string(), false, total_chars)
+}
+
+// ---------- PDF ----------
+
+fn extract_pdf(bytes: &[u8]) -> Result<Vec<String>, String> {
+    // pdf-extract panics on some malformed files instead of returning an error
+    let bytes = bytes.to_vec();
+    std::panic::catch_unwind(move || pdf_extract::extract_text_from_mem_by_pages(&bytes))
+        .map_err(|_| "Failed to read PDF: unsupported structure".to_string())?
+        .map_err(|e| format!("Failed to read PDF: {}", e))
+}
+
+// ---------- DOCX ----------
+
+fn unescape_xml(text: &str) -> String {
+    RE_XML_ENTITY
+        .replace_all(text, |caps: &regex::Captures| {
+            let entity = &caps[1];
+            let decoded = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16)
+                    .ok()
+                    .and_then(char::from_u32),
+                _ => entity[1..].parse().ok().and_then(char::from_u32),
+            };
+            decoded.map_or_else(|| caps[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+fn extract_docx(bytes: &[u8]) -> Result<Vec<String>, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| format!("Failed to read DOCX: {}", e))?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")
+        .map_err(|e| format!("Failed to read DOCX: {}", e))?
+        .take(MAX_DOCX_XML_BYTES)
+        .read_to_string(&mut xml)
+        .map_err(|e| format!("Failed to read DOCX: {}", e))?;
+    Ok(docx_pages(&xml))
+}
+
+/// Paragraph text from `word/document.xml`. Explicit page breaks are the only page
+/// information a DOCX carries.
+fn docx_pages(xml: &str) -> Vec<String> {
+    let mut pages = vec![String::new()];
+    let mut in_text = false;
+    for caps in RE_XML_TOKEN.captures_iter(xml) {
+        let current = pages.last_mut().expect("pages is never empty");
+        if let Some(text) = caps.get(5) {
+            if in_text {
+                current.push_str(&unescape_xml(text.as_str()));
+            }
+            continue;
+        }
+        let closing = &caps[1] == "/";
+        let self_closing = &caps[4] == "/";
+        match (&caps[2], closing) {
+            ("w:t", false) => in_text = !self_closing,
+            ("w:t", true) => in_text = false,
+            ("w:tab", false) => current.push('\t'),
+            ("w:br", false) if caps[3].contains("w:type=\"page\"") => pages.push(String::new()),
+            ("w:br", false) | ("w:cr", false) | ("w:p", true) => current.push('\n'),
+            ("w:tc", true) => current.push('\t'),
+            _ => {}
+        }
+    }
+    pages
+}
+
+// ---------- Text / CSV ----------
+
+/// RFC 4180 rows: quoted fields may contain delimiters, doubled quotes and newlines.
+fn parse_csv(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ if c == delimiter => row.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+fn detect_delimiter(text: &str, path: &Path) -> char {
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("tsv")) {
+        return '\t';
+    }
+    let header = text.lines().next().unwrap_or("");
+    [',', ';', '\t', '|']
+        .into_iter()
+        .max_by_key(|d| header.matches(*d).count())
+        .filter(|d| header.contains(*d))
+        .unwrap_or(',')
+}
+
+fn csv_cell(value: &str) -> String {
+    let flat = value.replace(['\r', '\n'], " ").replace('|', "\\|");
+    if flat.chars().count() > CSV_CELL_CHARS {
+        format!("{}…", flat.chars().take(CSV_CELL_CHARS).collect::<String>())
+    } else {
+        flat
+    }
+}
+
+/// Markdown table of the first rows, with the full shape noted above it.
+fn csv_preview(text: &str, path: &Path, name: &str) -> String {
+    let rows = parse_csv(text, detect_delimiter(text, path));
+    let columns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let data_rows = rows.len().saturating_sub(1);
+
+    let mut out = format!(
+        "--- Sheet: {} ({} rows × {} columns) ---\n",
+        name, data_rows, columns
+    );
+    let Some(header) = rows.first() else {
+        return out;
+    };
+    let line = |cells: &[String]| {
+        let mut cells: Vec<String> = cells.iter().map(|c| csv_cell(c)).collect();
+        cells.resize(columns, String::new());
+        format!("| {} |\n", cells.join(" | "))
+    };
+    out.push_str(&line(header));
+    out.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+    for row in rows.iter().skip(1).take(CSV_PREVIEW_ROWS) {
+        out.push_str(&line(row));
+    }
+    if data_rows > CSV_PREVIEW_ROWS {
+        out.push_str(&format!(
+            "\n[Preview: first {} of {} rows shown]",
+            CSV_PREVIEW_ROWS, data_rows
+        ));
+    }
+    out
+}
+
+// ---------- Entry point ----------
+
+pub fn extract(path: &Path, bytes: &[u8]) -> Result<ExtractedDocument, String> {
+    let kind = detect_kind(path, bytes).ok_or_else(|| "Not a supported document".to_string())?;
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "document".to_string());
+
+    let (mut sections, unit, page_count) = match kind {
+        DocumentKind::Pdf => {
+            let pages = extract_pdf(bytes)?;
+            let count = pages.len();
+            let sections: Vec<(String, String)> = pages
+                .into_iter()
+                .enumerate()
+                .map(|(i, text)| (format!("--- Page {} ---", i + 1), text))
+                .collect();
+            (sections, "pages", Some(count as u32))
+        }
+        DocumentKind::Docx => {
+            let pages = extract_docx(bytes)?;
+            let count = pages.len();
+            let sections = pages
+                .into_iter()
+                .enumerate()
+                .map(|(i, text)| {
+                    let marker = if count > 1 { format!("--- Page {} ---", i + 1) } else { String::new() };
+                    (marker, text)
+                })
+                .collect();
+            (sections, "pages", (count > 1).then_some(count as u32))
+        }
+        DocumentKind::Text => (vec![(String::new(), text_file::decode(bytes)?.0)], "sections", None),
+        DocumentKind::Csv => {
+            let (text, _) = text_file::decode(bytes)?;
+            (vec![(String::new(), csv_preview(&text, path, &file_name))], "sheets", None)
+        }
+    };
+
+    // Mask credentials before anything is cut, so every finding is reported
+    let mut secrets = Vec::new();
+    for (_, text) in sections.iter_mut() {
+        let (masked, found) = secret_scan::mask_secrets(text);
+        *text = masked;
+        secrets.extend(found);
+    }
+
+    let (text, truncated, total_chars) = fit_budget(&sections, unit);
+    let has_text = sections.iter().any(|(_, t)| !t.trim().is_empty());
+    let wants_preview = kind == DocumentKind::Pdf
+        && (!has_text || page_count.is_some_and(|n| n as usize > LARGE_PDF_PAGES));
+
+    Ok(ExtractedDocument {
+        text: DocumentText {
+            file_name,
+            kind: kind.as_str(),
+            text,
+            truncated,
+            page_count,
+            total_chars,
+            secrets,
+        },
+        wants_preview,
+    })
+}
+
+// ---------- PDF preview ----------
+
+/// PDFium is loaded at runtime and is optional: `CLAWPET_PDFIUM_LIB` points at the library,
+/// otherwise one next to the executable or on the system path is used. Without it PDFs
+/// are sent as text only.
+static PDFIUM: LazyLock<Option<pdfium_render::prelude::Pdfium>> = LazyLock::new(|| {
+    use pdfium_render::prelude::Pdfium;
+    let bindings = match std::env::var("CLAWPET_PDFIUM_LIB") {
+        Ok(path) if !path.trim().is_empty() => Pdfium::bind_to_library(path.trim()),
+        _ => {
+            let beside_exe = std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(Path::to_path_buf))
+                .map(|dir| Pdfium::pdfium_platform_library_name_at_path(&dir));
+            beside_exe
+                .map_or_else(Pdfium::bind_to_system_library, Pdfium::bind_to_library)
+                .or_else(|_| Pdfium::bind_to_system_library())
+        }
+    };
+    match bindings {
+        Ok(bindings) => Some(Pdfium::new(bindings)),
+        Err(e) => {
+            eprintln!("[documents] PDF previews disabled, PDFium not available: {}", e);
+            None
+        }
+    }
+});
+
+/// First page rendered to pixels, or `None` when PDFium isn't installed.
+pub fn render_first_page(bytes: &[u8]) -> Result<Option<image::RgbaImage>, String> {
+    use pdfium_render::prelude::PdfRenderConfig;
+    let Some(pdfium) = PDFIUM.as_ref() else {
+        return Ok(None);
+    };
+    let document = pdfium
+        .load_pdf_from_byte_slice(bytes, None)
+        .map_err(|e| format!("Failed to render PDF: {}", e))?;
+    let page = document
+        .pages()
+        .first()
+        .map_err(|e| format!("Failed to render PDF: {}", e))?;
+    let config = PdfRenderConfig::new()
+        .set_target_width(1600)
+        .set_maximum_height(2200);
+    let rendered = page
+        .render_with_config(&config)
+        .map_err(|e| format!("Failed to render PDF: {}", e))?;
+    Ok(Some(rendered.as_image().to_rgba8()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(marker: &str, chars: usize) -> (String, String) {
+        (marker.to_string(), "x".repeat(chars))
+    }
+
+    #[test]
+    fn sections_within_budget_are_joined_unchanged() {
+        let sections = [section("--- Page 1 ---", 10), section("--- Page 2 ---", 5)];
+        let (text, truncated, total) = fit_budget(&sections, "pages");
+        assert_eq!(text, format!("--- Page 1 ---\n{}\n\n--- Page 2 ---\n{}", "x".repeat(10), "x".repeat(5)));
+        assert!(!truncated);
+        assert_eq!(total, 15 + 10 + 2 + 15 + 5 + 2);
+    }
+
+    #[test]
+    fn oversized_documents_are_cut_with_a_note() {
+        let sections = [
+            section("--- Page 1 ---", 40_000),
+            section("--- Page 2 ---", 40_000),
+            section("--- Page 3 ---", 40_000),
+        ];
+        let (text, truncated, total) = fit_budget(&sections, "pages");
+        assert!(truncated);
+        assert_eq!(total, 3 * (15 + 40_000 + 2));
+        assert!(text.contains("--- Page 2 ---") && !text.contains("--- Page 3 ---"));
+        let (body, note) = text.rsplit_once("\n[Truncated: ").unwrap();
+        assert!(body.chars().count() <= MAX_DOCUMENT_CHARS);
+        assert_eq!(note, format!("pages 1-2 of 3 shown, {} of {} characters]", body.chars().count(), total));
+
+        // A single section is cut inside its text
+        let (text, truncated, _) = fit_budget(&[section("", 70_000)], "sections");
+        assert!(truncated);
+        assert!(text.ends_with(&format!("[Truncated: first {} of 70002 characters shown]", MAX_DOCUMENT_CHARS)));
+
+        // Too little room left for the next section: it is skipped, not shown as a sliver
+        let sections = [section("", MAX_DOCUMENT_CHARS - 100), section("--- Page 2 ---", 500)];
+        let (text, _, _) = fit_budget(&sections, "pages");
+        assert!(text.contains("pages 1 of 2 shown") && !text.contains("--- Page 2 ---"));
+    }
+
+    #[test]
+    fn docx_text_keeps_paragraphs_tabs_and_page_breaks() {
+        let xml = concat!(
+            r#"<w:document><w:body><w:p><w:r><w:t xml:space="preserve">Fish &amp; chips </w:t></w:r>"#,
+            r#"<w:r><w:t>&#x2014;&#8220;ok&quot;</w:t><w:tab/><w:t>&bogus;</w:t></w:r></w:p>"#,
+            r#"<w:tbl><w:tr><w:tc><w:p><w:r><w:t>A1</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>B1</w:t></w:r></w:p></w:tc></w:tr></w:tbl>"#,
+            r#"<w:p><w:r><w:t/><w:instrText>PAGE</w:instrText><w:br w:type="page"/><w:t>Second</w:t><w:br/>line</w:r></w:p>"#,
+            r#"</w:body></w:document>"#,
+        );
+        let pages = docx_pages(xml);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0], "Fish & chips \u{2014}\u{201C}ok\"\t&bogus;\nA1\n\tB1\n\t");
+        assert_eq!(pages[1], "Second\n\n");
+    }
+
+    #[test]
+    fn csv_preview_is_a_markdown_table_with_the_full_shape() {
+        let csv = "name;note;score\r\n\"Kim, D\";\"says \"\"hi\"\"\nthere\";1\nLee;a|b\n";
+        let preview = csv_preview(csv, Path::new("scores.csv"), "scores.csv");
+        assert_eq!(
+            preview,
+            concat!(
+                "--- Sheet: scores.csv (2 rows × 3 columns) ---\n",
+                "| name | note | score |\n",
+                "| --- | --- | --- |\n",
+                "| Kim, D | says \"hi\" there | 1 |\n",
+                "| Lee | a\\|b |  |\n",
+            )
+        );
+
+        let long: String = std::iter::once("id\tvalue".to_string())
+            .chain((0..60).map(|i| format!("{}\t{}", i, "v".repeat(80))))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let preview = csv_preview(&long, Path::new("data.tsv"), "data.tsv");
+        assert!(preview.starts_with("--- Sheet: data.tsv (60 rows × 2 columns) ---"));
+        assert!(preview.contains(&format!("| 0 | {}… |", "v".repeat(CSV_CELL_CHARS))));
+        assert!(!preview.contains("| 50 |"));
+        assert!(preview.ends_with("[Preview: first 50 of 60 rows shown]"));
+    }
+}
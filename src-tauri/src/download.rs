@@ -0,0 +1,321 @@
+use crate::http_client::{self, Route};
+use crate::url_policy;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, LazyLock, Mutex,
+    },
+    time::Duration,
+};
+use tokio::sync::Notify;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time allowed until response headers arrive (includes every redirect hop).
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(20);
+/// Longest silence between two body chunks.
+const READ_TIMEOUT: Duration = Duration::from_secs(15);
+/// Enough bytes to recognize every supported image signature, including an `<svg` root
+/// behind an XML prolog.
+const SNIFF_BYTES: usize = 1024;
+
+// ---------- Cancellation ----------
+
+#[derive(Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    async fn cancelled(&self) {
+        loop {
+            // Register before checking so a cancel() in between is not lost
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+static ACTIVE_DOWNLOADS: LazyLock<Mutex<HashMap<String, Arc<CancelToken>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Keeps a download cancellable by id until dropped.
+pub struct DownloadRegistration {
+    id: Option<String>,
+    pub token: Arc<CancelToken>,
+}
+
+impl Drop for DownloadRegistration {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            if let Ok(mut active) = ACTIVE_DOWNLOADS.lock() {
+                active.remove(&id);
+            }
+        }
+    }
+}
+
+/// Register a download under the webview-supplied `request_id` so `cancel_download` can stop it.
+pub fn register(request_id: Option<String>) -> DownloadRegistration {
+    let token = Arc::new(CancelToken::default());
+    if let Some(id) = request_id.as_ref() {
+        if let Ok(mut active) = ACTIVE_DOWNLOADS.lock() {
+            active.insert(id.clone(), token.clone());
+        }
+    }
+    DownloadRegistration {
+        id: request_id,
+        token,
+    }
+}
+
+// ---------- Content checks ----------
+
+/// Image type from the file signature, independent of what the server claims.
+pub fn sniff_image_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0x89, 0x50, 0x4E, 0x47]) {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.starts_with(b"BM") {
+        Some("image/bmp")
+    } else if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        Some("image/tiff")
+    } else if bytes.len() >= 6 && bytes[..4] == [0, 0, 1, 0] && bytes[4..6] != [0, 0] {
+        Some("image/x-icon")
+    } else if is_avif(bytes) {
+        Some("image/avif")
+    } else if is_svg(bytes) {
+        Some("image/svg+xml")
+    } else {
+        None
+    }
+}
+
+/// ISO-BMFF `ftyp` box whose major or compatible brands name AVIF.
+fn is_avif(bytes: &[u8]) -> bool {
+    if bytes.len() < 16 || &bytes[4..8] != b"ftyp" {
+        return false;
+    }
+    let box_end = (u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+        .clamp(16, bytes.len());
+    bytes[8..box_end]
+        .chunks_exact(4)
+        .any(|brand| brand == b"avif" || brand == b"avis")
+}
+
+/// An `<svg` root, optionally after a BOM, whitespace, XML prolog, comments or doctype.
+fn is_svg(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    let start = head
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(head.len());
+    let head = head[start..].to_ascii_lowercase();
+    if head.starts_with(b"<svg") {
+        return true;
+    }
+    (head.starts_with(b"<?xml") || head.starts_with(b"<!--") || head.starts_with(b"<!doctype svg"))
+        && head.windows(4).any(|w| w == b"<svg")
+}
+
+fn header_mime(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+}
+
+// ---------- Download ----------
+
+pub struct DownloadLimits {
+    pub max_bytes: usize,
+    pub user_agent: Option<&'static str>,
+    /// Apply `url_policy` (public addresses only, pinned, no proxy). Off for URLs the user
+    /// is already looking at, such as images on an intranet page being clipped.
+    pub public_only: bool,
+}
+
+pub struct DownloadedImage {
+    pub bytes: Vec<u8>,
+    /// Sniffed from the content, not taken from `Content-Type`.
+    pub mime_type: String,
+}
+
+fn too_large(max_bytes: usize) -> String {
+    format!("Image exceeds {}MB limit", max_bytes / (1024 * 1024))
+}
+
+/// Stream an image from a URL with a hard byte cap. Stops early when the server
+/// announces or sends more than `max_bytes`, when the first bytes are not an image, on
+/// timeouts, or when `token` is cancelled.
+pub async fn download_image(
+    url: &str,
+    limits: &DownloadLimits,
+    token: &CancelToken,
+) -> Result<DownloadedImage, String> {
+    let base = || {
+        let builder = http_client::builder(Route::Remote).connect_timeout(CONNECT_TIMEOUT);
+        match limits.user_agent {
+            Some(agent) => builder.user_agent(agent),
+            None => builder,
+        }
+    };
+
+    let request = async {
+        if limits.public_only {
+            return url_policy::get_public(url, base).await;
+        }
+        base()
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+    };
+
+    let mut response = tokio::select! {
+        _ = token.cancelled() => return Err("Download cancelled".to_string()),
+        result = tokio::time::timeout(RESPONSE_TIMEOUT, request) => {
+            result.map_err(|_| "Download timed out".to_string())??
+        }
+    };
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    if let Some(len) = response.content_length() {
+        if len > limits.max_bytes as u64 {
+            return Err(too_large(limits.max_bytes));
+        }
+    }
+
+    // Servers may mislabel images, but a declared non-image type must still sniff as one
+    let declared_image = header_mime(&response).is_some_and(|m| m.starts_with("image/"));
+
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut sniffed: Option<&'static str> = None;
+
+    loop {
+        let chunk = tokio::select! {
+            _ = token.cancelled() => return Err("Download cancelled".to_string()),
+            next = tokio::time::timeout(READ_TIMEOUT, response.chunk()) => {
+                next.map_err(|_| "Download stalled".to_string())?
+                    .map_err(|e| format!("Download read error: {}", e))?
+            }
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        if bytes.len() + chunk.len() > limits.max_bytes {
+            return Err(too_large(limits.max_bytes));
+        }
+        bytes.extend_from_slice(&chunk);
+
+        if sniffed.is_none() && bytes.len() >= SNIFF_BYTES {
+            sniffed = Some(sniff_image_mime(&bytes).ok_or_else(|| not_an_image(declared_image))?);
+        }
+    }
+
+    let mime_type = match sniffed {
+        Some(mime) => mime,
+        None => sniff_image_mime(&bytes).ok_or_else(|| not_an_image(declared_image))?,
+    };
+
+    Ok(DownloadedImage {
+        bytes,
+        mime_type: mime_type.to_string(),
+    })
+}
+
+fn not_an_image(declared_image: bool) -> String {
+    if declared_image {
+        "Downloaded content does not match its image type".to_string()
+    } else {
+        "URL is not a supported image".to_string()
+    }
+}
+
+// ---------- Tauri commands ----------
+
+#[tauri::command]
+pub async fn cancel_download(request_id: String) -> Result<bool, String> {
+    let token = ACTIVE_DOWNLOADS
+        .lock()
+        .map_err(|_| "Download registry is unavailable".to_string())?
+        .get(&request_id)
+        .cloned();
+    Ok(match token {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Answers every request with a tiny GIF.
+    fn image_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let _ = stream.read(&mut [0u8; 1024]);
+                let body = b"GIF89a\x01\x00\x01\x00\x00\x00\x00;";
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: image/gif\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(body);
+            }
+        });
+        format!("http://{}/pixel.gif", addr)
+    }
+
+    #[test]
+    fn local_urls_download_only_without_the_public_policy() {
+        let url = image_server();
+        let limits = |public_only| DownloadLimits {
+            max_bytes: 1024,
+            user_agent: None,
+            public_only,
+        };
+        let token = CancelToken::default();
+
+        tauri::async_runtime::block_on(async {
+            let image = download_image(&url, &limits(false), &token).await.unwrap();
+            assert_eq!(image.mime_type, "image/gif");
+            assert!(download_image(&url, &limits(true), &token).await.is_err());
+        });
+    }
+}
@@ -0,0 +1,413 @@
+use crate::{secret_scan, text_file};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Entries visited at most; huge trees are summarized from what was seen.
+const MAX_WALK_ENTRIES: usize = 20_000;
+const MAX_WALK_DEPTH: usize = 12;
+/// Directories below this depth are collapsed to a file count in the tree listing.
+const TREE_DEPTH: usize = 4;
+const MAX_TREE_LINES: usize = 300;
+/// Files larger than this are listed but never inlined.
+const MAX_INCLUDED_FILE_BYTES: u64 = 32 * 1024;
+/// Characters of file contents inlined across all included files.
+const MAX_INCLUDED_CHARS: usize = 40_000;
+const TOP_LANGUAGES: usize = 8;
+
+/// Skipped even without a .gitignore: VCS metadata and dependency folders.
+const ALWAYS_SKIPPED: &[&str] = &[".git", ".hg", ".svn", "node_modules", ".DS_Store"];
+
+/// Build and package manifests, in rough order of how much they say about a project.
+const MANIFESTS: &[&str] = &[
+    "cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "composer.json",
+    "gemfile",
+    "package.swift",
+    "pubspec.yaml",
+    "setup.py",
+    "requirements.txt",
+    "cmakelists.txt",
+    "makefile",
+    "dockerfile",
+    "docker-compose.yml",
+    "docker-compose.yaml",
+    "tsconfig.json",
+    "deno.json",
+];
+const GUIDES: &[&str] = &["contributing.md", "architecture.md", "agents.md", "claude.md"];
+const ENTRY_POINTS: &[&str] = &[
+    "main.rs", "lib.rs", "main.py", "app.py", "main.go", "index.ts", "index.js", "main.ts",
+    "main.tsx", "app.tsx", "program.cs",
+];
+const LOCKFILES: &[&str] = &[
+    "cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "go.sum",
+    "composer.lock",
+    "gemfile.lock",
+];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestFile {
+    /// Path relative to the dropped folder, with `/` separators.
+    pub path: String,
+    pub language: &'static str,
+    pub bytes: u64,
+    /// Path header plus fenced contents, secrets masked.
+    pub text: String,
+    pub secrets: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderDigest {
+    pub folder_name: String,
+    /// Tree listing, totals and language breakdown.
+    pub overview: String,
+    /// Inlined files, most relevant first. The UI lets the user drop entries before sending.
+    pub files: Vec<DigestFile>,
+    pub file_count: usize,
+    pub dir_count: usize,
+    pub total_bytes: u64,
+    /// Set when the walk stopped at `MAX_WALK_ENTRIES`.
+    pub truncated: bool,
+}
+
+struct Entry {
+    /// Relative path with `/` separators.
+    path: String,
+    depth: usize,
+    is_dir: bool,
+    bytes: u64,
+}
+
+// ---------- Walking ----------
+
+fn walk(root: &Path) -> (Vec<Entry>, bool) {
+    let walker = ignore::WalkBuilder::new(root)
+        .hidden(false)
+        .git_ignore(true)
+        .git_exclude(true)
+        .git_global(false)
+        .require_git(false)
+        .parents(false)
+        .follow_links(false)
+        .max_depth(Some(MAX_WALK_DEPTH))
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| {
+            !ALWAYS_SKIPPED.contains(&entry.file_name().to_string_lossy().as_ref())
+        })
+        .build();
+
+    let mut entries = Vec::new();
+    for result in walker {
+        let entry = match result {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("[folder] Skipping entry: {}", e);
+                continue;
+            }
+        };
+        if entry.depth() == 0 {
+            continue;
+        }
+        if entries.len() >= MAX_WALK_ENTRIES {
+            return (entries, true);
+        }
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        let bytes = if is_dir {
+            0
+        } else {
+            entry.metadata().map(|m| m.len()).unwrap_or(0)
+        };
+        entries.push(Entry {
+            path: relative.to_string_lossy().replace('\\', "/"),
+            depth: entry.depth(),
+            is_dir,
+            bytes,
+        });
+    }
+    (entries, false)
+}
+
+// ---------- Overview ----------
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn name_of(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Indented tree down to `TREE_DEPTH`; deeper directories show only what they contain.
+fn tree_listing(entries: &[Entry]) -> String {
+    // Files and bytes below every directory, for the collapsed lines
+    let mut totals: HashMap<&str, (usize, u64)> = HashMap::new();
+    for entry in entries.iter().filter(|e| !e.is_dir) {
+        let mut path = entry.path.as_str();
+        while let Some(slash) = path.rfind('/') {
+            path = &path[..slash];
+            let total = totals.entry(path).or_default();
+            total.0 += 1;
+            total.1 += entry.bytes;
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut hidden = 0;
+    for entry in entries.iter().filter(|e| e.depth <= TREE_DEPTH) {
+        if lines.len() >= MAX_TREE_LINES {
+            hidden += 1;
+            continue;
+        }
+        let indent = "  ".repeat(entry.depth - 1);
+        let name = name_of(&entry.path);
+        let line = if !entry.is_dir {
+            format!("{}{} ({})", indent, name, human_size(entry.bytes))
+        } else if entry.depth == TREE_DEPTH {
+            let (files, bytes) = totals.get(entry.path.as_str()).copied().unwrap_or_default();
+            format!("{}{}/ … {} files, {}", indent, name, files, human_size(bytes))
+        } else {
+            format!("{}{}/", indent, name)
+        };
+        lines.push(line);
+    }
+    if hidden > 0 {
+        lines.push(format!("… {} more entries", hidden));
+    }
+    lines.join("\n")
+}
+
+fn language_summary(entries: &[Entry]) -> String {
+    let mut languages: HashMap<&'static str, (usize, u64)> = HashMap::new();
+    for entry in entries.iter().filter(|e| !e.is_dir) {
+        let language = text_file::language_for(Path::new(&entry.path));
+        if language == "text" {
+            continue;
+        }
+        let total = languages.entry(language).or_default();
+        total.0 += 1;
+        total.1 += entry.bytes;
+    }
+    let mut ranked: Vec<_> = languages.into_iter().collect();
+    ranked.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then(a.0.cmp(b.0)));
+    ranked
+        .into_iter()
+        .take(TOP_LANGUAGES)
+        .map(|(language, (files, bytes))| format!("{} {} files ({})", language, files, human_size(bytes)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// ---------- Relevant files ----------
+
+/// How useful a file is for understanding the project; `None` for files never inlined.
+fn relevance(entry: &Entry) -> Option<i32> {
+    if entry.is_dir || entry.bytes == 0 || entry.bytes > MAX_INCLUDED_FILE_BYTES {
+        return None;
+    }
+    let name = name_of(&entry.path).to_lowercase();
+    if LOCKFILES.contains(&name.as_str()) {
+        return None;
+    }
+    let top_level = entry.depth == 1;
+    let base = if name.starts_with("readme") {
+        if top_level { 100 } else { 35 }
+    } else if let Some(rank) = MANIFESTS.iter().position(|m| *m == name) {
+        (if top_level { 90 } else { 50 }) - rank as i32
+    } else if GUIDES.contains(&name.as_str()) {
+        45
+    } else if ENTRY_POINTS.contains(&name.as_str()) {
+        30
+    } else {
+        return None;
+    };
+    Some(base - 5 * (entry.depth as i32 - 1))
+}
+
+fn include_files(root: &Path, entries: &[Entry]) -> Vec<DigestFile> {
+    let mut candidates: Vec<(i32, &Entry)> = entries
+        .iter()
+        .filter_map(|e| relevance(e).map(|score| (score, e)))
+        .collect();
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.path.cmp(&b.1.path)));
+
+    let mut files = Vec::new();
+    let mut used = 0;
+    for (_, entry) in candidates {
+        let Ok(bytes) = std::fs::read(root.join(&entry.path)) else {
+            continue;
+        };
+        let Ok((decoded, _)) = text_file::decode(&bytes) else {
+            continue;
+        };
+        let chars = decoded.chars().count();
+        if used + chars > MAX_INCLUDED_CHARS {
+            continue;
+        }
+        used += chars;
+        let (masked, secrets) = secret_scan::mask_secrets(&decoded);
+        let language = text_file::language_for(Path::new(&entry.path));
+        files.push(DigestFile {
+            text: text_file::fenced_block(&entry.path, language, &masked),
+            path: entry.path.clone(),
+            language,
+            bytes: entry.bytes,
+            secrets: secrets.len(),
+        });
+    }
+    files
+}
+
+// ---------- Entry point ----------
+
+pub fn digest(root: &Path) -> Result<FolderDigest, String> {
+    if !root.is_dir() {
+        return Err("Not a folder".to_string());
+    }
+    let folder_name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| root.to_string_lossy().into_owned());
+
+    let (entries, truncated) = walk(root);
+    let file_count = entries.iter().filter(|e| !e.is_dir).count();
+    let dir_count = entries.len() - file_count;
+    let total_bytes: u64 = entries.iter().map(|e| e.bytes).sum();
+
+    let mut overview = format!(
+        "--- Folder: {}/ ({} files in {} folders, {}) ---\n",
+        folder_name,
+        file_count,
+        dir_count,
+        human_size(total_bytes)
+    );
+    let languages = language_summary(&entries);
+    if !languages.is_empty() {
+        overview.push_str(&format!("Languages: {}\n", languages));
+    }
+    overview.push('\n');
+    overview.push_str(&tree_listing(&entries));
+    if truncated {
+        overview.push_str(&format!(
+            "\n[Truncated: stopped after {} entries]",
+            MAX_WALK_ENTRIES
+        ));
+    }
+
+    Ok(FolderDigest {
+        files: include_files(root, &entries),
+        folder_name,
+        overview,
+        file_count,
+        dir_count,
+        total_bytes,
+        truncated,
+    })
+}
+
+#[tauri::command]
+pub async fn read_folder_digest(path: String) -> Result<FolderDigest, String> {
+    let canonical = std::fs::canonicalize(&path)
+        .map_err(|e| format!("Failed to read folder: {e}"))?;
+    // Walking a large tree and reading files blocks; keep it off the async runtime
+    let digest = tauri::async_runtime::spawn_blocking(move || digest(&canonical))
+        .await
+        .map_err(|e| format!("Failed to read folder: {e}"))??;
+    let secrets: usize = digest.files.iter().map(|f| f.secrets).sum();
+    if secrets > 0 {
+        eprintln!(
+            "[folder] Masked {} possible secret(s) in {}",
+            secrets, digest.folder_name
+        );
+    }
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, bytes: u64) -> Entry {
+        Entry {
+            path: path.to_string(),
+            depth: path.matches('/').count() + 1,
+            is_dir: false,
+            bytes,
+        }
+    }
+
+    #[test]
+    fn top_level_readme_and_manifests_rank_first() {
+        let score = |path: &str| relevance(&file(path, 100));
+        assert!(score("README.md") > score("Cargo.toml"));
+        assert!(score("Cargo.toml") > score("package.json"));
+        assert!(score("package.json") > score("CONTRIBUTING.md"));
+        assert!(score("CONTRIBUTING.md") > score("crates/core/Cargo.toml"));
+        assert!(score("crates/core/Cargo.toml") > score("docs/README.md"));
+        assert!(score("docs/README.md") > score("src/main.rs"));
+        assert!(score("src/main.rs") > score("src/bin/tool/main.rs"));
+
+        for path in ["Cargo.lock", "src/util.rs", "notes.txt"] {
+            assert_eq!(score(path), None, "{path}");
+        }
+        assert_eq!(relevance(&file("README.md", 0)), None);
+        assert_eq!(relevance(&file("README.md", MAX_INCLUDED_FILE_BYTES + 1)), None);
+        let dir = Entry { is_dir: true, ..file("README.md", 10) };
+        assert_eq!(relevance(&dir), None);
+    }
+
+    #[test]
+    fn included_files_follow_relevance_within_the_budget() {
+        let root = std::env::temp_dir().join(format!("clawpet-folder-budget-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let files = [
+            ("README.md", "# Demo\n".to_string()),
+            // Too big to fit next to the README, so it is skipped
+            ("Cargo.toml", "x".repeat(MAX_INCLUDED_CHARS)),
+            ("package.json", format!("{{\"token\": \"{}\"}}", "Zq8".repeat(8))),
+            ("src/main.rs", "fn main() {}\n".to_string()),
+        ];
+        let mut entries = Vec::new();
+        for (path, contents) in &files {
+            std::fs::write(root.join(path), contents).unwrap();
+            entries.push(file(path, contents.len() as u64));
+        }
+
+        let included = include_files(&root, &entries);
+        let paths: Vec<&str> = included.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["README.md", "package.json", "src/main.rs"]);
+        assert_eq!(included[1].secrets, 1);
+        assert!(included[1].text.contains("[REDACTED:Secret assignment]"));
+        assert!(included[2].text.starts_with("--- src/main.rs ---\n```rust\n"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
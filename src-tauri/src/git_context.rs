@@ -0,0 +1,417 @@
+use crate::{secret_scan, text_file};
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+
+/// Bytes of diff text sent to the agent, shared across all changed files.
+const MAX_DIFF_BYTES: usize = 40_000;
+/// A file's diff is never cut below this many bytes, so every file keeps at least its first hunk.
+const MIN_FILE_SHARE: usize = 600;
+const LOG_ENTRIES: usize = 10;
+const MAX_STATUS_LINES: usize = 100;
+
+/// Generated files whose diffs say little; they're listed with their line counts only.
+const GENERATED_FILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "go.sum",
+    "composer.lock",
+    "Gemfile.lock",
+];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GitContext {
+    pub repo_name: String,
+    pub branch: String,
+    /// Branch, status, log and diffs as one text block, secrets masked.
+    pub text: String,
+    pub changed_files: usize,
+    /// Set when any diff was shortened or left out to fit the budget.
+    pub truncated: bool,
+    pub secrets: usize,
+}
+
+struct FileDiff {
+    path: String,
+    staged: bool,
+    text: String,
+}
+
+// ---------- git CLI ----------
+
+fn git(root: &Path, args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(root)
+        .args(["-c", "core.quotepath=off", "-c", "color.ui=never"])
+        .args(args)
+        // Reading status must not take the index lock from the user's own git commands
+        .env("GIT_OPTIONAL_LOCKS", "0");
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub fn is_repository(root: &Path) -> bool {
+    git(root, &["rev-parse", "--is-inside-work-tree"]).is_ok_and(|out| out.trim() == "true")
+}
+
+// ---------- Diffs ----------
+
+/// Path after the change, from the extended header lines. The `diff --git a/<old> b/<new>`
+/// line alone is ambiguous when a path contains " b/".
+fn diff_path(text: &str) -> String {
+    let mut header = text.lines();
+    let first = header.next().unwrap_or("").trim_start_matches("diff --git ");
+    let mut deleted = None;
+    for line in header.take_while(|line| !line.starts_with("@@")) {
+        // Git ends these lines with a tab when the path contains spaces
+        if let Some(path) = line.strip_prefix("+++ b/") {
+            return path.trim_end_matches('\t').to_string();
+        }
+        if let Some(path) = line.strip_prefix("rename to ").or_else(|| line.strip_prefix("copy to ")) {
+            return path.to_string();
+        }
+        if let Some(path) = line.strip_prefix("--- a/") {
+            deleted = Some(path.trim_end_matches('\t').to_string());
+        }
+    }
+    if let Some(path) = deleted {
+        return path;
+    }
+    // Binary or mode-only change without a rename: "a/<path> b/<path>" with the same path twice
+    let half = first.len().saturating_sub(1) / 2;
+    if first.len() % 2 == 1 && first.is_char_boundary(half) {
+        let (old, new) = (&first[..half], &first[half + 1..]);
+        if let (Some(old), Some(new)) = (old.strip_prefix("a/"), new.strip_prefix("b/")) {
+            if old == new {
+                return new.to_string();
+            }
+        }
+    }
+    first
+        .rsplit_once(" b/")
+        .map(|(_, path)| path.to_string())
+        .unwrap_or_else(|| first.to_string())
+}
+
+/// Split `git diff` output into one entry per file.
+fn split_diff(diff: &str, staged: bool) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    for chunk in diff.split("\ndiff --git ").filter(|c| !c.trim().is_empty()) {
+        let text = if chunk.starts_with("diff --git ") {
+            chunk.to_string()
+        } else {
+            format!("diff --git {}", chunk)
+        };
+        files.push(FileDiff {
+            path: diff_path(&text),
+            staged,
+            text: text.trim_end().to_string(),
+        });
+    }
+    files
+}
+
+fn line_counts(diff: &str) -> (usize, usize) {
+    diff.lines().fold((0, 0), |(added, removed), line| {
+        if line.starts_with("+++") || line.starts_with("---") {
+            (added, removed)
+        } else if line.starts_with('+') {
+            (added + 1, removed)
+        } else if line.starts_with('-') {
+            (added, removed + 1)
+        } else {
+            (added, removed)
+        }
+    })
+}
+
+/// Whole lines up to `limit` bytes, with a note on how much was cut.
+fn cut_diff(text: &str, limit: usize) -> (String, bool) {
+    if text.len() <= limit {
+        return (text.to_string(), false);
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    let mut kept = 0;
+    for line in text.lines() {
+        let len = line.len() + 1;
+        if used + len > limit {
+            break;
+        }
+        out.push_str(line);
+        out.push('\n');
+        used += len;
+        kept += 1;
+    }
+    out.push_str(&format!(
+        "[... {} more diff lines]",
+        text.lines().count() - kept
+    ));
+    (out, true)
+}
+
+/// Fit the diffs into `MAX_DIFF_BYTES`, smallest first: each file gets an equal share of
+/// what is left, so small changes come through whole and large ones are cut evenly.
+fn fit_diffs(files: &[FileDiff]) -> (Vec<String>, bool) {
+    let mut order: Vec<usize> = (0..files.len())
+        .filter(|&i| !is_generated(&files[i].path))
+        .collect();
+    order.sort_by_key(|&i| files[i].text.len());
+
+    let mut shown: Vec<Option<String>> = vec![None; files.len()];
+    let mut remaining = MAX_DIFF_BYTES;
+    let mut truncated = false;
+    for (position, &index) in order.iter().enumerate() {
+        let share = (remaining / (order.len() - position)).max(MIN_FILE_SHARE);
+        if remaining < MIN_FILE_SHARE {
+            truncated = true;
+            break;
+        }
+        let (text, cut) = cut_diff(&files[index].text, share.min(remaining));
+        truncated |= cut;
+        remaining = remaining.saturating_sub(text.len());
+        shown[index] = Some(text);
+    }
+
+    let blocks = files
+        .iter()
+        .zip(shown)
+        .map(|(file, text)| match text {
+            Some(text) => text,
+            None => {
+                let (added, removed) = line_counts(&file.text);
+                let reason = if is_generated(&file.path) { "generated file" } else { "over budget" };
+                format!(
+                    "diff --git a/{path} b/{path}\n[Diff omitted ({reason}): +{added} -{removed}]",
+                    path = file.path
+                )
+            }
+        })
+        .collect();
+    (blocks, truncated)
+}
+
+fn is_generated(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    GENERATED_FILES.contains(&name) || name.ends_with(".min.js")
+}
+
+// ---------- Entry point ----------
+
+pub fn context(root: &Path) -> Result<GitContext, String> {
+    if !is_repository(root) {
+        return Err("Not a git repository".to_string());
+    }
+    let top_level = git(root, &["rev-parse", "--show-toplevel"])?;
+    let repo_name = Path::new(top_level.trim())
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "repository".to_string());
+
+    // "## main...origin/main [ahead 1]" followed by "XY path" lines
+    let status = git(root, &["status", "--porcelain=v1", "--branch", "--untracked-files=normal"])?;
+    let mut status_lines = status.lines();
+    let branch_line = status_lines
+        .next()
+        .and_then(|line| line.strip_prefix("## "))
+        .map(|line| line.trim_start_matches("No commits yet on "))
+        .unwrap_or("HEAD")
+        .to_string();
+    let branch = branch_line
+        .split("...")
+        .next()
+        .unwrap_or(&branch_line)
+        .split(' ')
+        .next()
+        .unwrap_or("HEAD")
+        .to_string();
+    let changes: Vec<&str> = status_lines.collect();
+
+    // A fresh repository has no commits to log yet
+    let log = git(
+        root,
+        &[
+            "log",
+            &format!("-n{}", LOG_ENTRIES),
+            "--date=short",
+            "--pretty=format:%h %ad %an: %s",
+        ],
+    )
+    .unwrap_or_default();
+
+    let mut diffs = split_diff(&git(root, &["diff", "--cached", "--no-ext-diff", "--find-renames"])?, true);
+    diffs.extend(split_diff(&git(root, &["diff", "--no-ext-diff", "--find-renames"])?, false));
+    let (blocks, diff_truncated) = fit_diffs(&diffs);
+
+    let mut text = format!("--- Git: {} (branch {}) ---\n", repo_name, branch_line);
+    if changes.is_empty() {
+        text.push_str("Working tree clean\n");
+    } else {
+        text.push_str(&format!("Status ({} changed):\n", changes.len()));
+        for line in changes.iter().take(MAX_STATUS_LINES) {
+            text.push_str(line);
+            text.push('\n');
+        }
+        if changes.len() > MAX_STATUS_LINES {
+            text.push_str(&format!("... {} more\n", changes.len() - MAX_STATUS_LINES));
+        }
+    }
+    if !log.trim().is_empty() {
+        text.push_str("\nRecent commits:\n");
+        text.push_str(log.trim_end());
+        text.push('\n');
+    }
+    for (label, staged) in [("Staged changes", true), ("Unstaged changes", false)] {
+        let section: Vec<&str> = diffs
+            .iter()
+            .zip(&blocks)
+            .filter(|(diff, _)| diff.staged == staged)
+            .map(|(_, block)| block.as_str())
+            .collect();
+        if section.is_empty() {
+            continue;
+        }
+        text.push('\n');
+        text.push_str(&text_file::fenced_block(label, "diff", &section.join("\n")));
+        text.push('\n');
+    }
+    if diff_truncated {
+        text.push_str(&format!(
+            "\n[Truncated: diffs cut to {} KB, smallest files kept whole]",
+            MAX_DIFF_BYTES / 1000
+        ));
+    }
+
+    let (masked, secrets) = secret_scan::mask_secrets(text.trim_end());
+    Ok(GitContext {
+        repo_name,
+        branch,
+        text: masked,
+        changed_files: changes.len(),
+        truncated: diff_truncated,
+        secrets: secrets.len(),
+    })
+}
+
+#[tauri::command]
+pub async fn read_git_context(path: String) -> Result<GitContext, String> {
+    let canonical = std::fs::canonicalize(&path)
+        .map_err(|e| format!("Failed to read folder: {e}"))?;
+    // git runs as child processes that can take a while on big repositories
+    let context = tauri::async_runtime::spawn_blocking(move || context(&canonical))
+        .await
+        .map_err(|e| format!("Failed to read git repository: {e}"))??;
+    if context.secrets > 0 {
+        eprintln!(
+            "[git] Masked {} possible secret(s) in {} diff",
+            context.secrets, context.repo_name
+        );
+    }
+    Ok(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modified(path: &str, body: &str) -> String {
+        format!(
+            "diff --git a/{path} b/{path}\nindex 1111111..2222222 100644\n--- a/{path}\n+++ b/{path}\n@@ -1 +1 @@\n{body}\n"
+        )
+    }
+
+    fn file_diff(path: &str, text: String) -> FileDiff {
+        FileDiff {
+            path: path.to_string(),
+            staged: false,
+            text,
+        }
+    }
+
+    #[test]
+    fn diffs_are_split_per_file_with_the_new_path() {
+        let diff = [
+            modified("src/main.rs", "-old\n+new"),
+            modified("docs/a b/notes.md", "+line with diff --git a/x b/y inside"),
+            "diff --git a/old.txt b/new.txt\nsimilarity index 90%\nrename from old.txt\nrename to new.txt\n".to_string(),
+            "diff --git a/gone.rs b/gone.rs\ndeleted file mode 100644\n--- a/gone.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-bye\n".to_string(),
+            "diff --git a/img b/x.png b/img b/x.png\nindex 1..2 100644\nBinary files a/img b/x.png and b/img b/x.png differ\n".to_string(),
+            "diff --git a/with space.txt b/with space.txt\n--- a/with space.txt\t\n+++ b/with space.txt\t\n@@ -1 +1 @@\n-a\n+b\n".to_string(),
+        ]
+        .concat();
+        let files = split_diff(&diff, true);
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["src/main.rs", "docs/a b/notes.md", "new.txt", "gone.rs", "img b/x.png", "with space.txt"]
+        );
+        assert!(files.iter().all(|f| f.staged && f.text.starts_with("diff --git a/")));
+        assert!(files[1].text.ends_with("inside"));
+        assert!(split_diff("", false).is_empty());
+    }
+
+    #[test]
+    fn generated_files_are_recognized_by_name() {
+        for path in ["Cargo.lock", "web/package-lock.json", "vendor/app.min.js", "go.sum"] {
+            assert!(is_generated(path), "{path}");
+        }
+        for path in ["src/lock.rs", "Cargo.toml", "app.js", "min.js.map"] {
+            assert!(!is_generated(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn diffs_share_the_byte_budget_smallest_first() {
+        let big_body = |sign: char| {
+            let lines: Vec<String> = (0..3000).map(|i| format!("{sign}line {i:05} ééé")).collect();
+            lines.join("\n")
+        };
+        let files = vec![
+            file_diff("big.rs", modified("big.rs", &big_body('+'))),
+            file_diff("small.rs", modified("small.rs", "-a\n+b")),
+            file_diff("Cargo.lock", modified("Cargo.lock", "+x\n+y\n-z")),
+            file_diff("other.rs", modified("other.rs", &big_body('-'))),
+        ];
+        let (blocks, truncated) = fit_diffs(&files);
+        assert!(truncated);
+        assert_eq!(blocks[1], files[1].text);
+        assert_eq!(blocks[2], "diff --git a/Cargo.lock b/Cargo.lock\n[Diff omitted (generated file): +2 -1]");
+        // Both large files get about half of what the small one left
+        for block in [&blocks[0], &blocks[3]] {
+            assert!(block.ends_with("more diff lines]"));
+            assert!(block.len() <= (MAX_DIFF_BYTES - files[1].text.len()) / 2 + 40);
+            assert!(block.len() > MAX_DIFF_BYTES / 2 - 200);
+        }
+        let total: usize = blocks.iter().map(|b| b.len()).sum();
+        assert!(total <= MAX_DIFF_BYTES + 200);
+
+        // Once the budget is gone, later files are listed with their line counts only
+        let many: Vec<FileDiff> = (0..80)
+            .map(|i| file_diff(&format!("f{i}.rs"), modified(&format!("f{i}.rs"), &big_body('+'))))
+            .collect();
+        let (blocks, truncated) = fit_diffs(&many);
+        assert!(truncated);
+        assert!(blocks.iter().any(|b| b.ends_with("[Diff omitted (over budget): +3000 -0]")));
+    }
+}
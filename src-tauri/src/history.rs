@@ -0,0 +1,1495 @@
+use crate::attachment_store::{self, AttachmentStore, StoredAttachment};
+use crate::history_crypto::{self, HistoryKeys};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+/// Entries with the same role and text this close together are treated as the same message.
+const DUPLICATE_WINDOW_MS: i64 = 2 * 60 * 1000;
+const HISTORY_DB_FILE: &str = "history.sqlite3";
+const MAX_PAGE_SIZE: u32 = 500;
+const LOCAL_STORAGE_MIGRATED_KEY: &str = "local_storage_migrated";
+const LOCKED_ERROR: &str = "History is locked";
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+/// Retention runs on open/unlock and then at most this often from `append`.
+const RETENTION_INTERVAL_MS: i64 = 60 * 60 * 1000;
+/// Captured images not yet attached to any message are kept at least this long.
+const ORPHAN_GRACE_MS: i64 = DAY_MS;
+
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// ---------- Data types ----------
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryAttachment {
+    pub mime_type: String,
+    #[serde(default)]
+    pub file_name: Option<String>,
+    #[serde(default)]
+    pub size_bytes: usize,
+    /// Content hash in the attachment store, once the payload has been saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(skip)]
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: String,
+    pub role: String,
+    pub text: String,
+    pub timestamp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<HistoryAttachment>,
+}
+
+/// Attachment as sent by the webview: either an id returned by a capture/read command,
+/// or the raw image payload that went to the gateway.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewHistoryAttachment {
+    pub mime_type: String,
+    #[serde(default)]
+    pub file_name: Option<String>,
+    #[serde(default)]
+    pub hash: Option<String>,
+    #[serde(default)]
+    pub base64: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    /// Oldest first, like the chat view renders them.
+    pub entries: Vec<HistoryEntry>,
+    pub total: usize,
+    pub has_more: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySearchHit {
+    pub entry: HistoryEntry,
+    pub snippet: String,
+}
+
+#[derive(Serialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionResult {
+    pub expired_entries: usize,
+    pub trimmed_entries: usize,
+    pub purged_attachments: usize,
+    pub removed_blobs: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionStatus {
+    pub encrypted: bool,
+    pub locked: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryMigrationResult {
+    pub migrated: usize,
+    pub already_migrated: bool,
+}
+
+// ---------- Duplicate detection ----------
+
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn duplicate_key(entry: &HistoryEntry) -> (String, String) {
+    (entry.role.clone(), normalize_text(&entry.text))
+}
+
+/// Keep the `incoming` entries that are not already in `existing`, either by id or by
+/// role + text + nearby timestamp. Duplicates within `incoming` are dropped as well.
+pub(crate) fn select_new_entries(
+    existing: &[HistoryEntry],
+    incoming: Vec<HistoryEntry>,
+) -> Vec<HistoryEntry> {
+    let mut ids: HashSet<String> = existing.iter().map(|e| e.id.clone()).collect();
+    let mut seen: HashMap<(String, String), Vec<i64>> = HashMap::new();
+    for entry in existing {
+        seen.entry(duplicate_key(entry)).or_default().push(entry.timestamp);
+    }
+
+    let mut fresh = Vec::new();
+    for entry in incoming {
+        if ids.contains(&entry.id) {
+            continue;
+        }
+        let stamps = seen.entry(duplicate_key(&entry)).or_default();
+        if stamps
+            .iter()
+            .any(|t| (t - entry.timestamp).abs() <= DUPLICATE_WINDOW_MS)
+        {
+            continue;
+        }
+        stamps.push(entry.timestamp);
+        ids.insert(entry.id.clone());
+        fresh.push(entry);
+    }
+
+    fresh
+}
+
+fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn generate_id(timestamp: i64) -> String {
+    let seq = ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    format!("{:x}-{:08x}-{:x}", timestamp, nanos, seq)
+}
+
+/// Quote every term so user input can't trip FTS5 query syntax (`-`, `:`, `"` ...).
+fn fts_query(raw: &str) -> Option<String> {
+    let terms: Vec<String> = raw
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+// ---------- Retention ----------
+
+/// Limits read from the environment; unset or `0` means unlimited.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RetentionPolicy {
+    /// `CLAWPET_HISTORY_MAX_AGE_DAYS`: delete messages older than this.
+    pub max_age_days: Option<u64>,
+    /// `CLAWPET_HISTORY_MAX_MB`: delete the oldest messages until text + attachments fit.
+    pub max_size_bytes: Option<u64>,
+    /// `CLAWPET_HISTORY_ATTACHMENT_DAYS`: drop attachments of older messages, keep the text.
+    pub attachment_days: Option<u64>,
+}
+
+fn env_limit(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|v| *v > 0)
+}
+
+impl RetentionPolicy {
+    pub fn from_env() -> Self {
+        Self {
+            max_age_days: env_limit("CLAWPET_HISTORY_MAX_AGE_DAYS"),
+            max_size_bytes: env_limit("CLAWPET_HISTORY_MAX_MB").map(|mb| mb * 1024 * 1024),
+            attachment_days: env_limit("CLAWPET_HISTORY_ATTACHMENT_DAYS"),
+        }
+    }
+}
+
+fn days_before(now: i64, days: u64) -> i64 {
+    now.saturating_sub((days as i64).saturating_mul(DAY_MS))
+}
+
+/// Unlink attachments from entries older than `cutoff`. Metadata stays so the UI can still
+/// show that something was attached.
+fn purge_attachments_before(conn: &Connection, cutoff: i64) -> Result<usize, String> {
+    let rows: Vec<(i64, String)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT seq, attachments FROM entries
+                 WHERE timestamp < ?1 AND seq IN (SELECT entry_seq FROM entry_blobs)",
+            )
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        let rows = stmt
+            .query_map(params![cutoff], |row| Ok((row.get(0)?, row.get(1)?)))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        rows
+    };
+
+    let mut purged = 0usize;
+    for (seq, attachments_json) in rows {
+        let mut metadata: Vec<HistoryAttachment> =
+            serde_json::from_str(&attachments_json).unwrap_or_default();
+        for attachment in &mut metadata {
+            if attachment.hash.take().is_some() {
+                purged += 1;
+            }
+        }
+        let updated_json =
+            serde_json::to_string(&metadata).map_err(|e| format!("JSON encode error: {}", e))?;
+        conn.execute(
+            "UPDATE entries SET attachments = ?2 WHERE seq = ?1",
+            params![seq, updated_json],
+        )
+        .and_then(|_| conn.execute("DELETE FROM entry_blobs WHERE entry_seq = ?1", params![seq]))
+        .map_err(|e| format!("Failed to update history: {}", e))?;
+    }
+    Ok(purged)
+}
+
+/// Delete the oldest entries until stored text plus referenced originals fit `max_bytes`.
+fn trim_to_size(conn: &Connection, max_bytes: u64) -> Result<usize, String> {
+    let total: i64 = conn
+        .query_row(
+            "SELECT
+                (SELECT COALESCE(SUM(length(CAST(text AS BLOB)) + length(attachments)), 0) FROM entries)
+              + (SELECT COALESCE(SUM(b.size_bytes), 0) FROM blobs b WHERE b.evicted = 0
+                 AND EXISTS (SELECT 1 FROM entry_blobs eb WHERE eb.hash = b.hash))",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+    let mut excess = total - max_bytes as i64;
+    if excess <= 0 {
+        return Ok(0);
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT e.seq,
+                    length(CAST(e.text AS BLOB)) + length(e.attachments)
+                  + (SELECT COALESCE(SUM(b.size_bytes), 0) FROM entry_blobs eb
+                     JOIN blobs b ON b.hash = eb.hash
+                     WHERE eb.entry_seq = e.seq AND b.evicted = 0)
+             FROM entries e ORDER BY e.timestamp, e.seq",
+        )
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+    let mut rows = stmt
+        .query([])
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+
+    let mut doomed = Vec::new();
+    while excess > 0 {
+        let Some(row) = rows.next().map_err(|e| format!("Failed to read history: {}", e))? else {
+            break;
+        };
+        let seq: i64 = row.get(0).map_err(|e| format!("Failed to read history: {}", e))?;
+        let size: i64 = row.get(1).map_err(|e| format!("Failed to read history: {}", e))?;
+        doomed.push(seq);
+        excess -= size;
+    }
+    drop(rows);
+
+    for seq in &doomed {
+        conn.execute("DELETE FROM entries WHERE seq = ?1", params![seq])
+            .map_err(|e| format!("Failed to delete history: {}", e))?;
+    }
+    Ok(doomed.len())
+}
+
+// ---------- SQLite store ----------
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS entries (
+    seq INTEGER PRIMARY KEY,
+    id TEXT NOT NULL UNIQUE,
+    role TEXT NOT NULL,
+    text TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    attachments TEXT NOT NULL DEFAULT '[]'
+);
+CREATE INDEX IF NOT EXISTS entries_timestamp ON entries(timestamp, seq);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS entries_fts USING fts5(
+    text,
+    content='entries',
+    content_rowid='seq',
+    tokenize='unicode61'
+);
+
+CREATE TRIGGER IF NOT EXISTS entries_ai AFTER INSERT ON entries BEGIN
+    INSERT INTO entries_fts(rowid, text) VALUES (new.seq, new.text);
+END;
+CREATE TRIGGER IF NOT EXISTS entries_ad AFTER DELETE ON entries BEGIN
+    INSERT INTO entries_fts(entries_fts, rowid, text) VALUES ('delete', old.seq, old.text);
+END;
+CREATE TRIGGER IF NOT EXISTS entries_au AFTER UPDATE OF text ON entries BEGIN
+    INSERT INTO entries_fts(entries_fts, rowid, text) VALUES ('delete', old.seq, old.text);
+    INSERT INTO entries_fts(rowid, text) VALUES (new.seq, new.text);
+END;
+
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+"#;
+
+/// Upgrades applied in order on top of `SCHEMA`; `PRAGMA user_version` counts the ones done.
+const MIGRATIONS: &[&str] = &[
+    // 1: session tag + attachment payloads (needed for export)
+    r#"
+    ALTER TABLE entries ADD COLUMN session TEXT;
+    CREATE INDEX IF NOT EXISTS entries_session ON entries(session, timestamp);
+    CREATE TABLE IF NOT EXISTS entry_attachments (
+        entry_seq INTEGER NOT NULL REFERENCES entries(seq) ON DELETE CASCADE,
+        idx INTEGER NOT NULL,
+        data BLOB NOT NULL,
+        PRIMARY KEY (entry_seq, idx)
+    );
+    "#,
+    // 2: content-addressed attachment store; inline payloads are moved out on open
+    r#"
+    CREATE TABLE IF NOT EXISTS blobs (
+        hash TEXT PRIMARY KEY,
+        mime_type TEXT NOT NULL,
+        size_bytes INTEGER NOT NULL,
+        width INTEGER,
+        height INTEGER,
+        created_at INTEGER NOT NULL,
+        last_access INTEGER NOT NULL,
+        evicted INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS blobs_lru ON blobs(evicted, last_access);
+    CREATE TABLE IF NOT EXISTS entry_blobs (
+        entry_seq INTEGER NOT NULL REFERENCES entries(seq) ON DELETE CASCADE,
+        idx INTEGER NOT NULL,
+        hash TEXT NOT NULL REFERENCES blobs(hash),
+        PRIMARY KEY (entry_seq, idx)
+    );
+    CREATE INDEX IF NOT EXISTS entry_blobs_hash ON entry_blobs(hash);
+    "#,
+];
+
+fn run_migrations(conn: &mut Connection) -> Result<(), String> {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+        .map(|v| v.max(0) as usize)
+        .map_err(|e| format!("Failed to read history schema: {}", e))?;
+
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to upgrade history: {}", e))?;
+        tx.execute_batch(sql)
+            .and_then(|_| tx.pragma_update(None, "user_version", (i + 1) as i64))
+            .and_then(|_| tx.commit())
+            .map_err(|e| format!("Failed to upgrade history: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// In-memory database at the current schema.
+#[cfg(test)]
+pub(crate) fn memory_db() -> Connection {
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(SCHEMA).unwrap();
+    run_migrations(&mut conn).unwrap();
+    conn
+}
+
+const ENTRY_COLUMNS: &str = "id, role, text, timestamp, attachments, session";
+
+/// `conn` is `None` while encrypted history waits for its passphrase, or when the
+/// database could not be opened (`unavailable` says why).
+pub struct HistoryStore {
+    data_dir: PathBuf,
+    conn: Mutex<Option<Connection>>,
+    attachments: AttachmentStore,
+    retention: RetentionPolicy,
+    last_retention: AtomicI64,
+    unavailable: Option<String>,
+}
+
+/// Lock guard that is only handed out while the database is open.
+struct ConnGuard<'a>(MutexGuard<'a, Option<Connection>>);
+
+impl Deref for ConnGuard<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.0.as_ref().expect("checked by HistoryStore::lock")
+    }
+}
+
+impl DerefMut for ConnGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.0.as_mut().expect("checked by HistoryStore::lock")
+    }
+}
+
+/// Open (and upgrade) the database, keyed with SQLCipher when `keys` is given.
+fn open_connection(
+    path: &Path,
+    keys: Option<&HistoryKeys>,
+    attachments: &AttachmentStore,
+) -> Result<Connection, String> {
+    let mut conn = Connection::open(path).map_err(|e| format!("Failed to open history: {}", e))?;
+    if let Some(keys) = keys {
+        conn.pragma_update(None, "key", keys.db_key_literal().as_str())
+            .map_err(|e| format!("Failed to open history: {}", e))?;
+        // SQLCipher only notices a wrong key on the first read
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+            .map_err(|_| "Wrong passphrase".to_string())?;
+    }
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON; PRAGMA secure_delete = ON;",
+    )
+    .map_err(|e| format!("Failed to configure history: {}", e))?;
+    conn.execute_batch(SCHEMA)
+        .map_err(|e| format!("Failed to initialize history: {}", e))?;
+    run_migrations(&mut conn)?;
+    move_inline_attachments(&mut conn, attachments)?;
+    Ok(conn)
+}
+
+fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<HistoryEntry> {
+    let attachments_json: String = row.get(4)?;
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        role: row.get(1)?,
+        text: row.get(2)?,
+        timestamp: row.get(3)?,
+        session: row.get(5)?,
+        attachments: serde_json::from_str(&attachments_json).unwrap_or_default(),
+    })
+}
+
+/// Save attachment payloads to the store and replace them with their hash.
+fn store_attachment_payloads(
+    conn: &Connection,
+    attachments: &AttachmentStore,
+    entry: &mut HistoryEntry,
+) -> Result<(), String> {
+    for attachment in &mut entry.attachments {
+        let Some(data) = attachment.data.take() else {
+            if let Some(hash) = attachment.hash.as_deref() {
+                attachment.size_bytes = attachments.size_of(conn, hash)?;
+            }
+            continue;
+        };
+        let stored = attachments.put(conn, &data, &attachment.mime_type)?;
+        attachment.size_bytes = stored.size_bytes;
+        attachment.hash = Some(stored.hash);
+    }
+    Ok(())
+}
+
+fn insert_entry(conn: &Connection, entry: &HistoryEntry) -> Result<bool, String> {
+    let attachments_json =
+        serde_json::to_string(&entry.attachments).map_err(|e| format!("JSON encode error: {}", e))?;
+    let changed = conn
+        .execute(
+            "INSERT OR IGNORE INTO entries (id, role, text, timestamp, attachments, session)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                entry.id,
+                entry.role,
+                entry.text,
+                entry.timestamp,
+                attachments_json,
+                entry.session
+            ],
+        )
+        .map_err(|e| format!("Failed to save history: {}", e))?;
+    if changed == 0 {
+        return Ok(false);
+    }
+
+    let seq = conn.last_insert_rowid();
+    for (idx, attachment) in entry.attachments.iter().enumerate() {
+        let Some(hash) = attachment.hash.as_deref() else {
+            continue;
+        };
+        conn.execute(
+            "INSERT INTO entry_blobs (entry_seq, idx, hash) VALUES (?1, ?2, ?3)",
+            params![seq, idx as i64, hash],
+        )
+        .map_err(|e| format!("Failed to save attachment: {}", e))?;
+    }
+
+    Ok(true)
+}
+
+/// Finish or undo an encryption swap that was cut short. The key file is renamed into
+/// place last, so without it the `.plain` copy is still the real database.
+fn recover_interrupted_encryption(data_dir: &Path) {
+    let db_path = data_dir.join(HISTORY_DB_FILE);
+    let plain_path = db_path.with_extension("sqlite3.plain");
+    if !plain_path.exists() {
+        return;
+    }
+    if history_crypto::is_encrypted(data_dir) {
+        history_crypto::shred_file(&plain_path);
+    } else {
+        for suffix in ["sqlite3", "sqlite3-wal", "sqlite3-shm"] {
+            history_crypto::shred_file(&db_path.with_extension(suffix));
+        }
+        if let Err(e) = fs::rename(&plain_path, &db_path) {
+            eprintln!("[history] Failed to restore history: {}", e);
+            return;
+        }
+        eprintln!("[history] Restored history after an interrupted encryption");
+    }
+    history_crypto::shred_file(&db_path.with_extension("sqlite3.enc"));
+    history_crypto::shred_file(&history_crypto::staged_key_file_path(data_dir));
+}
+
+/// Entries saved before the attachment store kept payloads inline; move them into the store.
+fn move_inline_attachments(conn: &mut Connection, attachments: &AttachmentStore) -> Result<(), String> {
+    let rows: Vec<(i64, i64, Vec<u8>, String)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT a.entry_seq, a.idx, a.data, e.attachments FROM entry_attachments a
+                 JOIN entries e ON e.seq = a.entry_seq",
+            )
+            .map_err(|e| format!("Failed to read attachment: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read attachment: {}", e))?;
+        rows
+    };
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to upgrade history: {}", e))?;
+    for (seq, idx, data, attachments_json) in &rows {
+        let mut metadata: Vec<HistoryAttachment> =
+            serde_json::from_str(attachments_json).unwrap_or_default();
+        let mime_type = metadata
+            .get(*idx as usize)
+            .map(|a| a.mime_type.clone())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        let stored = attachments.put(&tx, data, &mime_type)?;
+
+        if let Some(attachment) = metadata.get_mut(*idx as usize) {
+            attachment.hash = Some(stored.hash.clone());
+        }
+        let updated_json = serde_json::to_string(&metadata)
+            .map_err(|e| format!("JSON encode error: {}", e))?;
+
+        tx.execute(
+            "INSERT OR REPLACE INTO entry_blobs (entry_seq, idx, hash) VALUES (?1, ?2, ?3)",
+            params![seq, idx, stored.hash],
+        )
+        .and_then(|_| {
+            tx.execute(
+                "UPDATE entries SET attachments = ?2 WHERE seq = ?1",
+                params![seq, updated_json],
+            )
+        })
+        .and_then(|_| {
+            tx.execute(
+                "DELETE FROM entry_attachments WHERE entry_seq = ?1 AND idx = ?2",
+                params![seq, idx],
+            )
+        })
+        .map_err(|e| format!("Failed to upgrade history: {}", e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to upgrade history: {}", e))?;
+
+    eprintln!("[history] Moved {} inline attachments into the store", rows.len());
+    Ok(())
+}
+
+impl HistoryStore {
+    fn new(data_dir: &Path, retention: RetentionPolicy, unavailable: Option<String>) -> Self {
+        Self {
+            data_dir: data_dir.to_path_buf(),
+            conn: Mutex::new(None),
+            attachments: AttachmentStore::new(
+                data_dir,
+                attachment_store::DEFAULT_ATTACHMENT_CAP_BYTES,
+            ),
+            retention,
+            last_retention: AtomicI64::new(0),
+            unavailable,
+        }
+    }
+
+    pub fn open(data_dir: &Path, retention: RetentionPolicy) -> Result<Self, String> {
+        fs::create_dir_all(data_dir)
+            .map_err(|e| format!("Failed to create history folder: {}", e))?;
+        recover_interrupted_encryption(data_dir);
+
+        let store = Self::new(data_dir, retention, None);
+        if history_crypto::is_encrypted(data_dir) {
+            eprintln!("[history] Encrypted history stays locked until the passphrase is entered");
+            return Ok(store);
+        }
+
+        let conn = open_connection(&store.db_path(), None, &store.attachments)?;
+        *store.slot()? = Some(conn);
+        if let Err(e) = store.run_retention() {
+            eprintln!("[history] Retention failed: {}", e);
+        }
+        Ok(store)
+    }
+
+    /// Store for when the database can't be opened: every call fails with `reason`, and
+    /// a panic wipe starts over with an empty database.
+    pub fn disabled(data_dir: &Path, retention: RetentionPolicy, reason: String) -> Self {
+        Self::new(data_dir, retention, Some(reason))
+    }
+
+    fn db_path(&self) -> PathBuf {
+        self.data_dir.join(HISTORY_DB_FILE)
+    }
+
+    fn slot(&self) -> Result<MutexGuard<'_, Option<Connection>>, String> {
+        self.conn
+            .lock()
+            .map_err(|_| "History store is unavailable".to_string())
+    }
+
+    fn lock(&self) -> Result<ConnGuard<'_>, String> {
+        let slot = self.slot()?;
+        if slot.is_none() {
+            return Err(match &self.unavailable {
+                Some(reason) => format!("History is unavailable: {}", reason),
+                None => LOCKED_ERROR.to_string(),
+            });
+        }
+        Ok(ConnGuard(slot))
+    }
+
+    // ---------- Retention ----------
+
+    /// Apply the retention policy now.
+    pub fn run_retention(&self) -> Result<RetentionResult, String> {
+        let now = now_ms();
+        self.last_retention.store(now, Ordering::Relaxed);
+
+        let mut conn = self.lock()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to update history: {}", e))?;
+        let mut result = RetentionResult::default();
+
+        if let Some(days) = self.retention.max_age_days {
+            result.expired_entries = tx
+                .execute(
+                    "DELETE FROM entries WHERE timestamp < ?1",
+                    params![days_before(now, days)],
+                )
+                .map_err(|e| format!("Failed to delete history: {}", e))?;
+        }
+        if let Some(days) = self.retention.attachment_days {
+            result.purged_attachments = purge_attachments_before(&tx, days_before(now, days))?;
+        }
+        if let Some(max_bytes) = self.retention.max_size_bytes {
+            result.trimmed_entries = trim_to_size(&tx, max_bytes)?;
+        }
+        result.removed_blobs = self
+            .attachments
+            .remove_unreferenced(&tx, now - ORPHAN_GRACE_MS)?;
+
+        tx.commit()
+            .map_err(|e| format!("Failed to update history: {}", e))?;
+
+        if result.expired_entries + result.trimmed_entries + result.purged_attachments
+            + result.removed_blobs
+            > 0
+        {
+            eprintln!("[history] Retention: {:?}", result);
+        }
+        Ok(result)
+    }
+
+    fn maybe_run_retention(&self) {
+        let last = self.last_retention.load(Ordering::Relaxed);
+        if now_ms() - last < RETENTION_INTERVAL_MS {
+            return;
+        }
+        if let Err(e) = self.run_retention() {
+            eprintln!("[history] Retention failed: {}", e);
+        }
+    }
+
+    // ---------- Encryption ----------
+
+    pub fn encryption_status(&self) -> Result<EncryptionStatus, String> {
+        Ok(EncryptionStatus {
+            encrypted: history_crypto::is_encrypted(&self.data_dir),
+            locked: self.slot()?.is_none(),
+        })
+    }
+
+    /// Re-encrypt the plaintext database with SQLCipher and seal stored attachments.
+    /// Until the encrypted copy is open, any failure leaves the plaintext history in place
+    /// and open.
+    pub fn enable_encryption(&self, passphrase: &str) -> Result<(), String> {
+        if history_crypto::is_encrypted(&self.data_dir) {
+            return Err("History is already encrypted".to_string());
+        }
+        let (key_file, keys) = history_crypto::create_keys(passphrase)?;
+
+        let mut slot = self.slot()?;
+        let conn = slot.as_ref().ok_or(LOCKED_ERROR)?;
+        let db_path = self.db_path();
+        let encrypted_path = db_path.with_extension("sqlite3.enc");
+        let plain_path = db_path.with_extension("sqlite3.plain");
+        history_crypto::shred_file(&encrypted_path);
+
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read history schema: {}", e))?;
+        let exported = conn
+            .execute(
+                "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+                params![
+                    encrypted_path.to_string_lossy(),
+                    keys.db_key_literal().as_str()
+                ],
+            )
+            .and_then(|_| conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(())))
+            .and_then(|_| {
+                conn.execute_batch(&format!(
+                    "PRAGMA encrypted.user_version = {}; DETACH DATABASE encrypted;",
+                    version
+                ))
+            })
+            .map_err(|e| format!("Failed to encrypt history: {}", e))
+            .and_then(|_| history_crypto::stage_key_file(&self.data_dir, &key_file));
+        if let Err(e) = exported {
+            let _ = conn.execute_batch("DETACH DATABASE encrypted;");
+            history_crypto::shred_file(&encrypted_path);
+            history_crypto::shred_file(&history_crypto::staged_key_file_path(&self.data_dir));
+            return Err(e);
+        }
+
+        // Closing the last connection checkpoints and removes the plaintext WAL
+        drop(slot.take());
+        self.attachments.set_cipher(Some(keys.file_cipher.clone()));
+        match self.swap_in_encrypted(&keys) {
+            Ok(conn) => *slot = Some(conn),
+            Err(e) => {
+                self.attachments.set_cipher(None);
+                match open_connection(&db_path, None, &self.attachments) {
+                    Ok(conn) => *slot = Some(conn),
+                    Err(reopen) => eprintln!("[history] Failed to reopen history: {}", reopen),
+                }
+                return Err(e);
+            }
+        }
+        history_crypto::shred_file(&plain_path);
+
+        // The database is encrypted from here on; a file that misses sealing is still
+        // readable, since plaintext attachment files pass through `history_crypto::open`
+        let sealed = self
+            .attachments
+            .seal_existing()
+            .map_err(|e| format!("History is encrypted, but sealing stored images failed: {}", e))?;
+        eprintln!("[history] Encryption enabled ({} attachment files sealed)", sealed);
+        Ok(())
+    }
+
+    /// Move the exported copy over the database, commit the key file and open it. On
+    /// failure the plaintext database is put back, so history is never left without its key.
+    fn swap_in_encrypted(&self, keys: &HistoryKeys) -> Result<Connection, String> {
+        let db_path = self.db_path();
+        let encrypted_path = db_path.with_extension("sqlite3.enc");
+        let plain_path = db_path.with_extension("sqlite3.plain");
+
+        fs::rename(&db_path, &plain_path).map_err(|e| format!("Failed to encrypt history: {}", e))?;
+        let swapped = fs::rename(&encrypted_path, &db_path)
+            .map_err(|e| format!("Failed to encrypt history: {}", e))
+            .and_then(|_| history_crypto::commit_key_file(&self.data_dir))
+            .and_then(|_| open_connection(&db_path, Some(keys), &self.attachments));
+        if swapped.is_err() {
+            // No other key file can exist: the store was plaintext when this started
+            history_crypto::shred_file(&history_crypto::key_file_path(&self.data_dir));
+            recover_interrupted_encryption(&self.data_dir);
+        }
+        swapped
+    }
+
+    pub fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        {
+            let mut slot = self.slot()?;
+            if slot.is_some() {
+                return Ok(());
+            }
+            let keys = history_crypto::load_keys(&self.data_dir, passphrase)?;
+            self.attachments.set_cipher(Some(keys.file_cipher.clone()));
+            match open_connection(&self.db_path(), Some(&keys), &self.attachments) {
+                Ok(conn) => *slot = Some(conn),
+                Err(e) => {
+                    self.attachments.set_cipher(None);
+                    return Err(e);
+                }
+            }
+        }
+        self.run_retention()?;
+        Ok(())
+    }
+
+    /// Close the database and forget the keys until the next unlock.
+    pub fn lock_store(&self) -> Result<(), String> {
+        if !history_crypto::is_encrypted(&self.data_dir) {
+            return Err("History is not encrypted".to_string());
+        }
+        self.slot()?.take();
+        self.attachments.set_cipher(None);
+        Ok(())
+    }
+
+    /// Delete every message, attachment, capture and the encryption key, then start over
+    /// with an empty plaintext store.
+    pub fn wipe(&self) -> Result<(), String> {
+        let mut slot = self.slot()?;
+        slot.take();
+        self.attachments.set_cipher(None);
+
+        let db_path = self.db_path();
+        for suffix in ["sqlite3", "sqlite3-wal", "sqlite3-shm", "sqlite3.enc", "sqlite3.plain"] {
+            history_crypto::shred_file(&db_path.with_extension(suffix));
+        }
+        history_crypto::shred_file(&history_crypto::key_file_path(&self.data_dir));
+        history_crypto::shred_file(&history_crypto::staged_key_file_path(&self.data_dir));
+        self.attachments.wipe()?;
+
+        *slot = Some(open_connection(&db_path, None, &self.attachments)?);
+        eprintln!("[history] Panic wipe completed");
+        Ok(())
+    }
+
+    pub fn append(
+        &self,
+        role: &str,
+        text: &str,
+        session: Option<String>,
+        attachments: Vec<HistoryAttachment>,
+    ) -> Result<HistoryEntry, String> {
+        let timestamp = now_ms();
+        let mut entry = HistoryEntry {
+            id: generate_id(timestamp),
+            role: role.to_string(),
+            text: text.trim().to_string(),
+            timestamp,
+            session,
+            attachments,
+        };
+        let mut conn = self.lock()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to save history: {}", e))?;
+        store_attachment_payloads(&tx, &self.attachments, &mut entry)?;
+        insert_entry(&tx, &entry)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to save history: {}", e))?;
+        if !entry.attachments.is_empty() {
+            self.attachments.evict_over_cap(&conn)?;
+        }
+        drop(conn);
+        self.maybe_run_retention();
+        Ok(entry)
+    }
+
+    /// Newest-first paging: `offset` counts entries back from the latest one.
+    pub fn page(&self, offset: u32, limit: u32) -> Result<HistoryPage, String> {
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+        let conn = self.lock()?;
+
+        let total: i64 = conn
+            .query_row("SELECT COUNT(*) FROM entries", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+
+        let mut stmt = conn
+            .prepare(
+                &format!(
+                    "SELECT {} FROM entries ORDER BY timestamp DESC, seq DESC LIMIT ?1 OFFSET ?2",
+                    ENTRY_COLUMNS
+                ),
+            )
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        let mut entries = stmt
+            .query_map(params![limit, offset], row_to_entry)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        entries.reverse();
+
+        let total = total.max(0) as usize;
+        Ok(HistoryPage {
+            has_more: (offset as usize + entries.len()) < total,
+            entries,
+            total,
+        })
+    }
+
+    pub fn search(&self, query: &str, limit: u32) -> Result<Vec<HistorySearchHit>, String> {
+        let Some(match_expr) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+        let conn = self.lock()?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT e.id, e.role, e.text, e.timestamp, e.attachments, e.session,
+                        snippet(entries_fts, 0, '**', '**', '...', 16)
+                 FROM entries_fts
+                 JOIN entries e ON e.seq = entries_fts.rowid
+                 WHERE entries_fts MATCH ?1
+                 ORDER BY bm25(entries_fts), e.timestamp DESC
+                 LIMIT ?2",
+            )
+            .map_err(|e| format!("Failed to search history: {}", e))?;
+
+        let hits = stmt
+            .query_map(params![match_expr, limit], |row| {
+                Ok(HistorySearchHit {
+                    entry: row_to_entry(row)?,
+                    snippet: row.get(6)?,
+                })
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to search history: {}", e))?;
+
+        Ok(hits)
+    }
+
+    pub fn delete(&self, ids: &[String]) -> Result<usize, String> {
+        let mut conn = self.lock()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to delete history: {}", e))?;
+        let mut removed = 0usize;
+        for id in ids {
+            removed += tx
+                .execute("DELETE FROM entries WHERE id = ?1", params![id])
+                .map_err(|e| format!("Failed to delete history: {}", e))?;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to delete history: {}", e))?;
+        Ok(removed)
+    }
+
+    fn all_entries(conn: &Connection) -> Result<Vec<HistoryEntry>, String> {
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM entries", ENTRY_COLUMNS))
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        let entries = stmt
+            .query_map([], row_to_entry)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        Ok(entries)
+    }
+
+    /// Insert entries that are not already stored (see [`select_new_entries`]).
+    pub fn merge(&self, incoming: Vec<HistoryEntry>) -> Result<usize, String> {
+        let mut conn = self.lock()?;
+        let existing = Self::all_entries(&conn)?;
+        let fresh = select_new_entries(&existing, incoming);
+
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to save history: {}", e))?;
+        let mut added = 0usize;
+        for mut entry in fresh {
+            store_attachment_payloads(&tx, &self.attachments, &mut entry)?;
+            if insert_entry(&tx, &entry)? {
+                added += 1;
+            }
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to save history: {}", e))?;
+        self.attachments.evict_over_cap(&conn)?;
+        Ok(added)
+    }
+
+    /// Entries in `[from, to]` (either bound optional), optionally limited to one session,
+    /// oldest first and with attachment payloads loaded.
+    pub fn entries_for_export(
+        &self,
+        from: Option<i64>,
+        to: Option<i64>,
+        session: Option<&str>,
+    ) -> Result<Vec<HistoryEntry>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM entries
+                 WHERE timestamp >= ?1 AND timestamp <= ?2 AND (?3 IS NULL OR session = ?3)
+                 ORDER BY timestamp, seq",
+                ENTRY_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        let mut entries = stmt
+            .query_map(
+                params![from.unwrap_or(i64::MIN), to.unwrap_or(i64::MAX), session],
+                row_to_entry,
+            )
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+
+        for attachment in entries.iter_mut().flat_map(|e| e.attachments.iter_mut()) {
+            let Some(hash) = attachment.hash.as_deref() else {
+                continue;
+            };
+            // Evicted originals simply export without their payload
+            if let Ok((data, _)) = self.attachments.read_original(&conn, hash) {
+                attachment.data = Some(data);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Save a freshly captured/read image so later history entries can reference it by hash.
+    pub fn put_attachment(&self, bytes: &[u8], mime_type: &str) -> Result<StoredAttachment, String> {
+        let conn = self.lock()?;
+        let stored = self.attachments.put(&conn, bytes, mime_type)?;
+        self.attachments.evict_over_cap(&conn)?;
+        Ok(stored)
+    }
+
+    pub fn attachment_original(&self, hash: &str) -> Result<(Vec<u8>, String), String> {
+        let conn = self.lock()?;
+        self.attachments.read_original(&conn, hash)
+    }
+
+    pub fn attachment_thumbnail(&self, hash: &str) -> Result<Vec<u8>, String> {
+        let conn = self.lock()?;
+        self.attachments.read_thumbnail(&conn, hash)
+    }
+
+    fn meta_get(conn: &Connection, key: &str) -> Result<Option<String>, String> {
+        conn.query_row("SELECT value FROM meta WHERE key = ?1", params![key], |row| {
+            row.get(0)
+        })
+        .optional()
+        .map_err(|e| format!("Failed to read history: {}", e))
+    }
+
+    fn meta_set(conn: &Connection, key: &str, value: &str) -> Result<(), String> {
+        conn.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )
+        .map(|_| ())
+        .map_err(|e| format!("Failed to save history: {}", e))
+    }
+
+    /// One-time import of the old webview localStorage history.
+    pub fn migrate_local_storage(
+        &self,
+        entries: Vec<HistoryEntry>,
+    ) -> Result<HistoryMigrationResult, String> {
+        {
+            let conn = self.lock()?;
+            if Self::meta_get(&conn, LOCAL_STORAGE_MIGRATED_KEY)?.is_some() {
+                return Ok(HistoryMigrationResult {
+                    migrated: 0,
+                    already_migrated: true,
+                });
+            }
+        }
+
+        let migrated = self.merge(entries)?;
+
+        let conn = self.lock()?;
+        Self::meta_set(&conn, LOCAL_STORAGE_MIGRATED_KEY, &now_ms().to_string())?;
+
+        Ok(HistoryMigrationResult {
+            migrated,
+            already_migrated: false,
+        })
+    }
+}
+
+// ---------- Tauri commands ----------
+
+#[tauri::command]
+pub async fn history_append(
+    store: tauri::State<'_, HistoryStore>,
+    role: String,
+    text: String,
+    session: Option<String>,
+    attachments: Option<Vec<NewHistoryAttachment>>,
+) -> Result<HistoryEntry, String> {
+    if role != "user" && role != "assistant" {
+        return Err("Invalid history role".to_string());
+    }
+
+    use base64::Engine;
+    let mut decoded = Vec::new();
+    for attachment in attachments.unwrap_or_default() {
+        let (hash, data) = match (attachment.hash, attachment.base64) {
+            (Some(hash), _) => (Some(hash), None),
+            (None, Some(b64)) => {
+                let data = base64::engine::general_purpose::STANDARD
+                    .decode(b64.trim())
+                    .map_err(|e| format!("Invalid attachment data: {}", e))?;
+                (None, Some(data))
+            }
+            (None, None) => return Err("Attachment has no data".to_string()),
+        };
+        decoded.push(HistoryAttachment {
+            mime_type: attachment.mime_type,
+            file_name: attachment.file_name,
+            size_bytes: data.as_ref().map(|d| d.len()).unwrap_or(0),
+            hash,
+            data,
+        });
+    }
+
+    if text.trim().is_empty() && decoded.is_empty() {
+        return Err("History entry is empty".to_string());
+    }
+    store.append(&role, &text, session, decoded)
+}
+
+#[tauri::command]
+pub async fn history_page(
+    store: tauri::State<'_, HistoryStore>,
+    offset: Option<u32>,
+    limit: Option<u32>,
+) -> Result<HistoryPage, String> {
+    store.page(offset.unwrap_or(0), limit.unwrap_or(100))
+}
+
+#[tauri::command]
+pub async fn history_search(
+    store: tauri::State<'_, HistoryStore>,
+    query: String,
+    limit: Option<u32>,
+) -> Result<Vec<HistorySearchHit>, String> {
+    store.search(&query, limit.unwrap_or(50))
+}
+
+#[tauri::command]
+pub async fn history_delete(
+    store: tauri::State<'_, HistoryStore>,
+    ids: Vec<String>,
+) -> Result<usize, String> {
+    store.delete(&ids)
+}
+
+#[tauri::command]
+pub async fn history_migrate_local(
+    store: tauri::State<'_, HistoryStore>,
+    entries: Vec<HistoryEntry>,
+) -> Result<HistoryMigrationResult, String> {
+    store.migrate_local_storage(entries)
+}
+
+#[tauri::command]
+pub async fn history_apply_retention(
+    store: tauri::State<'_, HistoryStore>,
+) -> Result<RetentionResult, String> {
+    store.run_retention()
+}
+
+#[tauri::command]
+pub async fn history_encryption_status(
+    store: tauri::State<'_, HistoryStore>,
+) -> Result<EncryptionStatus, String> {
+    store.encryption_status()
+}
+
+#[tauri::command]
+pub async fn history_enable_encryption(
+    store: tauri::State<'_, HistoryStore>,
+    passphrase: String,
+) -> Result<(), String> {
+    store.enable_encryption(&passphrase)
+}
+
+#[tauri::command]
+pub async fn history_unlock(
+    store: tauri::State<'_, HistoryStore>,
+    passphrase: String,
+) -> Result<(), String> {
+    store.unlock(&passphrase)
+}
+
+#[tauri::command]
+pub async fn history_lock(store: tauri::State<'_, HistoryStore>) -> Result<(), String> {
+    store.lock_store()
+}
+
+/// Delete all history, stored captures and the capture files ClawPet saved, in one go.
+#[tauri::command]
+pub async fn panic_wipe(app: tauri::AppHandle, store: tauri::State<'_, HistoryStore>) -> Result<(), String> {
+    store.wipe()?;
+    crate::capture_save::wipe(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, role: &str, text: &str, timestamp: i64) -> HistoryEntry {
+        HistoryEntry {
+            id: id.to_string(),
+            role: role.to_string(),
+            text: text.to_string(),
+            timestamp,
+            session: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    /// Store in its own folder under the system temp dir.
+    fn open_store(name: &str, retention: RetentionPolicy) -> (HistoryStore, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("clawpet-history-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        (HistoryStore::open(&dir, retention).unwrap(), dir)
+    }
+
+    fn png(seed: u8) -> Vec<u8> {
+        let image = image::RgbaImage::from_pixel(8, 8, image::Rgba([seed, 10, 20, 255]));
+        let mut bytes = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn with_image(id: &str, timestamp: i64, seed: u8) -> HistoryEntry {
+        HistoryEntry {
+            attachments: vec![HistoryAttachment {
+                mime_type: "image/png".into(),
+                file_name: None,
+                size_bytes: 0,
+                hash: None,
+                data: Some(png(seed)),
+            }],
+            ..entry(id, "user", "look at this", timestamp)
+        }
+    }
+
+    #[test]
+    fn migrations_run_once_and_leave_the_current_schema() {
+        let mut conn = memory_db();
+        // A second open must not re-run (and fail on) `ALTER TABLE`
+        run_migrations(&mut conn).unwrap();
+
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len() as i64);
+        for table in ["entries", "entries_fts", "meta", "entry_attachments", "blobs", "entry_blobs"] {
+            let found: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM sqlite_master WHERE name = ?1",
+                    params![table],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(found, 1, "missing {table}");
+        }
+        insert_entry(&conn, &HistoryEntry { session: Some("main".into()), ..entry("a", "user", "hi", 1) }).unwrap();
+        let session: Option<String> = conn
+            .query_row("SELECT session FROM entries WHERE id = 'a'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(session.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn new_entries_skip_known_ids_and_nearby_duplicates() {
+        let existing = vec![entry("a", "user", "hello   world", 1_000_000)];
+        let incoming = vec![
+            entry("a", "user", "edited", 5_000_000),
+            // Same message, re-sent a minute later
+            entry("b", "user", "hello world", 1_060_000),
+            entry("c", "user", "hello world", 1_000_000 + 10 * 60 * 1000),
+            entry("d", "assistant", "hello world", 1_000_000),
+            // Duplicate of `c` within the incoming batch itself
+            entry("e", "user", " hello world ", 1_000_000 + 10 * 60 * 1000 + 1000),
+        ];
+        let ids: Vec<String> = select_new_entries(&existing, incoming)
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, ["c", "d"]);
+    }
+
+    #[test]
+    fn search_terms_are_quoted_for_fts() {
+        assert_eq!(fts_query("   "), None);
+        assert_eq!(fts_query(r#"say "hi" -x:y"#).as_deref(), Some(r#""say" """hi""" "-x:y""#));
+
+        let conn = memory_db();
+        insert_entry(&conn, &entry("a", "user", r#"run rm -rf /tmp and say "hi" to col:umn"#, 1)).unwrap();
+        insert_entry(&conn, &entry("b", "user", "nothing to see", 2)).unwrap();
+        let count = |query: &str| -> i64 {
+            conn.query_row(
+                "SELECT COUNT(*) FROM entries_fts WHERE entries_fts MATCH ?1",
+                params![fts_query(query).unwrap()],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        // Raw, these would be FTS5 syntax errors (NOT, column filters, unbalanced quotes)
+        assert_eq!(count("-rf"), 1);
+        assert_eq!(count("col:umn"), 1);
+        assert_eq!(count(r#""hi"#), 1);
+        assert_eq!(count("AND OR NOT"), 0);
+    }
+
+    #[test]
+    fn retention_expires_old_entries_and_drops_old_attachments() {
+        let now = now_ms();
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            attachment_days: Some(7),
+            max_size_bytes: None,
+        };
+        let (store, dir) = open_store("retention", policy);
+        store
+            .merge(vec![
+                entry("old", "user", "ancient", days_before(now, 40)),
+                with_image("week", days_before(now, 10), 1),
+                with_image("new", now - 1000, 2),
+            ])
+            .unwrap();
+
+        let result = store.run_retention().unwrap();
+        assert_eq!(result.expired_entries, 1);
+        assert_eq!(result.purged_attachments, 1);
+        let entries = store.page(0, 10).unwrap().entries;
+        let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["week", "new"]);
+        // The text and what was attached stay; only the payload goes
+        assert_eq!(entries[0].attachments[0].mime_type, "image/png");
+        assert!(entries[0].attachments[0].hash.is_none());
+        assert!(entries[1].attachments[0].hash.is_some());
+
+        drop(store);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn size_limit_trims_the_oldest_entries_first() {
+        let conn = memory_db();
+        for (i, id) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            // 100 bytes of text + `[]` per entry
+            insert_entry(&conn, &entry(id, "user", &"x".repeat(100), i as i64)).unwrap();
+        }
+        assert_eq!(trim_to_size(&conn, 1000).unwrap(), 0);
+        assert_eq!(trim_to_size(&conn, 300).unwrap(), 3);
+        let ids: Vec<String> = HistoryStore::all_entries(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, ["d", "e"]);
+    }
+
+    #[test]
+    fn encryption_locks_unlocks_and_rejects_wrong_passphrases() {
+        let (store, dir) = open_store("encrypt", RetentionPolicy::default());
+        store.merge(vec![with_image("a", now_ms(), 5), entry("b", "user", "secret dashboard", now_ms())]).unwrap();
+        assert!(store.enable_encryption("short").is_err());
+        store.enable_encryption("correct horse battery").unwrap();
+        assert!(store.enable_encryption("correct horse battery").is_err());
+
+        let raw = fs::read(dir.join(HISTORY_DB_FILE)).unwrap();
+        assert!(!raw.windows(9).any(|w| w == b"dashboard"));
+        assert!(!dir.join("history.sqlite3.plain").exists());
+        assert_eq!(store.search("dashboard", 10).unwrap().len(), 1);
+
+        store.lock_store().unwrap();
+        assert_eq!(store.page(0, 10).err().unwrap(), LOCKED_ERROR);
+        drop(store);
+
+        let store = HistoryStore::open(&dir, RetentionPolicy::default()).unwrap();
+        assert!(store.encryption_status().unwrap().locked);
+        assert_eq!(store.unlock("wrong passphrase").unwrap_err(), "Wrong passphrase");
+        assert!(store.encryption_status().unwrap().locked);
+        store.unlock("correct horse battery").unwrap();
+        let entries = store.page(0, 10).unwrap().entries;
+        assert_eq!(entries.len(), 2);
+        let hash = entries[0].attachments[0].hash.clone().unwrap();
+        assert_eq!(store.attachment_original(&hash).unwrap().0, png(5));
+
+        drop(store);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn failed_encryption_keeps_the_plaintext_history_open() {
+        let (store, dir) = open_store("encrypt-fail", RetentionPolicy::default());
+        store.merge(vec![entry("a", "user", "keep me", now_ms())]).unwrap();
+        // A folder where the key file goes makes the last step of the swap fail
+        fs::create_dir_all(history_crypto::key_file_path(&dir)).unwrap();
+
+        assert!(store.enable_encryption("correct horse battery").is_err());
+        assert!(!store.encryption_status().unwrap().locked);
+        assert_eq!(store.page(0, 10).unwrap().total, 1);
+        for leftover in ["history.sqlite3.plain", "history.sqlite3.enc", "history.key.json.tmp"] {
+            assert!(!dir.join(leftover).exists(), "{leftover} left behind");
+        }
+        drop(store);
+
+        fs::remove_dir(history_crypto::key_file_path(&dir)).unwrap();
+        let store = HistoryStore::open(&dir, RetentionPolicy::default()).unwrap();
+        assert_eq!(store.page(0, 10).unwrap().entries[0].text, "keep me");
+        drop(store);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn interrupted_encryption_is_rolled_back_on_open() {
+        let (store, dir) = open_store("encrypt-crash", RetentionPolicy::default());
+        store.merge(vec![entry("a", "user", "keep me", now_ms())]).unwrap();
+        drop(store);
+        // Crash after the encrypted copy was moved in, before the key file was
+        let db_path = dir.join(HISTORY_DB_FILE);
+        fs::rename(&db_path, db_path.with_extension("sqlite3.plain")).unwrap();
+        fs::write(&db_path, b"not a plaintext database").unwrap();
+
+        let store = HistoryStore::open(&dir, RetentionPolicy::default()).unwrap();
+        assert_eq!(store.page(0, 10).unwrap().total, 1);
+        drop(store);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn panic_wipe_resets_an_encrypted_or_broken_store() {
+        let (store, dir) = open_store("wipe", RetentionPolicy::default());
+        store.merge(vec![with_image("a", now_ms(), 6)]).unwrap();
+        store.enable_encryption("correct horse battery").unwrap();
+        store.lock_store().unwrap();
+
+        store.wipe().unwrap();
+        let status = store.encryption_status().unwrap();
+        assert!(!status.encrypted && !status.locked);
+        assert_eq!(store.page(0, 10).unwrap().total, 0);
+        assert!(!dir.join("attachments").exists());
+        drop(store);
+
+        // A database that can't be opened leaves a disabled store until the next wipe
+        fs::write(dir.join(HISTORY_DB_FILE), b"garbage, not sqlite at all").unwrap();
+        let reason = HistoryStore::open(&dir, RetentionPolicy::default()).err().unwrap();
+        let store = HistoryStore::disabled(&dir, RetentionPolicy::default(), reason);
+        assert!(store.page(0, 10).err().unwrap().starts_with("History is unavailable"));
+        store.wipe().unwrap();
+        assert_eq!(store.page(0, 10).unwrap().total, 0);
+        drop(store);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,200 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use zeroize::Zeroizing;
+
+const KEY_FILE: &str = "history.key.json";
+const KEY_FILE_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+// Argon2id defaults (OWASP baseline): 64MB memory, 3 passes.
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const KDF_ITERATIONS: u32 = 3;
+const KDF_PARALLELISM: u32 = 1;
+
+/// Prefix of attachment files written while encryption is on; anything else is plaintext.
+const SEALED_MAGIC: &[u8; 4] = b"CPE1";
+const NONCE_LEN: usize = 24;
+
+/// KDF parameters stored next to the database. Holds no secret: the passphrase is
+/// only checked by whether SQLCipher can read the database with the derived key.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyFile {
+    version: u32,
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+/// Keys derived from the passphrase: one for SQLCipher, one for attachment files.
+pub struct HistoryKeys {
+    db_key_hex: Zeroizing<String>,
+    pub file_cipher: XChaCha20Poly1305,
+}
+
+impl HistoryKeys {
+    /// Raw-key form understood by `PRAGMA key` / `ATTACH ... KEY`.
+    pub fn db_key_literal(&self) -> Zeroizing<String> {
+        Zeroizing::new(format!("x'{}'", self.db_key_hex.as_str()))
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+pub fn key_file_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(KEY_FILE)
+}
+
+pub fn is_encrypted(data_dir: &Path) -> bool {
+    key_file_path(data_dir).is_file()
+}
+
+fn derive_keys(passphrase: &str, key_file: &KeyFile) -> Result<HistoryKeys, String> {
+    let salt = from_hex(&key_file.salt).ok_or("History key file is corrupted")?;
+    let params = Params::new(
+        key_file.memory_kib,
+        key_file.iterations,
+        key_file.parallelism,
+        Some(64),
+    )
+    .map_err(|e| format!("Invalid key parameters: {}", e))?;
+
+    let mut output = Zeroizing::new([0u8; 64]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, output.as_mut())
+        .map_err(|e| format!("Failed to derive history key: {}", e))?;
+
+    let file_cipher = XChaCha20Poly1305::new_from_slice(&output[32..])
+        .map_err(|_| "Failed to derive history key".to_string())?;
+    Ok(HistoryKeys {
+        db_key_hex: Zeroizing::new(to_hex(&output[..32])),
+        file_cipher,
+    })
+}
+
+/// Fresh salt + keys for a new passphrase. The key file is written by the caller once the
+/// database has actually been re-encrypted.
+pub fn create_keys(passphrase: &str) -> Result<(KeyFile, HistoryKeys), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(format!(
+            "Passphrase must be at least {} characters",
+            MIN_PASSPHRASE_CHARS
+        ));
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key_file = KeyFile {
+        version: KEY_FILE_VERSION,
+        salt: to_hex(&salt),
+        memory_kib: KDF_MEMORY_KIB,
+        iterations: KDF_ITERATIONS,
+        parallelism: KDF_PARALLELISM,
+    };
+    let keys = derive_keys(passphrase, &key_file)?;
+    Ok((key_file, keys))
+}
+
+pub fn load_keys(data_dir: &Path, passphrase: &str) -> Result<HistoryKeys, String> {
+    let raw = fs::read_to_string(key_file_path(data_dir))
+        .map_err(|e| format!("Failed to read history key file: {}", e))?;
+    let key_file: KeyFile =
+        serde_json::from_str(&raw).map_err(|e| format!("History key file is corrupted: {}", e))?;
+    if key_file.version != KEY_FILE_VERSION {
+        return Err("Unsupported history key file version".to_string());
+    }
+    derive_keys(passphrase, &key_file)
+}
+
+/// Where the key file is written before `commit_key_file` moves it into place.
+pub fn staged_key_file_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(format!("{}.tmp", KEY_FILE))
+}
+
+/// Write the key file under a temporary name. Nothing counts as encrypted until
+/// `commit_key_file` renames it.
+pub fn stage_key_file(data_dir: &Path, key_file: &KeyFile) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(key_file)
+        .map_err(|e| format!("JSON encode error: {}", e))?;
+    fs::write(staged_key_file_path(data_dir), json)
+        .map_err(|e| format!("Failed to save history key file: {}", e))
+}
+
+pub fn commit_key_file(data_dir: &Path) -> Result<(), String> {
+    fs::rename(staged_key_file_path(data_dir), key_file_path(data_dir))
+        .map_err(|e| format!("Failed to save history key file: {}", e))
+}
+
+// ---------- Attachment files ----------
+
+pub fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.len() >= SEALED_MAGIC.len() + NONCE_LEN && bytes.starts_with(SEALED_MAGIC)
+}
+
+/// `CPE1 || nonce || ciphertext+tag`
+pub fn seal(cipher: &XChaCha20Poly1305, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "Failed to encrypt attachment".to_string())?;
+    let mut out = Vec::with_capacity(SEALED_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(SEALED_MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Decrypt a sealed file; plaintext files (written before encryption was enabled) pass through.
+pub fn open(cipher: Option<&XChaCha20Poly1305>, bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    if !is_sealed(&bytes) {
+        return Ok(bytes);
+    }
+    let cipher = cipher.ok_or("History is locked")?;
+    let (nonce, ciphertext) = bytes[SEALED_MAGIC.len()..].split_at(NONCE_LEN);
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt attachment".to_string())
+}
+
+/// Overwrite a file with zeros before removing it. Best effort: SSDs and copy-on-write
+/// filesystems may still keep old blocks around.
+pub fn shred_file(path: &Path) {
+    if let Ok(meta) = fs::metadata(path) {
+        if let Ok(mut file) = fs::OpenOptions::new().write(true).open(path) {
+            let zeros = vec![0u8; 64 * 1024];
+            let mut remaining = meta.len();
+            while remaining > 0 {
+                let n = remaining.min(zeros.len() as u64) as usize;
+                if file.write_all(&zeros[..n]).is_err() {
+                    break;
+                }
+                remaining -= n as u64;
+            }
+            let _ = file.sync_all();
+        }
+    }
+    let _ = fs::remove_file(path);
+}
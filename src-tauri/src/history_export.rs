@@ -0,0 +1,492 @@
+use crate::history::{HistoryAttachment, HistoryEntry, HistoryStore};
+use crate::obsidian_clipper::unique_path;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Jsonl,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRequest {
+    pub format: ExportFormat,
+    /// Inclusive range in epoch milliseconds.
+    #[serde(default)]
+    pub from: Option<i64>,
+    #[serde(default)]
+    pub to: Option<i64>,
+    /// Gateway session key (or transcript id for imported sessions).
+    #[serde(default)]
+    pub session: Option<String>,
+    /// Output file; defaults to `Downloads/ClawPet/clawpet-history-<time>.<ext>`.
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub path: String,
+    pub entries: usize,
+    pub attachments: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonlAttachment<'a> {
+    mime_type: &'a str,
+    file_name: Option<&'a str>,
+    size_bytes: usize,
+    base64: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonlEntry<'a> {
+    id: &'a str,
+    role: &'a str,
+    text: &'a str,
+    timestamp: i64,
+    session: Option<&'a str>,
+    attachments: Vec<JsonlAttachment<'a>>,
+}
+
+// ---------- Helpers ----------
+
+fn default_export_dir() -> PathBuf {
+    dirs::download_dir()
+        .or_else(dirs::document_dir)
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("ClawPet")
+}
+
+fn format_local_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(timestamp)
+        .map(|dt| {
+            dt.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+fn role_label(role: &str) -> &'static str {
+    if role == "user" {
+        "You"
+    } else {
+        "Assistant"
+    }
+}
+
+fn extension_for_mime(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        "application/pdf" => "pdf",
+        "text/plain" => "txt",
+        _ => "bin",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn is_image(mime_type: &str) -> bool {
+    mime_type.starts_with("image/")
+}
+
+/// Name shown for a non-image attachment.
+fn attachment_label(attachment: &HistoryAttachment) -> String {
+    attachment
+        .file_name
+        .clone()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| format!("attachment.{}", extension_for_mime(&attachment.mime_type)))
+}
+
+/// Markdown link destination that survives spaces and parentheses in the export's name.
+fn markdown_target(target: &str) -> String {
+    let mut out = String::with_capacity(target.len() + 2);
+    out.push('<');
+    for c in target.chars() {
+        if matches!(c, '<' | '>' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('>');
+    out
+}
+
+fn describe_range(request: &ExportRequest, entries: &[HistoryEntry]) -> String {
+    let first = request
+        .from
+        .or_else(|| entries.first().map(|e| e.timestamp))
+        .map(format_local_time)
+        .unwrap_or_default();
+    let last = request
+        .to
+        .or_else(|| entries.last().map(|e| e.timestamp))
+        .map(format_local_time)
+        .unwrap_or_default();
+    let mut summary = format!("{} messages, {} – {}", entries.len(), first, last);
+    if let Some(session) = &request.session {
+        summary.push_str(&format!(", session `{}`", session));
+    }
+    summary
+}
+
+// ---------- Writers ----------
+
+/// Markdown with attachments written next to it in `<name>_files/`.
+fn write_markdown(
+    path: &Path,
+    request: &ExportRequest,
+    entries: &[HistoryEntry],
+) -> Result<usize, String> {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("clawpet-history")
+        .to_string();
+    let files_dir_name = format!("{}_files", stem);
+    let files_dir = path.with_file_name(&files_dir_name);
+
+    let mut md = String::new();
+    md.push_str("# ClawPet conversation\n\n");
+    md.push_str(&format!("_{}_\n\n", describe_range(request, entries)));
+
+    let mut written = 0usize;
+    for (n, entry) in entries.iter().enumerate() {
+        md.push_str(&format!(
+            "### {} · {}\n\n",
+            role_label(&entry.role),
+            format_local_time(entry.timestamp)
+        ));
+        if !entry.text.is_empty() {
+            md.push_str(&entry.text);
+            md.push_str("\n\n");
+        }
+
+        for (i, attachment) in entry.attachments.iter().enumerate() {
+            let Some(data) = attachment.data.as_deref() else {
+                md.push_str(&format!("_[attachment not stored: {}]_\n\n", attachment.mime_type));
+                continue;
+            };
+            if written == 0 {
+                fs::create_dir_all(&files_dir)
+                    .map_err(|e| format!("Failed to create export folder: {}", e))?;
+            }
+            let name = format!(
+                "{:04}-{}.{}",
+                n + 1,
+                i + 1,
+                extension_for_mime(&attachment.mime_type)
+            );
+            fs::write(files_dir.join(&name), data)
+                .map_err(|e| format!("Failed to save attachment: {}", e))?;
+            let target = markdown_target(&format!("{}/{}", files_dir_name, name));
+            if is_image(&attachment.mime_type) {
+                md.push_str(&format!("![attachment]({})\n\n", target));
+            } else {
+                md.push_str(&format!("[{}]({})\n\n", attachment_label(attachment), target));
+            }
+            written += 1;
+        }
+    }
+
+    fs::write(path, md.as_bytes()).map_err(|e| format!("Failed to save export: {}", e))?;
+    Ok(written)
+}
+
+/// Single self-contained page: images inlined as data URLs, other attachments as
+/// download links.
+fn write_html(
+    path: &Path,
+    request: &ExportRequest,
+    entries: &[HistoryEntry],
+) -> Result<usize, String> {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\n");
+    html.push_str("<title>ClawPet conversation</title>\n<style>\n");
+    html.push_str(
+        "body{font-family:system-ui,sans-serif;background:#f0dfb8;color:#3b2a0b;max-width:820px;margin:24px auto;padding:0 16px}\n\
+         .meta{color:#6b5822;font-size:13px}\n\
+         .msg{border:2px solid #2a2f45;padding:10px 12px;margin:12px 0;box-shadow:2px 2px 0 rgba(30,34,53,.35)}\n\
+         .user{background:#d5f0d2;margin-left:12%}\n\
+         .assistant{background:#f7f0dd;margin-right:12%}\n\
+         .who{font-size:12px;color:#6b5822;margin-bottom:6px}\n\
+         .text{white-space:pre-wrap;word-break:break-word}\n\
+         img{max-width:100%;margin-top:8px;border:1px solid #7d5a14}\n",
+    );
+    html.push_str("</style></head><body>\n");
+    html.push_str("<h1>ClawPet conversation</h1>\n");
+    html.push_str(&format!(
+        "<p class=\"meta\">{}</p>\n",
+        escape_html(&describe_range(request, entries))
+    ));
+
+    let mut inlined = 0usize;
+    for entry in entries {
+        let class = if entry.role == "user" { "user" } else { "assistant" };
+        html.push_str(&format!("<div class=\"msg {}\">\n", class));
+        html.push_str(&format!(
+            "<div class=\"who\">{} · {}</div>\n",
+            role_label(&entry.role),
+            escape_html(&format_local_time(entry.timestamp))
+        ));
+        if !entry.text.is_empty() {
+            html.push_str(&format!(
+                "<div class=\"text\">{}</div>\n",
+                escape_html(&entry.text)
+            ));
+        }
+        for attachment in &entry.attachments {
+            match attachment.data.as_deref() {
+                Some(data) => {
+                    let b64 = base64::engine::general_purpose::STANDARD.encode(data);
+                    if is_image(&attachment.mime_type) {
+                        html.push_str(&format!(
+                            "<img alt=\"attachment\" src=\"data:{};base64,{}\">\n",
+                            escape_html(&attachment.mime_type),
+                            b64
+                        ));
+                    } else {
+                        let label = escape_html(&attachment_label(attachment));
+                        html.push_str(&format!(
+                            "<div class=\"meta\"><a download=\"{}\" href=\"data:{};base64,{}\">{}</a> ({})</div>\n",
+                            label,
+                            escape_html(&attachment.mime_type),
+                            b64,
+                            label,
+                            escape_html(&attachment.mime_type)
+                        ));
+                    }
+                    inlined += 1;
+                }
+                None => html.push_str(&format!(
+                    "<div class=\"meta\">[attachment not stored: {}]</div>\n",
+                    escape_html(&attachment.mime_type)
+                )),
+            }
+        }
+        html.push_str("</div>\n");
+    }
+
+    html.push_str("</body></html>\n");
+    fs::write(path, html.as_bytes()).map_err(|e| format!("Failed to save export: {}", e))?;
+    Ok(inlined)
+}
+
+/// One JSON object per line, attachments as base64.
+fn write_jsonl(path: &Path, entries: &[HistoryEntry]) -> Result<usize, String> {
+    let mut out = String::new();
+    let mut included = 0usize;
+
+    for entry in entries {
+        let attachments: Vec<JsonlAttachment<'_>> = entry
+            .attachments
+            .iter()
+            .map(|a| JsonlAttachment {
+                mime_type: &a.mime_type,
+                file_name: a.file_name.as_deref(),
+                size_bytes: a.size_bytes,
+                base64: a
+                    .data
+                    .as_deref()
+                    .map(|d| base64::engine::general_purpose::STANDARD.encode(d)),
+            })
+            .collect();
+        included += attachments.iter().filter(|a| a.base64.is_some()).count();
+
+        let line = serde_json::to_string(&JsonlEntry {
+            id: &entry.id,
+            role: &entry.role,
+            text: &entry.text,
+            timestamp: entry.timestamp,
+            session: entry.session.as_deref(),
+            attachments,
+        })
+        .map_err(|e| format!("JSON encode error: {}", e))?;
+        out.push_str(&line);
+        out.push('\n');
+    }
+
+    fs::write(path, out.as_bytes()).map_err(|e| format!("Failed to save export: {}", e))?;
+    Ok(included)
+}
+
+// ---------- Main entry point ----------
+
+#[tauri::command]
+pub async fn export_history(
+    store: tauri::State<'_, HistoryStore>,
+    request: ExportRequest,
+) -> Result<ExportResult, String> {
+    if let (Some(from), Some(to)) = (request.from, request.to) {
+        if from > to {
+            return Err("Export range is empty".to_string());
+        }
+    }
+
+    let entries = store.entries_for_export(request.from, request.to, request.session.as_deref())?;
+    if entries.is_empty() {
+        return Err("No messages in the selected range".to_string());
+    }
+
+    let path = match request.path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(p) => PathBuf::from(p),
+        None => {
+            let dir = default_export_dir();
+            fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create export folder: {}", e))?;
+            let name = format!(
+                "clawpet-history-{}",
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            );
+            unique_path(&dir, &name, request.format.extension())
+        }
+    };
+
+    let attachments = match request.format {
+        ExportFormat::Markdown => write_markdown(&path, &request, &entries)?,
+        ExportFormat::Html => write_html(&path, &request, &entries)?,
+        ExportFormat::Jsonl => write_jsonl(&path, &entries)?,
+    };
+
+    eprintln!(
+        "[history-export] Saved {} messages ({} attachments) to {}",
+        entries.len(),
+        attachments,
+        path.display()
+    );
+
+    Ok(ExportResult {
+        path: path.to_string_lossy().to_string(),
+        entries: entries.len(),
+        attachments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(mime_type: &str, file_name: Option<&str>, data: &[u8]) -> HistoryAttachment {
+        HistoryAttachment {
+            mime_type: mime_type.to_string(),
+            file_name: file_name.map(str::to_string),
+            size_bytes: data.len(),
+            hash: None,
+            data: Some(data.to_vec()),
+        }
+    }
+
+    fn entries() -> Vec<HistoryEntry> {
+        vec![HistoryEntry {
+            id: "a".into(),
+            role: "user".into(),
+            text: "see <attached>".into(),
+            timestamp: 1_700_000_000_000,
+            session: None,
+            attachments: vec![
+                attachment("image/png", None, b"png bytes"),
+                attachment("application/pdf", Some("notes (final).pdf"), b"%PDF"),
+            ],
+        }]
+    }
+
+    fn request(format: ExportFormat) -> ExportRequest {
+        ExportRequest {
+            format,
+            from: None,
+            to: None,
+            session: None,
+            path: None,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clawpet-export-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn markdown_links_survive_spaces_and_parentheses() {
+        let dir = temp_dir("md");
+        let path = dir.join("my chat (1).md");
+        assert_eq!(write_markdown(&path, &request(ExportFormat::Markdown), &entries()).unwrap(), 2);
+
+        let md = fs::read_to_string(&path).unwrap();
+        assert!(md.contains("![attachment](<my chat (1)_files/0001-1.png>)"));
+        assert!(md.contains("[notes (final).pdf](<my chat (1)_files/0001-2.pdf>)"));
+        assert_eq!(fs::read(dir.join("my chat (1)_files/0001-1.png")).unwrap(), b"png bytes");
+        assert_eq!(markdown_target("a<b>.png"), r"<a\<b\>.png>");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn html_inlines_only_images() {
+        let dir = temp_dir("html");
+        let path = dir.join("chat.html");
+        assert_eq!(write_html(&path, &request(ExportFormat::Html), &entries()).unwrap(), 2);
+
+        let html = fs::read_to_string(&path).unwrap();
+        assert_eq!(html.matches("<img ").count(), 1);
+        assert!(html.contains("src=\"data:image/png;base64,"));
+        assert!(html.contains("<a download=\"notes (final).pdf\" href=\"data:application/pdf;base64,"));
+        assert!(html.contains("see &lt;attached&gt;"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn jsonl_keeps_one_entry_per_line() {
+        let dir = temp_dir("jsonl");
+        let path = dir.join("chat.jsonl");
+        assert_eq!(write_jsonl(&path, &entries()).unwrap(), 2);
+
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["attachments"][1]["fileName"], "notes (final).pdf");
+        assert_eq!(lines[0]["attachments"][0]["base64"], "cG5nIGJ5dGVz");
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+/// Bindings changed in the app, in the app data dir. They win over the env vars.
+const SETTINGS_FILE: &str = "hotkeys.json";
+
+/// Bindings as configured and whether they could be registered, for `list_hotkeys`.
+static HOTKEYS: LazyLock<Mutex<Vec<HotkeyStatus>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+/// Held while hotkeys are re-registered, so two changes can't interleave. Separate from
+/// `HOTKEYS`, which the shortcut handler locks on every key press.
+static APPLYING: Mutex<()> = Mutex::new(());
+
+/// Saved overrides: action id -> keys, or `null` for a hotkey turned off.
+type HotkeySettings = HashMap<HotkeyAction, Option<String>>;
+
+/// Actions a global hotkey can trigger. The serialized ids match the pet's menu actions.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum HotkeyAction {
+    CaptureArea,
+    CaptureDisplay,
+    ReadBrowser,
+    ClipToObsidian,
+    TogglePet,
+}
+
+impl HotkeyAction {
+    const ALL: [HotkeyAction; 5] = [
+        HotkeyAction::CaptureArea,
+        HotkeyAction::CaptureDisplay,
+        HotkeyAction::ReadBrowser,
+        HotkeyAction::ClipToObsidian,
+        HotkeyAction::TogglePet,
+    ];
+
+    fn env_var(self) -> &'static str {
+        match self {
+            HotkeyAction::CaptureArea => "CLAWPET_HOTKEY_CAPTURE_AREA",
+            HotkeyAction::CaptureDisplay => "CLAWPET_HOTKEY_CAPTURE_DISPLAY",
+            HotkeyAction::ReadBrowser => "CLAWPET_HOTKEY_READ_BROWSER",
+            HotkeyAction::ClipToObsidian => "CLAWPET_HOTKEY_CLIP_TO_OBSIDIAN",
+            HotkeyAction::TogglePet => "CLAWPET_HOTKEY_TOGGLE_PET",
+        }
+    }
+
+    fn default_binding(self) -> &'static str {
+        match self {
+            HotkeyAction::CaptureArea => "Alt+Shift+A",
+            HotkeyAction::CaptureDisplay => "Alt+Shift+S",
+            HotkeyAction::ReadBrowser => "Alt+Shift+R",
+            HotkeyAction::ClipToObsidian => "Alt+Shift+O",
+            HotkeyAction::TogglePet => "Alt+Shift+P",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            HotkeyAction::CaptureArea => "Area capture",
+            HotkeyAction::CaptureDisplay => "Full screen capture",
+            HotkeyAction::ReadBrowser => "Read browser page",
+            HotkeyAction::ClipToObsidian => "Save to Obsidian",
+            HotkeyAction::TogglePet => "Show/hide pet",
+        }
+    }
+
+    /// The configured binding: the saved setting, else the env var, else the default.
+    fn binding(self, settings: &HotkeySettings) -> Option<String> {
+        match settings.get(&self) {
+            Some(saved) => saved.as_deref().and_then(parse_binding),
+            None => match std::env::var(self.env_var()) {
+                Ok(value) => parse_binding(&value),
+                Err(_) => Some(self.default_binding().to_string()),
+            },
+        }
+    }
+}
+
+/// `off`, `none` or an empty value turns a hotkey off.
+fn parse_binding(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() || value.eq_ignore_ascii_case("off") || value.eq_ignore_ascii_case("none") {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+// ---------- Settings file ----------
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(SETTINGS_FILE))
+        .map_err(|e| format!("Failed to find app data folder: {}", e))
+}
+
+/// Missing file means no overrides; a broken one is logged and ignored.
+fn read_settings(path: &Path) -> HotkeySettings {
+    let Ok(content) = fs::read_to_string(path) else {
+        return HotkeySettings::new();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        eprintln!("[hotkeys] Ignoring {}: {}", path.display(), e);
+        HotkeySettings::new()
+    })
+}
+
+/// Written to a temp file and renamed, so a crash never leaves half a file behind.
+fn write_settings(path: &Path, settings: &HotkeySettings) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to save hotkeys: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to save hotkeys: {}", e))?;
+    let staged = path.with_extension("json.tmp");
+    fs::write(&staged, json).map_err(|e| format!("Failed to save hotkeys: {}", e))?;
+    fs::rename(&staged, path).map_err(|e| format!("Failed to save hotkeys: {}", e))
+}
+
+fn configured_bindings(settings: &HotkeySettings) -> Vec<(HotkeyAction, Option<String>)> {
+    HotkeyAction::ALL
+        .iter()
+        .map(|action| (*action, action.binding(settings)))
+        .collect()
+}
+
+/// Bindings after changing `changed`, or the reason the change can't be used. `resolve`
+/// blames the later of two clashing actions, so the changed one is checked last.
+fn check_change(
+    settings: &HotkeySettings,
+    changed: HotkeyAction,
+) -> Result<Vec<(HotkeyAction, Option<String>)>, String> {
+    let bindings = configured_bindings(settings);
+    let (mut order, last): (Vec<_>, Vec<_>) =
+        bindings.iter().cloned().partition(|(action, _)| *action != changed);
+    order.extend(last);
+    match resolve(&order).pop().and_then(|status| status.error) {
+        Some(error) => Err(error),
+        None => Ok(bindings),
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyStatus {
+    pub action: HotkeyAction,
+    pub label: &'static str,
+    /// `None` when the hotkey is turned off.
+    pub binding: Option<String>,
+    pub registered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip)]
+    shortcut: Option<Shortcut>,
+}
+
+#[derive(Serialize, Clone)]
+struct HotkeyPayload {
+    action: HotkeyAction,
+}
+
+/// Parse the bindings and reject the ones that can't be registered: bad syntax, no
+/// modifier key (it would swallow that key everywhere), or the same keys as an earlier
+/// action.
+fn resolve(bindings: &[(HotkeyAction, Option<String>)]) -> Vec<HotkeyStatus> {
+    let mut resolved: Vec<HotkeyStatus> = Vec::new();
+    for (action, binding) in bindings {
+        let mut status = HotkeyStatus {
+            action: *action,
+            label: action.label(),
+            binding: binding.clone(),
+            registered: false,
+            error: None,
+            shortcut: None,
+        };
+        if let Some(binding) = binding {
+            match Shortcut::from_str(binding) {
+                Ok(shortcut) if shortcut.mods.is_empty() => {
+                    status.error = Some(format!("{} needs a modifier key such as Ctrl, Alt or Shift", binding));
+                }
+                Ok(shortcut) => {
+                    let taken = resolved
+                        .iter()
+                        .find(|other| other.shortcut.as_ref().is_some_and(|s| s.id() == shortcut.id()));
+                    match taken {
+                        Some(other) => {
+                            status.error = Some(format!("{} is already used for {}", binding, other.label));
+                        }
+                        None => status.shortcut = Some(shortcut),
+                    }
+                }
+                Err(e) => status.error = Some(format!("Invalid hotkey {}: {}", binding, e)),
+            }
+        }
+        resolved.push(status);
+    }
+    resolved
+}
+
+/// Swap the registered hotkeys for `bindings`. Failures (including keys another app
+/// already holds) are logged and kept for `list_hotkeys`.
+fn apply(app: &AppHandle, bindings: &[(HotkeyAction, Option<String>)]) -> Vec<HotkeyStatus> {
+    let _applying = APPLYING.lock().unwrap_or_else(|e| e.into_inner());
+    let previous = std::mem::take(&mut *HOTKEYS.lock().unwrap_or_else(|e| e.into_inner()));
+    for status in previous.iter().filter(|status| status.registered) {
+        if let Some(shortcut) = status.shortcut {
+            if let Err(e) = app.global_shortcut().unregister(shortcut) {
+                eprintln!("[hotkeys] Failed to release {}: {}", status.label, e);
+            }
+        }
+    }
+
+    let mut statuses = resolve(bindings);
+    for status in statuses.iter_mut() {
+        if let Some(shortcut) = status.shortcut {
+            match app.global_shortcut().register(shortcut) {
+                Ok(()) => status.registered = true,
+                Err(e) => {
+                    status.error = Some(format!(
+                        "Failed to register {}: {}",
+                        status.binding.as_deref().unwrap_or_default(),
+                        e
+                    ));
+                }
+            }
+        }
+        if let Some(error) = &status.error {
+            eprintln!("[hotkeys] {} ({}): {}", status.label, status.action.env_var(), error);
+        }
+    }
+
+    *HOTKEYS.lock().unwrap_or_else(|e| e.into_inner()) = statuses.clone();
+    statuses
+}
+
+/// Register the configured hotkeys at startup. Failures never stop the app from starting.
+pub fn register(app: &AppHandle) {
+    let settings = match settings_path(app) {
+        Ok(path) => read_settings(&path),
+        Err(e) => {
+            eprintln!("[hotkeys] {}", e);
+            HotkeySettings::new()
+        }
+    };
+    apply(app, &configured_bindings(&settings));
+}
+
+fn toggle_pet(app: &AppHandle) {
+    if let Some(win) = app.get_webview_window("main") {
+        if win.is_visible().unwrap_or(false) {
+            let _ = win.hide();
+        } else {
+            let _ = win.show();
+            let _ = win.set_focus();
+        }
+    }
+}
+
+/// Global shortcut handler: the pet's visibility is toggled here, every other action is
+/// run by the pet window so its result lands in the chat like a menu click.
+pub fn handle(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let action = HOTKEYS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|status| status.registered && status.shortcut.as_ref().is_some_and(|s| s.id() == shortcut.id()))
+        .map(|status| status.action);
+    match action {
+        Some(HotkeyAction::TogglePet) => toggle_pet(app),
+        Some(action) => {
+            if let Err(e) = app.emit_to("main", "clawpet://hotkey", HotkeyPayload { action }) {
+                eprintln!("[hotkeys] Failed to send {}: {}", action.label(), e);
+            }
+        }
+        None => {}
+    }
+}
+
+#[tauri::command]
+pub async fn list_hotkeys() -> Result<Vec<HotkeyStatus>, String> {
+    Ok(HOTKEYS.lock().unwrap_or_else(|e| e.into_inner()).clone())
+}
+
+/// Change one hotkey, save it and re-register. `None` drops the saved binding (back to
+/// the env var or default); `"off"` turns the hotkey off. A binding that is invalid or
+/// clashes with another action is rejected and nothing is saved.
+#[tauri::command]
+pub async fn set_hotkey(
+    app: AppHandle,
+    action: HotkeyAction,
+    binding: Option<String>,
+) -> Result<Vec<HotkeyStatus>, String> {
+    let path = settings_path(&app)?;
+    let mut settings = read_settings(&path);
+    match binding {
+        Some(binding) => settings.insert(action, parse_binding(&binding)),
+        None => settings.remove(&action),
+    };
+
+    let bindings = check_change(&settings, action)?;
+    write_settings(&path, &settings)?;
+    Ok(apply(&app, &bindings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(action: HotkeyAction, keys: &str) -> (HotkeyAction, Option<String>) {
+        (action, Some(keys.to_string()))
+    }
+
+    #[test]
+    fn conflicting_and_bare_bindings_are_reported() {
+        let statuses = resolve(&[
+            binding(HotkeyAction::CaptureArea, "Alt+Shift+A"),
+            binding(HotkeyAction::CaptureDisplay, "shift+alt+a"),
+            binding(HotkeyAction::ReadBrowser, "R"),
+            binding(HotkeyAction::ClipToObsidian, "Alt+Shift+Nope"),
+            (HotkeyAction::TogglePet, None),
+        ]);
+        assert!(statuses[0].shortcut.is_some() && statuses[0].error.is_none());
+        assert_eq!(
+            statuses[1].error.as_deref(),
+            Some("shift+alt+a is already used for Area capture")
+        );
+        assert!(statuses[2].error.as_deref().unwrap().contains("modifier"));
+        assert!(statuses[3].error.as_deref().unwrap().starts_with("Invalid hotkey"));
+        assert!(statuses[4].shortcut.is_none() && statuses[4].error.is_none());
+        assert!(statuses[1..].iter().all(|status| status.shortcut.is_none()));
+    }
+
+    #[test]
+    fn parse_errors_and_missing_modifiers_name_the_binding() {
+        let statuses = resolve(&[
+            binding(HotkeyAction::CaptureArea, "Ctrl+"),
+            binding(HotkeyAction::CaptureDisplay, "Ctrl+Banana"),
+            binding(HotkeyAction::ReadBrowser, "F5"),
+            binding(HotkeyAction::ClipToObsidian, "CommandOrControl+Shift+O"),
+        ]);
+        assert!(statuses[0].error.as_deref().unwrap().starts_with("Invalid hotkey Ctrl+"));
+        assert!(statuses[1].error.as_deref().unwrap().starts_with("Invalid hotkey Ctrl+Banana"));
+        assert_eq!(
+            statuses[2].error.as_deref(),
+            Some("F5 needs a modifier key such as Ctrl, Alt or Shift")
+        );
+        assert!(statuses[3].error.is_none() && statuses[3].shortcut.is_some());
+
+        // Turned-off hotkeys never clash with anything
+        let statuses = resolve(&[
+            (HotkeyAction::CaptureArea, None),
+            binding(HotkeyAction::TogglePet, "Alt+Shift+A"),
+        ]);
+        assert!(statuses.iter().all(|status| status.error.is_none()));
+    }
+
+    #[test]
+    fn saved_bindings_win_and_can_turn_hotkeys_off() {
+        assert_eq!(parse_binding("  Alt+X "), Some("Alt+X".to_string()));
+        for off in ["", "  ", "off", "NONE"] {
+            assert_eq!(parse_binding(off), None, "{off:?}");
+        }
+
+        let settings = HotkeySettings::from([
+            (HotkeyAction::CaptureArea, Some("Ctrl+Shift+1".to_string())),
+            (HotkeyAction::TogglePet, None),
+        ]);
+        let bindings = configured_bindings(&settings);
+        assert_eq!(bindings[0], binding(HotkeyAction::CaptureArea, "Ctrl+Shift+1"));
+        assert_eq!(bindings[4], (HotkeyAction::TogglePet, None));
+        assert_eq!(bindings[1].1, HotkeyAction::CaptureDisplay.binding(&HotkeySettings::new()));
+    }
+
+    #[test]
+    fn a_change_that_clashes_is_rejected() {
+        // Taking another action's keys is blamed on the action being changed
+        let settings = HotkeySettings::from([
+            (HotkeyAction::CaptureArea, Some("Ctrl+Shift+9".to_string())),
+            (HotkeyAction::TogglePet, Some("Ctrl+Shift+9".to_string())),
+        ]);
+        assert_eq!(
+            check_change(&settings, HotkeyAction::CaptureArea).unwrap_err(),
+            "Ctrl+Shift+9 is already used for Show/hide pet"
+        );
+        let settings = HotkeySettings::from([(HotkeyAction::ReadBrowser, Some("B".to_string()))]);
+        assert!(check_change(&settings, HotkeyAction::ReadBrowser).unwrap_err().contains("modifier"));
+
+        let settings = HotkeySettings::from([(HotkeyAction::ReadBrowser, Some("Ctrl+Alt+B".to_string()))]);
+        let bindings = check_change(&settings, HotkeyAction::ReadBrowser).unwrap();
+        assert_eq!(bindings[2], binding(HotkeyAction::ReadBrowser, "Ctrl+Alt+B"));
+    }
+
+    #[test]
+    fn settings_file_round_trips() {
+        let dir = std::env::temp_dir().join(format!("clawpet-hotkeys-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join(SETTINGS_FILE);
+        assert!(read_settings(&path).is_empty());
+
+        let settings = HotkeySettings::from([
+            (HotkeyAction::CaptureArea, Some("Ctrl+Shift+1".to_string())),
+            (HotkeyAction::TogglePet, None),
+        ]);
+        write_settings(&path, &settings).unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+        assert!(json.contains("\"capture-area\": \"Ctrl+Shift+1\""), "{json}");
+        assert!(json.contains("\"toggle-pet\": null"), "{json}");
+        assert_eq!(read_settings(&path), settings);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(read_settings(&path).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,596 @@
+use image::{DynamicImage, ImageFormat};
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const TAG_ORIENTATION: u16 = 0x0112;
+
+/// `CLAWPET_KEEP_IMAGE_METADATA=true` sends EXIF (GPS, camera serials, ...) along with
+/// attachments. Off by default.
+pub fn keep_metadata() -> bool {
+    std::env::var("CLAWPET_KEEP_IMAGE_METADATA")
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+// ---------- EXIF ----------
+
+/// Raw TIFF block of the EXIF data in a JPEG, PNG or WebP, if any.
+pub fn exif_block(bytes: &[u8], format: ImageFormat) -> Option<&[u8]> {
+    match format {
+        ImageFormat::Jpeg => jpeg_segments(bytes)?
+            .into_iter()
+            .find(|seg| seg.marker == 0xE1 && seg.payload.starts_with(EXIF_HEADER))
+            .map(|seg| &seg.payload[EXIF_HEADER.len()..]),
+        ImageFormat::Png => png_chunks(bytes)?
+            .into_iter()
+            .find(|chunk| chunk.kind == *b"eXIf")
+            .map(|chunk| chunk.data),
+        ImageFormat::WebP => webp_chunks(bytes)?
+            .into_iter()
+            .find(|chunk| chunk.kind == *b"EXIF")
+            // Some writers keep the JPEG-style prefix inside the chunk
+            .map(|chunk| chunk.data.strip_prefix(EXIF_HEADER).unwrap_or(chunk.data)),
+        _ => None,
+    }
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(..4)? {
+            b"II*\0" => true,
+            b"MM\0*" => false,
+            _ => return None,
+        };
+        Some(Self {
+            data,
+            little_endian,
+        })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let b: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let b: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+
+    /// Offset of the orientation value inside the block, with the value itself.
+    fn orientation(&self) -> Option<(usize, u16)> {
+        let ifd = self.u32_at(4)? as usize;
+        let count = self.u16_at(ifd)? as usize;
+        (0..count).find_map(|i| {
+            let entry = ifd + 2 + i * 12;
+            if self.u16_at(entry)? != TAG_ORIENTATION || self.u16_at(entry + 2)? != 3 {
+                return None;
+            }
+            let value_offset = entry + 8;
+            Some((value_offset, self.u16_at(value_offset)?))
+        })
+    }
+}
+
+/// EXIF orientation (1-8); 1 when absent or invalid.
+pub fn orientation(exif: &[u8]) -> u16 {
+    Tiff::parse(exif)
+        .and_then(|tiff| tiff.orientation())
+        .map(|(_, value)| value)
+        .filter(|value| (1..=8).contains(value))
+        .unwrap_or(1)
+}
+
+/// Orientation the pixels have to be turned by: the EXIF tag, or for TIFF the tag in the
+/// file's own first IFD (TIFF has no separate EXIF block).
+pub fn image_orientation(bytes: &[u8], format: ImageFormat) -> u16 {
+    match format {
+        ImageFormat::Tiff => orientation(bytes),
+        _ => exif_block(bytes, format).map(orientation).unwrap_or(1),
+    }
+}
+
+/// Rotate/flip decoded pixels so they display upright without the tag.
+pub fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Copy of the EXIF block with orientation reset to 1, for images whose pixels were rotated.
+pub fn upright_exif(exif: &[u8]) -> Vec<u8> {
+    let mut out = exif.to_vec();
+    if let Some(tiff) = Tiff::parse(exif) {
+        if let Some((offset, _)) = tiff.orientation() {
+            let one = if tiff.little_endian {
+                1u16.to_le_bytes()
+            } else {
+                1u16.to_be_bytes()
+            };
+            out[offset..offset + 2].copy_from_slice(&one);
+        }
+    }
+    out
+}
+
+/// Put an EXIF block back into a freshly encoded JPEG (after SOI and the JFIF header).
+pub fn insert_jpeg_exif(jpeg: &[u8], exif: &[u8]) -> Option<Vec<u8>> {
+    let length = u16::try_from(2 + EXIF_HEADER.len() + exif.len()).ok()?;
+    let segments = jpeg_segments(jpeg)?;
+    let split = 2 + segments
+        .first()
+        .filter(|seg| seg.marker == 0xE0)
+        .map_or(0, |seg| seg.raw.len());
+
+    let mut out = Vec::with_capacity(jpeg.len() + length as usize + 2);
+    out.extend_from_slice(&jpeg[..split]);
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&length.to_be_bytes());
+    out.extend_from_slice(EXIF_HEADER);
+    out.extend_from_slice(exif);
+    out.extend_from_slice(&jpeg[split..]);
+    Some(out)
+}
+
+// ---------- Stripping ----------
+
+/// Drop EXIF, XMP, IPTC and comments without re-encoding the pixels. Color profiles and
+/// everything needed to decode stay. `None` when the container can't be parsed.
+pub fn strip(bytes: &[u8], format: ImageFormat) -> Option<Vec<u8>> {
+    match format {
+        ImageFormat::Jpeg => strip_jpeg(bytes),
+        ImageFormat::Png => strip_png(bytes),
+        ImageFormat::WebP => strip_webp(bytes),
+        ImageFormat::Gif => strip_gif(bytes),
+        // Anything else is re-encoded by the pipeline
+        _ => Some(bytes.to_vec()),
+    }
+}
+
+struct JpegSegment<'a> {
+    marker: u8,
+    /// Whole segment including the marker and length bytes.
+    raw: &'a [u8],
+    payload: &'a [u8],
+}
+
+/// Header segments up to (not including) SOS, plus the offset where SOS starts.
+fn jpeg_layout(bytes: &[u8]) -> Option<(Vec<JpegSegment<'_>>, usize)> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut segments = Vec::new();
+    let mut pos = 2;
+    loop {
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        let start = pos;
+        // Markers may be preceded by any number of 0xFF fill bytes
+        while *bytes.get(pos)? == 0xFF {
+            pos += 1;
+        }
+        let marker = bytes[pos];
+        pos += 1;
+        if marker == 0xDA {
+            return Some((segments, start));
+        }
+        if matches!(marker, 0x01 | 0xD0..=0xD7) {
+            segments.push(JpegSegment {
+                marker,
+                raw: &bytes[start..pos],
+                payload: &[],
+            });
+            continue;
+        }
+        let length = u16::from_be_bytes([*bytes.get(pos)?, *bytes.get(pos + 1)?]) as usize;
+        if length < 2 {
+            return None;
+        }
+        let end = pos.checked_add(length)?;
+        segments.push(JpegSegment {
+            marker,
+            raw: bytes.get(start..end)?,
+            payload: &bytes[pos + 2..end],
+        });
+        pos = end;
+    }
+}
+
+fn jpeg_segments(bytes: &[u8]) -> Option<Vec<JpegSegment<'_>>> {
+    jpeg_layout(bytes).map(|(segments, _)| segments)
+}
+
+fn strip_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+    let (segments, scan_start) = jpeg_layout(bytes)?;
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(&bytes[..2]);
+    for segment in segments {
+        // APP1 = EXIF/XMP, APP13 = Photoshop/IPTC, COM = comment
+        if matches!(segment.marker, 0xE1 | 0xED | 0xFE) {
+            continue;
+        }
+        out.extend_from_slice(segment.raw);
+    }
+    out.extend_from_slice(&bytes[scan_start..]);
+    Some(out)
+}
+
+struct Chunk<'a> {
+    kind: [u8; 4],
+    /// Whole chunk as stored (header, data, CRC or padding).
+    raw: &'a [u8],
+    data: &'a [u8],
+}
+
+fn png_chunks(bytes: &[u8]) -> Option<Vec<Chunk<'_>>> {
+    if !bytes.starts_with(PNG_SIGNATURE) {
+        return None;
+    }
+    let mut chunks = Vec::new();
+    let mut pos = PNG_SIGNATURE.len();
+    while pos < bytes.len() {
+        let length = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let kind: [u8; 4] = bytes.get(pos + 4..pos + 8)?.try_into().ok()?;
+        let data_end = (pos + 8).checked_add(length)?;
+        let end = data_end.checked_add(4)?;
+        chunks.push(Chunk {
+            kind,
+            raw: bytes.get(pos..end)?,
+            data: &bytes[pos + 8..data_end],
+        });
+        pos = end;
+        if kind == *b"IEND" {
+            break;
+        }
+    }
+    Some(chunks)
+}
+
+fn strip_png(bytes: &[u8]) -> Option<Vec<u8>> {
+    let chunks = png_chunks(bytes)?;
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(PNG_SIGNATURE);
+    for chunk in chunks {
+        // XMP lives in an iTXt chunk; text chunks also carry author/software/comments
+        if matches!(&chunk.kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            continue;
+        }
+        out.extend_from_slice(chunk.raw);
+    }
+    Some(out)
+}
+
+fn webp_chunks(bytes: &[u8]) -> Option<Vec<Chunk<'_>>> {
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WEBP" {
+        return None;
+    }
+    let riff_end = (8 + u32::from_le_bytes(bytes[4..8].try_into().ok()?) as usize).min(bytes.len());
+    let mut chunks = Vec::new();
+    let mut pos = 12;
+    while pos + 8 <= riff_end {
+        let kind: [u8; 4] = bytes[pos..pos + 4].try_into().ok()?;
+        let length = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let data_end = (pos + 8).checked_add(length)?;
+        let end = (data_end + (length & 1)).min(riff_end);
+        chunks.push(Chunk {
+            kind,
+            raw: bytes.get(pos..end)?,
+            data: bytes.get(pos + 8..data_end)?,
+        });
+        pos = end;
+    }
+    Some(chunks)
+}
+
+fn strip_webp(bytes: &[u8]) -> Option<Vec<u8>> {
+    let chunks = webp_chunks(bytes)?;
+    let mut body = Vec::with_capacity(bytes.len());
+    body.extend_from_slice(b"WEBP");
+    for chunk in chunks {
+        match &chunk.kind {
+            b"EXIF" | b"XMP " => continue,
+            b"VP8X" if chunk.raw.len() > 8 => {
+                // Clear the EXIF (0x08) and XMP (0x04) presence flags
+                let mut vp8x = chunk.raw.to_vec();
+                vp8x[8] &= !0x0C;
+                body.extend_from_slice(&vp8x);
+            }
+            _ => body.extend_from_slice(chunk.raw),
+        }
+    }
+    let mut out = Vec::with_capacity(body.len() + 8);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&u32::try_from(body.len()).ok()?.to_le_bytes());
+    out.extend_from_slice(&body);
+    Some(out)
+}
+
+/// Length of a run of GIF data sub-blocks (size byte + data, ending with a zero size),
+/// including the terminator.
+fn gif_sub_blocks_len(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut pos = start;
+    loop {
+        let size = *bytes.get(pos)? as usize;
+        pos += 1 + size;
+        if size == 0 {
+            return Some(pos - start);
+        }
+    }
+}
+
+fn gif_color_table_len(flags: u8) -> usize {
+    if flags & 0x80 == 0 {
+        0
+    } else {
+        3 << ((flags & 0x07) + 1)
+    }
+}
+
+/// Drop comment extensions and XMP application extensions. Frames, palettes, the
+/// NETSCAPE loop count and color profiles stay.
+fn strip_gif(bytes: &[u8]) -> Option<Vec<u8>> {
+    if !bytes.starts_with(b"GIF87a") && !bytes.starts_with(b"GIF89a") {
+        return None;
+    }
+    let header_end = 13 + gif_color_table_len(*bytes.get(10)?);
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(bytes.get(..header_end)?);
+    let mut pos = header_end;
+    loop {
+        let start = pos;
+        match *bytes.get(pos)? {
+            0x21 => {
+                let label = *bytes.get(pos + 1)?;
+                let body = pos + 2;
+                pos = body + gif_sub_blocks_len(bytes, body)?;
+                let is_xmp = label == 0xFF && bytes
+                    .get(body..body + 12)
+                    .is_some_and(|id| id == b"\x0BXMP DataXMP");
+                if label == 0xFE || is_xmp {
+                    continue;
+                }
+            }
+            0x2C => {
+                let flags = *bytes.get(pos + 9)?;
+                // Descriptor, local palette, LZW code size, then the image data blocks
+                let data = pos + 10 + gif_color_table_len(flags) + 1;
+                pos = data + gif_sub_blocks_len(bytes, data)?;
+            }
+            0x3B => {
+                out.push(0x3B);
+                return Some(out);
+            }
+            _ => return None,
+        }
+        out.extend_from_slice(bytes.get(start..pos)?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = 0xFFFF_FFFFu32;
+        for &byte in bytes {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+
+    fn png_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&crc32(&chunk[4..]).to_be_bytes());
+        chunk
+    }
+
+    /// Minimal EXIF block: one IFD holding only the orientation tag.
+    fn exif(orientation: u16, little_endian: bool) -> Vec<u8> {
+        let u16b = |v: u16| if little_endian { v.to_le_bytes() } else { v.to_be_bytes() };
+        let u32b = |v: u32| if little_endian { v.to_le_bytes() } else { v.to_be_bytes() };
+        let mut out = if little_endian { b"II*\0".to_vec() } else { b"MM\0*".to_vec() };
+        out.extend_from_slice(&u32b(8));
+        out.extend_from_slice(&u16b(1));
+        out.extend_from_slice(&u16b(TAG_ORIENTATION));
+        out.extend_from_slice(&u16b(3));
+        out.extend_from_slice(&u32b(1));
+        out.extend_from_slice(&u16b(orientation));
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(&u32b(0));
+        out
+    }
+
+    fn sample() -> DynamicImage {
+        let mut image = RgbaImage::from_pixel(2, 3, Rgba([0, 0, 255, 255]));
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        DynamicImage::ImageRgba8(image)
+    }
+
+    fn jpeg() -> Vec<u8> {
+        crate::image_pipeline::encode_jpeg(&sample(), 90).unwrap()
+    }
+
+    fn jpeg_markers(bytes: &[u8]) -> Vec<u8> {
+        jpeg_segments(bytes).unwrap().iter().map(|seg| seg.marker).collect()
+    }
+
+    #[test]
+    fn broken_jpeg_segment_lengths_are_rejected() {
+        let header = [0xFF, 0xD8, 0xFF, 0xE1];
+        // Length runs past the end of the file
+        let oversized = [&header[..], &[0xFF, 0xFF], b"Exif\0\0II"].concat();
+        assert!(strip(&oversized, ImageFormat::Jpeg).is_none());
+        assert!(exif_block(&oversized, ImageFormat::Jpeg).is_none());
+        // Length smaller than the length field itself
+        let undersized = [&header[..], &[0x00, 0x01], &[0xFF, 0xDA]].concat();
+        assert!(strip(&undersized, ImageFormat::Jpeg).is_none());
+        // Cut off inside the length field, and no SOS at all
+        assert!(strip(&[0xFF, 0xD8, 0xFF, 0xE1, 0x00], ImageFormat::Jpeg).is_none());
+        assert!(strip(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x02], ImageFormat::Jpeg).is_none());
+        assert!(strip(b"not a jpeg", ImageFormat::Jpeg).is_none());
+    }
+
+    #[test]
+    fn jpeg_stripping_drops_exif_xmp_and_comments_only() {
+        let plain = jpeg();
+        let with_exif = insert_jpeg_exif(&plain, &exif(6, false)).unwrap();
+        let xmp = [&[0xFF, 0xE1, 0x00, 0x22][..], b"http://ns.adobe.com/xap/1.0/\0<x>"].concat();
+        let icc = [&[0xFF, 0xE2, 0x00, 0x10][..], b"ICC_PROFILE\0\x01\x01"].concat();
+        let comment = [&[0xFF, 0xFE, 0x00, 0x07][..], b"hello"].concat();
+        let mut tagged = with_exif[..2].to_vec();
+        for extra in [&xmp, &icc, &comment] {
+            tagged.extend_from_slice(extra);
+        }
+        tagged.extend_from_slice(&with_exif[2..]);
+        assert!(jpeg_markers(&tagged).contains(&0xE1));
+
+        let stripped = strip(&tagged, ImageFormat::Jpeg).unwrap();
+        let markers = jpeg_markers(&stripped);
+        assert!(!markers.iter().any(|m| matches!(m, 0xE1 | 0xFE)));
+        assert!(markers.contains(&0xE2));
+        assert!(exif_block(&stripped, ImageFormat::Jpeg).is_none());
+        assert_eq!(image::load_from_memory(&stripped).unwrap().width(), 2);
+    }
+
+    #[test]
+    fn exif_orientation_is_read_reset_and_reinserted() {
+        for little_endian in [true, false] {
+            for value in 1..=8 {
+                assert_eq!(orientation(&exif(value, little_endian)), value);
+            }
+            assert_eq!(orientation(&exif(9, little_endian)), 1);
+            assert_eq!(orientation(&upright_exif(&exif(6, little_endian))), 1);
+        }
+        assert_eq!(orientation(b"II*\0\xff\xff\xff\xff"), 1);
+        // TIFF files carry the tag in their own first IFD
+        assert_eq!(image_orientation(&exif(8, true), ImageFormat::Tiff), 8);
+
+        let tagged = insert_jpeg_exif(&jpeg(), &upright_exif(&exif(3, true))).unwrap();
+        assert_eq!(exif_block(&tagged, ImageFormat::Jpeg), Some(&upright_exif(&exif(3, true))[..]));
+        assert_eq!(image_orientation(&tagged, ImageFormat::Jpeg), 1);
+        assert_eq!(jpeg_markers(&tagged)[..2], [0xE0, 0xE1]);
+        assert!(image::load_from_memory(&tagged).is_ok());
+    }
+
+    #[test]
+    fn every_orientation_turns_the_image_upright() {
+        // Where the red top-left pixel of a 2x3 image ends up, and the new size
+        let expected = [
+            (1, (0, 0), (2, 3)),
+            (2, (1, 0), (2, 3)),
+            (3, (1, 2), (2, 3)),
+            (4, (0, 2), (2, 3)),
+            (5, (0, 0), (3, 2)),
+            (6, (2, 0), (3, 2)),
+            (7, (2, 1), (3, 2)),
+            (8, (0, 1), (3, 2)),
+        ];
+        for (value, (x, y), size) in expected {
+            let turned = apply_orientation(sample(), value).to_rgba8();
+            assert_eq!(turned.dimensions(), size, "orientation {value}");
+            assert_eq!(turned.get_pixel(x, y)[0], 255, "orientation {value}");
+        }
+    }
+
+    #[test]
+    fn png_text_and_exif_chunks_are_removed_with_valid_crcs() {
+        let plain = crate::image_pipeline::encode_png(&sample()).unwrap();
+        let iend = plain.len() - 12;
+        let mut tagged = plain[..iend].to_vec();
+        tagged.extend(png_chunk(b"tEXt", b"Author\0someone"));
+        tagged.extend(png_chunk(b"iTXt", b"XML:com.adobe.xmp\0\0\0\0\0<x/>"));
+        tagged.extend(png_chunk(b"eXIf", &exif(6, true)));
+        tagged.extend_from_slice(&plain[iend..]);
+        assert_eq!(image_orientation(&tagged, ImageFormat::Png), 6);
+
+        let stripped = strip(&tagged, ImageFormat::Png).unwrap();
+        let chunks = png_chunks(&stripped).unwrap();
+        assert!(chunks.iter().all(|c| !matches!(&c.kind, b"tEXt" | b"iTXt" | b"eXIf")));
+        for chunk in &chunks {
+            let stored = u32::from_be_bytes(chunk.raw[chunk.raw.len() - 4..].try_into().unwrap());
+            assert_eq!(stored, crc32(&chunk.raw[4..chunk.raw.len() - 4]));
+        }
+        assert_eq!(stripped, plain);
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn webp_metadata_chunks_and_vp8x_flags_are_cleared() {
+        let chunk = |kind: &[u8; 4], data: &[u8]| {
+            let mut out = kind.to_vec();
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                out.push(0);
+            }
+            out
+        };
+        // Alpha (0x10) + EXIF (0x08) + XMP (0x04) flags
+        let vp8x = chunk(b"VP8X", &[0x1C, 0, 0, 0, 1, 0, 0, 2, 0, 0]);
+        let body = [
+            vp8x.clone(),
+            chunk(b"VP8L", b"odd"),
+            chunk(b"EXIF", &[EXIF_HEADER, &exif(6, true)[..]].concat()),
+            chunk(b"XMP ", b"<x/>"),
+        ]
+        .concat();
+        let mut webp = b"RIFF".to_vec();
+        webp.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        webp.extend_from_slice(b"WEBP");
+        webp.extend_from_slice(&body);
+        assert_eq!(image_orientation(&webp, ImageFormat::WebP), 6);
+
+        let stripped = strip(&webp, ImageFormat::WebP).unwrap();
+        let chunks = webp_chunks(&stripped).unwrap();
+        let kinds: Vec<&[u8; 4]> = chunks.iter().map(|c| &c.kind).collect();
+        assert_eq!(kinds, [b"VP8X", b"VP8L"]);
+        assert_eq!(chunks[0].data[0], 0x10);
+        assert_eq!(chunks[1].raw.len(), 12);
+        let riff_size = u32::from_le_bytes(stripped[4..8].try_into().unwrap()) as usize;
+        assert_eq!(riff_size, stripped.len() - 8);
+    }
+
+    #[test]
+    fn gif_comments_and_xmp_are_removed_but_frames_stay() {
+        let mut plain = Vec::new();
+        image::codecs::gif::GifEncoder::new(&mut plain)
+            .encode_frame(image::Frame::new(sample().to_rgba8()))
+            .unwrap();
+        let header_end = 13 + gif_color_table_len(plain[10]);
+        let comment = [&[0x21, 0xFE, 0x05][..], b"hello", &[0x00]].concat();
+        let xmp = [&[0x21, 0xFF][..], b"\x0BXMP DataXMP", b"\x04<x/>", &[0x00]].concat();
+        let tagged = [&plain[..header_end], &comment, &xmp, &plain[header_end..]].concat();
+
+        let stripped = strip(&tagged, ImageFormat::Gif).unwrap();
+        assert_eq!(stripped, plain);
+        assert_eq!(image::load_from_memory(&stripped).unwrap().height(), 3);
+        assert!(strip(&tagged[..tagged.len() - 1], ImageFormat::Gif).is_none());
+    }
+}
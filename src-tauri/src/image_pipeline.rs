@@ -0,0 +1,365 @@
+use crate::animation_sheet;
+use crate::avif_decode;
+use crate::download::sniff_image_mime;
+use crate::image_metadata;
+use crate::svg_render;
+use image::{DynamicImage, ImageFormat};
+use serde::Serialize;
+use std::io::Cursor;
+
+pub const MAX_CHAT_ATTACHMENT_BYTES: usize = 4_800_000; // keep below OpenClaw 5MB gateway limit
+/// Largest encoded source we accept; anything below this is shrunk to fit the gateway.
+pub const MAX_SOURCE_IMAGE_BYTES: usize = 40 * 1024 * 1024; // 40MB
+/// Decoder allocation cap (~120MP RGBA) so a tiny file can't expand into gigabytes.
+const MAX_DECODE_ALLOC: u64 = 480 * 1024 * 1024;
+
+const JPEG_QUALITIES: [u8; 4] = [88, 78, 68, 58];
+const MAX_RESIZE_STEPS: usize = 8;
+const MIN_SIDE: u32 = 320;
+
+// ---------- Data types ----------
+
+/// What the pipeline did, so the UI can say "resized to 48%".
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+pub struct ImageInfo {
+    pub original_width: u32,
+    pub original_height: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Final width / original width (1.0 when the size was kept).
+    pub scale: f32,
+    /// False when the original pixels were kept (metadata may still have been stripped).
+    pub reencoded: bool,
+    /// Set when an animation was replaced by a keyframe contact sheet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation_frames: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyframes: Option<u32>,
+}
+
+pub struct NormalizedImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: String,
+    pub info: ImageInfo,
+}
+
+// ---------- Decoding ----------
+
+/// Limits every decoder runs with, including the frame-by-frame animation decoders.
+pub(crate) fn decode_limits() -> image::io::Limits {
+    let mut limits = image::io::Limits::default();
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    limits
+}
+
+fn reader(bytes: &[u8]) -> Result<image::io::Reader<Cursor<&[u8]>>, String> {
+    let mut reader = image::io::Reader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+    reader.limits(decode_limits());
+    Ok(reader)
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<DynamicImage, String> {
+    reader(bytes)?
+        .decode()
+        .map_err(|e| format!("Failed to decode image: {}", e))
+}
+
+/// Formats the gateway takes as-is.
+fn passthrough_mime(format: ImageFormat) -> Option<&'static str> {
+    match format {
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::Gif => Some("image/gif"),
+        ImageFormat::WebP => Some("image/webp"),
+        _ => None,
+    }
+}
+
+// ---------- Encoding ----------
+
+pub(crate) fn encode_png(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(bytes)
+}
+
+pub(crate) fn encode_jpeg(image: &DynamicImage, quality: u8) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality)
+        .encode_image(&DynamicImage::ImageRgb8(image.to_rgb8()))
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(bytes)
+}
+
+/// Re-encode until the result fits the gateway limit. PNG is tried first for captures and
+/// transparent images (crisp text, keeps alpha), then a JPEG quality ladder; when nothing
+/// fits, shrink in proportion to how far the smallest attempt overshot and try again.
+fn fit_for_chat(
+    image: DynamicImage,
+    prefer_png: bool,
+) -> Result<(Vec<u8>, &'static str, u32, u32), String> {
+    let try_png = prefer_png || image.color().has_alpha();
+    let mut current = image;
+
+    for _ in 0..MAX_RESIZE_STEPS {
+        if try_png {
+            let png = encode_png(&current)?;
+            if png.len() <= MAX_CHAT_ATTACHMENT_BYTES {
+                return Ok((png, "image/png", current.width(), current.height()));
+            }
+        }
+
+        let mut smallest = usize::MAX;
+        for quality in JPEG_QUALITIES {
+            let jpeg = encode_jpeg(&current, quality)?;
+            if jpeg.len() <= MAX_CHAT_ATTACHMENT_BYTES {
+                return Ok((jpeg, "image/jpeg", current.width(), current.height()));
+            }
+            smallest = smallest.min(jpeg.len());
+        }
+
+        // Encoded size scales roughly with pixel count, i.e. with the square of the side
+        let factor = ((MAX_CHAT_ATTACHMENT_BYTES as f64 / smallest as f64).sqrt() * 0.95)
+            .clamp(0.35, 0.85);
+        let next_width = ((current.width() as f64) * factor).round() as u32;
+        let next_height = ((current.height() as f64) * factor).round() as u32;
+        if next_width.min(next_height) < MIN_SIDE {
+            break;
+        }
+        current = current.resize_exact(
+            next_width,
+            next_height,
+            image::imageops::FilterType::Triangle,
+        );
+    }
+
+    Err("Image is too large to attach".to_string())
+}
+
+fn finish(
+    original: (u32, u32),
+    (bytes, mime_type, width, height): (Vec<u8>, &'static str, u32, u32),
+) -> NormalizedImage {
+    NormalizedImage {
+        bytes,
+        mime_type: mime_type.to_string(),
+        info: ImageInfo {
+            original_width: original.0,
+            original_height: original.1,
+            width,
+            height,
+            scale: if original.0 == 0 {
+                1.0
+            } else {
+                width as f32 / original.0 as f32
+            },
+            reencoded: true,
+            ..ImageInfo::default()
+        },
+    }
+}
+
+// ---------- Entry points ----------
+
+/// Encoded image from a file, URL, paste or browser screenshot. Kept as-is (minus metadata)
+/// when the gateway can take it; otherwise decoded, turned upright, downsized (aspect
+/// ratio kept) and re-encoded.
+pub fn normalize_bytes(bytes: &[u8]) -> Result<NormalizedImage, String> {
+    if bytes.len() > MAX_SOURCE_IMAGE_BYTES {
+        return Err(format!(
+            "Image exceeds {}MB limit",
+            MAX_SOURCE_IMAGE_BYTES / (1024 * 1024)
+        ));
+    }
+
+    match sniff_image_mime(bytes) {
+        // Vector art is rasterized and then treated like a capture (PNG first, crisp lines)
+        Some("image/svg+xml") => return normalize_pixels(svg_render::rasterize(bytes)?, true),
+        // The image crate can't read AVIF; decode it ourselves and keep alpha if it has any
+        Some("image/avif") => {
+            let image = avif_decode::decode(bytes)?;
+            let transparent = image.pixels().any(|pixel| pixel[3] < 255);
+            return normalize_pixels(image, transparent);
+        }
+        _ => {}
+    }
+
+    let format = reader(bytes)?
+        .format()
+        .ok_or_else(|| "Not a supported image file".to_string())?;
+
+    // Models usually look at the first frame only; show them the motion instead
+    if let Some(sheet) = animation_sheet::contact_sheet(bytes, format)? {
+        let mut normalized = normalize_pixels(sheet.image, false)?;
+        normalized.info.animation_frames = Some(sheet.total_frames);
+        normalized.info.keyframes = Some(sheet.keyframes);
+        return Ok(normalized);
+    }
+
+    let keep_metadata = image_metadata::keep_metadata();
+    let exif = image_metadata::exif_block(bytes, format);
+    let orientation = image_metadata::image_orientation(bytes, format);
+
+    // Sideways photos have to be re-encoded; everything else only loses its metadata
+    let passthrough = passthrough_mime(format)
+        .filter(|_| bytes.len() <= MAX_CHAT_ATTACHMENT_BYTES && orientation == 1)
+        .and_then(|mime_type| {
+            let kept = if keep_metadata {
+                Some(bytes.to_vec())
+            } else {
+                image_metadata::strip(bytes, format)
+            };
+            kept.map(|kept| (kept, mime_type))
+        });
+    if let Some((kept, mime_type)) = passthrough {
+        let (width, height) = reader(bytes)?
+            .into_dimensions()
+            .map_err(|e| format!("Failed to read image: {}", e))?;
+        return Ok(NormalizedImage {
+            bytes: kept,
+            mime_type: mime_type.to_string(),
+            info: ImageInfo {
+                original_width: width,
+                original_height: height,
+                width,
+                height,
+                scale: 1.0,
+                reencoded: false,
+                ..ImageInfo::default()
+            },
+        });
+    }
+
+    let image = image_metadata::apply_orientation(decode(bytes)?, orientation);
+    let original = (image.width(), image.height());
+    let mut normalized = finish(original, fit_for_chat(image, false)?);
+
+    // Re-encoding drops all metadata; put EXIF back (now upright) when asked to keep it
+    if let Some(exif) = exif.filter(|_| keep_metadata && normalized.mime_type == "image/jpeg") {
+        if let Some(with_exif) =
+            image_metadata::insert_jpeg_exif(&normalized.bytes, &image_metadata::upright_exif(exif))
+        {
+            if with_exif.len() <= MAX_CHAT_ATTACHMENT_BYTES {
+                normalized.bytes = with_exif;
+            }
+        }
+    }
+    Ok(normalized)
+}
+
+/// Raw pixels from a screen capture. Area captures keep PNG while it fits; full-screen
+/// captures go straight to JPEG since the alpha channel is always opaque.
+pub fn normalize_capture(image: image::RgbaImage, prefer_png: bool) -> Result<NormalizedImage, String> {
+    normalize_pixels(image, prefer_png)
+}
+
+fn normalize_pixels(image: image::RgbaImage, prefer_png: bool) -> Result<NormalizedImage, String> {
+    let original = image.dimensions();
+    let image = if prefer_png {
+        DynamicImage::ImageRgba8(image)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(image).to_rgb8())
+    };
+    Ok(finish(original, fit_for_chat(image, prefer_png)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(format: ImageFormat) -> Vec<u8> {
+        let pixels = image::RgbaImage::from_pixel(4, 4, image::Rgba([9, 8, 7, 255]));
+        let image = DynamicImage::ImageRgba8(pixels);
+        let mut bytes = Vec::new();
+        image.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn formats_are_detected_from_content() {
+        for (format, mime) in [
+            (ImageFormat::Png, "image/png"),
+            (ImageFormat::Jpeg, "image/jpeg"),
+            (ImageFormat::Gif, "image/gif"),
+            (ImageFormat::Bmp, "image/bmp"),
+            (ImageFormat::Tiff, "image/tiff"),
+            (ImageFormat::Ico, "image/x-icon"),
+        ] {
+            let bytes = sample(format);
+            assert_eq!(sniff_image_mime(&bytes), Some(mime));
+            let normalized = normalize_bytes(&bytes).unwrap();
+            assert_eq!((normalized.info.width, normalized.info.height), (4, 4));
+        }
+        assert_eq!(
+            sniff_image_mime(b"\xEF\xBB\xBF<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(sniff_image_mime(b"<html><body><svg/></body></html>"), None);
+    }
+
+    #[test]
+    fn avif_goes_to_the_avif_decoder() {
+        // A bare ftyp box is sniffed as AVIF and fails in the decoder, not as "unsupported"
+        let mut avif = b"\0\0\0\x1cftypavif\0\0\0\0avifmif1miaf".to_vec();
+        avif.extend_from_slice(&[0; 64]);
+        assert_eq!(sniff_image_mime(&avif), Some("image/avif"));
+        let error = normalize_bytes(&avif).err().unwrap();
+        assert!(error.starts_with("Failed to decode image"), "{error}");
+
+        let heic = b"\0\0\0\x18ftypheic\0\0\0\0mif1heic".to_vec();
+        assert_eq!(sniff_image_mime(&heic), None);
+    }
+
+    /// Random pixels compress badly in every format, so they force the resize path.
+    fn noise_png(width: u32, height: u32) -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        let pixels = image::RgbImage::from_fn(width, height, |_, _| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let [r, g, b, _] = state.to_le_bytes();
+            image::Rgb([r, g, b])
+        });
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(pixels)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn oversized_images_are_shrunk_to_fit_keeping_the_aspect_ratio() {
+        let bytes = noise_png(3000, 2000);
+        assert!(bytes.len() > MAX_CHAT_ATTACHMENT_BYTES);
+
+        let normalized = normalize_bytes(&bytes).unwrap();
+        let info = normalized.info;
+        assert!(normalized.bytes.len() <= MAX_CHAT_ATTACHMENT_BYTES);
+        assert_eq!(normalized.mime_type, "image/jpeg");
+        assert!(info.reencoded);
+        assert_eq!((info.original_width, info.original_height), (3000, 2000));
+        assert!(info.width < 3000);
+        assert!((info.width as f32 / info.height as f32 - 1.5).abs() < 0.01);
+        assert!((info.scale - info.width as f32 / 3000.0).abs() < f32::EPSILON);
+
+        let decoded = decode(&normalized.bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (info.width, info.height));
+    }
+
+    #[test]
+    fn small_images_pass_through_without_reencoding() {
+        let bytes = sample(ImageFormat::Png);
+        let normalized = normalize_bytes(&bytes).unwrap();
+        let info = normalized.info;
+        assert!(!info.reencoded);
+        assert_eq!(normalized.mime_type, "image/png");
+        assert_eq!(info.scale, 1.0);
+        assert_eq!((info.original_width, info.original_height), (4, 4));
+        assert_eq!((info.width, info.height), (4, 4));
+        assert_eq!(decode(&normalized.bytes).unwrap().to_rgba8(), decode(&bytes).unwrap().to_rgba8());
+    }
+}
@@ -1,8 +1,32 @@
+mod animation_sheet;
+mod attachment_store;
+mod avif_decode;
 mod browser;
+mod capture_annotate;
+mod capture_backend;
+mod capture_geometry;
+mod capture_redact;
+mod capture_save;
+mod capture_visibility;
 mod config_reader;
+mod document_reader;
+mod download;
+mod folder_digest;
+mod git_context;
+mod history;
+mod history_crypto;
+mod history_export;
+mod hotkeys;
+mod http_client;
+mod image_metadata;
+mod image_pipeline;
 mod obsidian_clipper;
+mod secret_scan;
+mod session_import;
+mod svg_render;
+mod text_file;
+mod url_policy;
 
-use std::io::Cursor;
 use tauri::{
     image::Image,
     menu::{Menu, MenuItem},
@@ -14,17 +38,54 @@ use tauri::{
 struct FetchImageResult {
     base64: String,
     mime_type: String,
+    /// Hash in the attachment store, so history can reference the image without re-sending it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attachment_id: Option<String>,
+    /// Original vs. attached dimensions, so the UI can mention a downscale.
+    #[serde(flatten)]
+    info: image_pipeline::ImageInfo,
 }
 
-const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024; // 10MB
-const MAX_CHAT_ATTACHMENT_BYTES: usize = 4_800_000; // keep below OpenClaw 5MB gateway limit
+#[derive(serde::Serialize)]
+struct BrowserReadResult {
+    #[serde(flatten)]
+    page: browser::BrowserPageData,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    screenshot_attachment_id: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentReadResult {
+    #[serde(flatten)]
+    document: document_reader::DocumentText,
+    /// First page of a large or scanned PDF, attached as an image next to the text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preview: Option<FetchImageResult>,
+}
 
+/// Physical webview pixels of the area selected on the capture overlay.
 #[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct CaptureRegion {
     x: i32,
     y: i32,
     width: u32,
     height: u32,
+    /// Scale factor of the overlay window the region was drawn in (1 when omitted).
+    #[serde(default)]
+    scale_factor: Option<f64>,
+}
+
+/// Changes drawn into a capture before it is encoded: masks first, then the pointer and
+/// annotations on top. Positions are in pixels of the captured image.
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct CaptureEdits {
+    redactions: Vec<capture_redact::Redaction>,
+    annotations: Vec<capture_annotate::Annotation>,
+    /// Draw the mouse pointer where it is when the capture is taken.
+    show_cursor: bool,
 }
 
 #[derive(serde::Serialize)]
@@ -35,258 +96,289 @@ struct CaptureDisplayInfo {
     width: u32,
     height: u32,
     is_primary: bool,
+    scale_factor: f32,
+    rotation: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
 }
 
-fn is_safe_url(url: &str) -> bool {
-    let Ok(parsed) = url::Url::parse(url) else {
-        return false;
-    };
-    let scheme = parsed.scheme();
-    if scheme != "http" && scheme != "https" {
-        return false;
-    }
-    if let Some(host) = parsed.host_str() {
-        let lower = host.to_lowercase();
-        if lower == "localhost"
-            || lower == "127.0.0.1"
-            || lower == "[::1]"
-            || lower.ends_with(".local")
-            || lower.starts_with("10.")
-            || lower.starts_with("192.168.")
-        {
-            return false;
-        }
-    }
-    true
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CaptureWindowInfo {
+    id: u32,
+    title: String,
+    app_name: String,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    /// Display under the window's center.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
 }
 
-#[tauri::command]
-async fn fetch_image_url(url: String) -> Result<FetchImageResult, String> {
-    if !is_safe_url(&url) {
-        return Err("URL is not allowed".to_string());
-    }
-
-    let response = reqwest::get(&url).await.map_err(|e| e.to_string())?;
-
-    if let Some(content_len) = response.content_length() {
-        if content_len as usize > MAX_IMAGE_BYTES {
-            return Err("Image exceeds 10MB limit".to_string());
+fn store_attachment(store: &history::HistoryStore, bytes: &[u8], mime_type: &str) -> Option<String> {
+    match store.put_attachment(bytes, mime_type) {
+        Ok(stored) => Some(stored.hash),
+        Err(e) => {
+            eprintln!("[attachments] Failed to store attachment: {}", e);
+            None
         }
     }
+}
 
-    let header_mime_type = response
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("")
-        .split(';')
-        .next()
-        .unwrap_or("")
-        .trim()
-        .to_string();
-
-    let header_is_image = header_mime_type.starts_with("image/");
-
-    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+fn attach_image(
+    store: &history::HistoryStore,
+    image: image_pipeline::NormalizedImage,
+) -> FetchImageResult {
+    use base64::Engine;
+    let base64 = base64::engine::general_purpose::STANDARD.encode(&image.bytes);
+    let attachment_id = store_attachment(store, &image.bytes, &image.mime_type);
 
-    if bytes.len() > MAX_IMAGE_BYTES {
-        return Err("Image exceeds 10MB limit".to_string());
+    FetchImageResult {
+        base64,
+        mime_type: image.mime_type,
+        attachment_id,
+        info: image.info,
     }
+}
 
-    let mime_type = if header_is_image {
-        header_mime_type
-    } else {
-        detect_image_mime(&url, &bytes).ok_or_else(|| "URL is not a supported image".to_string())?
-    };
-
-    use base64::Engine;
-    let base64 = base64::engine::general_purpose::STANDARD.encode(&bytes);
-
-    Ok(FetchImageResult { base64, mime_type })
+/// Encoded bytes from a file, URL or the webview, normalized and stored.
+async fn attach_source(store: &history::HistoryStore, bytes: Vec<u8>) -> Result<FetchImageResult, String> {
+    // Decoding, resizing and animation sheets take seconds on large inputs; keep them off the async runtime
+    let image = tauri::async_runtime::spawn_blocking(move || image_pipeline::normalize_bytes(&bytes))
+        .await
+        .map_err(|e| format!("Failed to process image: {e}"))??;
+    Ok(attach_image(store, image))
 }
 
-fn detect_image_mime(path: &str, bytes: &[u8]) -> Option<String> {
-    // Magic bytes detection
-    if bytes.len() >= 4 && bytes[..4] == [0x89, 0x50, 0x4E, 0x47] {
-        return Some("image/png".into());
-    }
-    if bytes.len() >= 3 && bytes[..3] == [0xFF, 0xD8, 0xFF] {
-        return Some("image/jpeg".into());
-    }
-    if bytes.len() >= 4 && &bytes[..4] == b"GIF8" {
-        return Some("image/gif".into());
-    }
-    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
-        return Some("image/webp".into());
-    }
-    // Fallback to extension (strip URL query/hash if present)
-    let clean_path = path
-        .split('?')
-        .next()
-        .unwrap_or(path)
-        .split('#')
-        .next()
-        .unwrap_or(path);
-
-    match clean_path.rsplit('.').next().map(|s| s.to_lowercase()).as_deref() {
-        Some("png") => Some("image/png".into()),
-        Some("jpg") | Some("jpeg") => Some("image/jpeg".into()),
-        Some("gif") => Some("image/gif".into()),
-        Some("webp") => Some("image/webp".into()),
-        Some("bmp") => Some("image/bmp".into()),
-        _ => None, // Not a recognized image
-    }
+#[tauri::command]
+async fn fetch_image_url(
+    store: tauri::State<'_, history::HistoryStore>,
+    url: String,
+    request_id: Option<String>,
+) -> Result<FetchImageResult, String> {
+    let registration = download::register(request_id);
+    let limits = download::DownloadLimits {
+        max_bytes: image_pipeline::MAX_SOURCE_IMAGE_BYTES,
+        user_agent: None,
+        public_only: true,
+    };
+    let downloaded = download::download_image(&url, &limits, &registration.token).await?;
+    attach_source(&store, downloaded.bytes).await
 }
 
 #[tauri::command]
-async fn read_image_file(path: String) -> Result<FetchImageResult, String> {
+async fn read_image_file(
+    store: tauri::State<'_, history::HistoryStore>,
+    path: String,
+) -> Result<FetchImageResult, String> {
     let canonical = std::fs::canonicalize(&path)
         .map_err(|e| format!("Failed to read file: {e}"))?;
     let path_str = canonical.to_string_lossy();
     if path_str.contains("..") {
         return Err("Invalid file path".to_string());
     }
-    let bytes = std::fs::read(&canonical).map_err(|e| format!("Failed to read file: {e}"))?;
-
-    if bytes.len() > MAX_IMAGE_BYTES {
-        return Err("Image exceeds 10MB limit".to_string());
+    let size = std::fs::metadata(&canonical)
+        .map_err(|e| format!("Failed to read file: {e}"))?
+        .len();
+    if size > image_pipeline::MAX_SOURCE_IMAGE_BYTES as u64 {
+        return Err(format!(
+            "Image exceeds {}MB limit",
+            image_pipeline::MAX_SOURCE_IMAGE_BYTES / (1024 * 1024)
+        ));
     }
-
-    let mime_type = detect_image_mime(&path, &bytes)
-        .ok_or_else(|| "Not a supported image file".to_string())?;
-
-    use base64::Engine;
-    let base64 = base64::engine::general_purpose::STANDARD.encode(&bytes);
-
-    Ok(FetchImageResult { base64, mime_type })
+    let bytes = std::fs::read(&canonical).map_err(|e| format!("Failed to read file: {e}"))?;
+    attach_source(&store, bytes).await
 }
 
 #[tauri::command]
-async fn capture_screen_region(region: CaptureRegion) -> Result<FetchImageResult, String> {
-    if region.width == 0 || region.height == 0 {
-        return Err("Capture area is empty".to_string());
-    }
-
-    let screens = screenshots::Screen::all().map_err(|e| format!("Failed to list screens: {e}"))?;
-    if screens.is_empty() {
-        return Err("No display found".to_string());
-    }
-
-    let center_x = region.x.saturating_add((region.width / 2) as i32);
-    let center_y = region.y.saturating_add((region.height / 2) as i32);
-
-    let screen = screens
-        .iter()
-        .find(|screen| {
-            let info = screen.display_info;
-            let right = info.x + info.width as i32;
-            let bottom = info.y + info.height as i32;
-            center_x >= info.x && center_x < right && center_y >= info.y && center_y < bottom
-        })
-        .or_else(|| screens.first())
-        .ok_or_else(|| "No display found".to_string())?;
-
-    let info = screen.display_info;
-    let screen_right = info.x + info.width as i32;
-    let screen_bottom = info.y + info.height as i32;
-
-    let left = region.x.clamp(info.x, screen_right.saturating_sub(1));
-    let top = region.y.clamp(info.y, screen_bottom.saturating_sub(1));
-
-    let max_width = (screen_right - left).max(0) as u32;
-    let max_height = (screen_bottom - top).max(0) as u32;
-    let width = region.width.min(max_width);
-    let height = region.height.min(max_height);
-
-    if width == 0 || height == 0 {
-        return Err("Capture area is outside of the display".to_string());
-    }
-
-    let rel_x = left - info.x;
-    let rel_y = top - info.y;
-
-    let image = screen
-        .capture_area(rel_x, rel_y, width, height)
-        .map_err(|e| format!("Failed to capture screen: {e}"))?;
-
-    let mut png_bytes = Vec::new();
-    {
-        let mut cursor = Cursor::new(&mut png_bytes);
-        image::DynamicImage::ImageRgba8(image)
-            .write_to(&mut cursor, image::ImageFormat::Png)
-            .map_err(|e| format!("Failed to encode screenshot: {e}"))?;
+async fn read_document_file(
+    store: tauri::State<'_, history::HistoryStore>,
+    path: String,
+) -> Result<DocumentReadResult, String> {
+    let canonical = std::fs::canonicalize(&path)
+        .map_err(|e| format!("Failed to read file: {e}"))?;
+    let size = std::fs::metadata(&canonical)
+        .map_err(|e| format!("Failed to read file: {e}"))?
+        .len();
+    if size > document_reader::MAX_DOCUMENT_BYTES {
+        return Err(format!(
+            "Document exceeds {}MB limit",
+            document_reader::MAX_DOCUMENT_BYTES / (1024 * 1024)
+        ));
     }
+    // PDF parsing and page rendering take seconds on large files; keep them off the async runtime
+    let (extracted, preview) = tauri::async_runtime::spawn_blocking(move || {
+        let bytes = std::fs::read(&canonical).map_err(|e| format!("Failed to read file: {e}"))?;
+        let extracted = document_reader::extract(&canonical, &bytes)?;
+        let preview = if extracted.wants_preview {
+            match document_reader::render_first_page(&bytes) {
+                Ok(Some(page)) => image_pipeline::normalize_capture(page, true).ok(),
+                Ok(None) => None,
+                Err(e) => {
+                    eprintln!("[documents] {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Ok::<_, String>((extracted, preview))
+    })
+    .await
+    .map_err(|e| format!("Failed to read document: {e}"))??;
 
-    if png_bytes.len() > MAX_IMAGE_BYTES {
-        return Err("Image exceeds 10MB limit".to_string());
-    }
+    Ok(DocumentReadResult {
+        document: extracted.text,
+        preview: preview.map(|image| attach_image(&store, image)),
+    })
+}
 
+/// Images the webview read itself (HTML5 file drop, clipboard paste) go through the same
+/// pipeline as files and URLs before they are attached.
+#[tauri::command]
+async fn normalize_image_data(
+    store: tauri::State<'_, history::HistoryStore>,
+    base64: String,
+) -> Result<FetchImageResult, String> {
     use base64::Engine;
-    let base64 = base64::engine::general_purpose::STANDARD.encode(&png_bytes);
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64.trim())
+        .map_err(|e| format!("Failed to decode image: {e}"))?;
+    attach_source(&store, bytes).await
+}
 
-    Ok(FetchImageResult {
-        base64,
-        mime_type: "image/png".to_string(),
-    })
+/// Mouse pointer position on a capture of `frame`, if it is inside.
+fn cursor_in_capture(
+    app: &tauri::AppHandle,
+    frame: capture_backend::CaptureFrame,
+    image: &image::RgbaImage,
+) -> Option<capture_annotate::Point> {
+    let position = app.cursor_position().ok()?;
+    let backend = capture_backend::active();
+    let displays = backend.displays().ok()?;
+    let point = capture_backend::physical_to_display_units(&displays, backend.units(), position.x, position.y);
+    let (x, y) = frame.pixel(point, image.dimensions())?;
+    Some(capture_annotate::Point { x, y })
 }
 
-fn encode_dynamic_as_jpeg(
-    image: &image::DynamicImage,
-    quality: u8,
-) -> Result<Vec<u8>, String> {
-    let mut bytes = Vec::new();
-    {
-        let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality);
-        encoder
-            .encode_image(image)
-            .map_err(|e| format!("Failed to encode screenshot: {e}"))?;
+/// Edit, save, encode and attach a raw capture. Redactions are applied before anything
+/// is encoded or written, so the hidden pixels never leave the backend.
+fn finish_capture(
+    app: &tauri::AppHandle,
+    store: &history::HistoryStore,
+    mut image: image::RgbaImage,
+    frame: Option<capture_backend::CaptureFrame>,
+    edits: &CaptureEdits,
+    context: capture_save::CaptureContext,
+    prefer_png: bool,
+) -> Result<FetchImageResult, String> {
+    capture_redact::apply(&mut image, &edits.redactions);
+    if edits.show_cursor {
+        if let Some(tip) = frame.and_then(|frame| cursor_in_capture(app, frame, &image)) {
+            capture_annotate::draw_cursor(&mut image, tip);
+        }
     }
-    Ok(bytes)
-}
+    capture_annotate::apply(&mut image, &edits.annotations);
+    // The file on disk gets the edits too, but not the chat's downscaling
+    capture_save::save(app, &image, context);
+    let image = image_pipeline::normalize_capture(image, prefer_png)?;
 
-fn encode_screen_for_chat(image: image::RgbaImage) -> Result<Vec<u8>, String> {
-    let mut current = image::DynamicImage::ImageRgba8(image);
-    let qualities = [88u8, 80, 72, 64, 56, 48];
+    Ok(attach_image(store, image))
+}
 
-    for _ in 0..5 {
-        for quality in qualities {
-            let encoded = encode_dynamic_as_jpeg(&current, quality)?;
-            if encoded.len() <= MAX_CHAT_ATTACHMENT_BYTES {
-                return Ok(encoded);
-            }
-        }
+/// Hide ClawPet, grab the target from the active capture backend, and attach the result.
+async fn capture_and_attach(
+    app: &tauri::AppHandle,
+    store: &history::HistoryStore,
+    target: capture_backend::CaptureTarget,
+    kind: &'static str,
+    hide_windows: Option<bool>,
+    edits: &CaptureEdits,
+    prefer_png: bool,
+) -> Result<FetchImageResult, String> {
+    let backend = capture_backend::active();
+    let hidden = capture_visibility::hide_for_capture(app, hide_windows).await;
+    let image = capture_backend::capture(&*backend, &target)?;
+    drop(hidden);
+
+    let context = capture_save::CaptureContext {
+        kind,
+        display: Some(display_name(app, backend.units(), &target.display)),
+        ..Default::default()
+    };
+    finish_capture(app, store, image, Some(target.frame()), edits, context, prefer_png)
+}
 
-        let next_width = ((current.width() as f32) * 0.82).round() as u32;
-        let next_height = ((current.height() as f32) * 0.82).round() as u32;
-        if next_width < 640 || next_height < 360 {
-            break;
-        }
+#[tauri::command]
+async fn capture_screen_region(
+    app: tauri::AppHandle,
+    store: tauri::State<'_, history::HistoryStore>,
+    region: CaptureRegion,
+    hide_windows: Option<bool>,
+    edits: Option<CaptureEdits>,
+) -> Result<FetchImageResult, String> {
+    let target = capture_backend::region_target(
+        &*capture_backend::active(),
+        (region.x, region.y, region.width, region.height),
+        region.scale_factor.unwrap_or(1.0),
+    )?;
+    capture_and_attach(&app, &store, target, "region", hide_windows, &edits.unwrap_or_default(), true).await
+}
 
-        current = current.resize(
-            next_width,
-            next_height,
-            image::imageops::FilterType::Triangle,
-        );
-    }
+/// The display's name from the capture backend, or from the window system's monitor
+/// list where the backend doesn't know it.
+fn monitor_name(
+    monitors: &[tauri::Monitor],
+    units: capture_geometry::DisplayUnits,
+    display: &capture_backend::Display,
+) -> Option<String> {
+    display.name.clone().or_else(|| {
+        monitors
+            .iter()
+            .find(|monitor| {
+                let position = monitor.position();
+                capture_geometry::monitor_origin(units, position.x, position.y, monitor.scale_factor())
+                    == (display.x, display.y)
+            })
+            .and_then(|monitor| monitor.name().cloned())
+    })
+}
 
-    Err("Captured screen is too large to attach. Try area capture.".to_string())
+/// Name of a captured display for saved file names.
+fn display_name(
+    app: &tauri::AppHandle,
+    units: capture_geometry::DisplayUnits,
+    display: &capture_backend::Display,
+) -> String {
+    let monitors = app.available_monitors().unwrap_or_default();
+    monitor_name(&monitors, units, display).unwrap_or_else(|| format!("display{}", display.id))
 }
 
 #[tauri::command]
-async fn list_capture_displays() -> Result<Vec<CaptureDisplayInfo>, String> {
-    let screens = screenshots::Screen::all().map_err(|e| format!("Failed to list screens: {e}"))?;
-
-    let mut displays: Vec<CaptureDisplayInfo> = screens
-        .iter()
-        .map(|screen| {
-            let info = screen.display_info;
+async fn list_capture_displays(app: tauri::AppHandle) -> Result<Vec<CaptureDisplayInfo>, String> {
+    let backend = capture_backend::active();
+    let units = backend.units();
+    let monitors = app.available_monitors().unwrap_or_default();
+
+    let mut displays: Vec<CaptureDisplayInfo> = backend
+        .displays()?
+        .into_iter()
+        .map(|display| {
+            let name = monitor_name(&monitors, units, &display);
             CaptureDisplayInfo {
-                id: info.id,
-                x: info.x,
-                y: info.y,
-                width: info.width,
-                height: info.height,
-                is_primary: info.is_primary,
+                id: display.id,
+                x: display.x,
+                y: display.y,
+                width: display.width,
+                height: display.height,
+                is_primary: display.is_primary,
+                scale_factor: display.scale_factor,
+                rotation: display.rotation,
+                name,
             }
         })
         .collect();
@@ -296,66 +388,185 @@ async fn list_capture_displays() -> Result<Vec<CaptureDisplayInfo>, String> {
 }
 
 #[tauri::command]
-async fn capture_screen_display(display_id: u32) -> Result<FetchImageResult, String> {
-    let screens = screenshots::Screen::all().map_err(|e| format!("Failed to list screens: {e}"))?;
+async fn capture_screen_display(
+    app: tauri::AppHandle,
+    store: tauri::State<'_, history::HistoryStore>,
+    display_id: u32,
+    hide_windows: Option<bool>,
+    edits: Option<CaptureEdits>,
+) -> Result<FetchImageResult, String> {
+    let target = capture_backend::display_target(&*capture_backend::active(), display_id)?;
+    capture_and_attach(&app, &store, target, "display", hide_windows, &edits.unwrap_or_default(), false).await
+}
 
-    let screen = screens
-        .iter()
-        .find(|screen| screen.display_info.id == display_id)
-        .ok_or_else(|| "Selected display not found".to_string())?;
+/// Every display stitched into one image at its position on the virtual desktop.
+#[tauri::command]
+async fn capture_all_displays(
+    app: tauri::AppHandle,
+    store: tauri::State<'_, history::HistoryStore>,
+    hide_windows: Option<bool>,
+    edits: Option<CaptureEdits>,
+) -> Result<FetchImageResult, String> {
+    let backend = capture_backend::active();
+    let hidden = capture_visibility::hide_for_capture(&app, hide_windows).await;
+    let image = capture_backend::capture_all(&*backend)?;
+    drop(hidden);
+
+    let frame = capture_backend::desktop_frame(&backend.displays()?);
+    let context = capture_save::CaptureContext {
+        kind: "all",
+        ..Default::default()
+    };
+    finish_capture(&app, &store, image, Some(frame), &edits.unwrap_or_default(), context, false)
+}
 
-    let image = screen
-        .capture()
-        .map_err(|e| format!("Failed to capture screen: {e}"))?;
+/// `x`/`y` are physical webview coordinates (the pet's center); `scale_factor` is the
+/// pet window's, needed where displays are measured in points.
+#[tauri::command]
+async fn capture_screen_for_point(
+    app: tauri::AppHandle,
+    store: tauri::State<'_, history::HistoryStore>,
+    x: i32,
+    y: i32,
+    scale_factor: Option<f64>,
+    hide_windows: Option<bool>,
+    edits: Option<CaptureEdits>,
+) -> Result<FetchImageResult, String> {
+    let target = capture_backend::point_target(
+        &*capture_backend::active(),
+        x,
+        y,
+        scale_factor.unwrap_or(1.0),
+    )?;
+    capture_and_attach(&app, &store, target, "display", hide_windows, &edits.unwrap_or_default(), false).await
+}
 
-    let jpeg_bytes = encode_screen_for_chat(image)?;
+/// Display under the mouse pointer, for the capture hotkey: the pet may be on another
+/// screen than the one being worked on.
+#[tauri::command]
+async fn capture_screen_under_cursor(
+    app: tauri::AppHandle,
+    store: tauri::State<'_, history::HistoryStore>,
+    hide_windows: Option<bool>,
+    edits: Option<CaptureEdits>,
+) -> Result<FetchImageResult, String> {
+    let position = app
+        .cursor_position()
+        .map_err(|e| format!("Failed to read pointer position: {e}"))?;
+    let backend = capture_backend::active();
+    let (x, y) = capture_backend::physical_to_display_units(&backend.displays()?, backend.units(), position.x, position.y);
+    let target = capture_backend::point_target(&*backend, x.round() as i32, y.round() as i32, 1.0)?;
+    capture_and_attach(&app, &store, target, "display", hide_windows, &edits.unwrap_or_default(), false).await
+}
 
-    use base64::Engine;
-    let base64 = base64::engine::general_purpose::STANDARD.encode(&jpeg_bytes);
+#[tauri::command]
+async fn list_capture_windows(app: tauri::AppHandle) -> Result<Vec<CaptureWindowInfo>, String> {
+    let backend = capture_backend::active();
+    let displays = backend.displays()?;
+    let is_own = capture_visibility::is_own_window(&app);
+
+    Ok(backend
+        .windows()?
+        .into_iter()
+        .filter(|window| !window.is_minimized && !window.title.trim().is_empty() && !is_own(window))
+        .map(|window| {
+            let display = capture_backend::display_for_window(&displays, backend.units(), &window)
+                .map(|index| &displays[index]);
+            CaptureWindowInfo {
+                id: window.id,
+                title: window.title,
+                app_name: window.app_name,
+                x: window.x,
+                y: window.y,
+                width: window.width,
+                height: window.height,
+                display_id: display.map(|display| display.id),
+                display_name: display.and_then(|display| display.name.clone()),
+            }
+        })
+        .collect())
+}
 
-    Ok(FetchImageResult {
-        base64,
-        mime_type: "image/jpeg".to_string(),
-    })
+async fn capture_window_and_attach(
+    app: &tauri::AppHandle,
+    store: &history::HistoryStore,
+    window_id: u32,
+    hide_windows: Option<bool>,
+    edits: &CaptureEdits,
+) -> Result<FetchImageResult, String> {
+    let backend = capture_backend::active();
+    let window = capture_backend::find_window(&*backend, window_id)?;
+    let hidden = capture_visibility::hide_for_capture(app, hide_windows).await;
+    let image = backend.capture_window(window_id)?;
+    drop(hidden);
+
+    let frame = edits.show_cursor.then(|| window.frame());
+    let context = capture_save::CaptureContext {
+        kind: "window",
+        display: None,
+        app: Some(window.app_name),
+        title: Some(window.title),
+    };
+    finish_capture(app, store, image, frame, edits, context, true)
 }
 
 #[tauri::command]
-async fn capture_screen_for_point(x: i32, y: i32) -> Result<FetchImageResult, String> {
-    let screens = screenshots::Screen::all().map_err(|e| format!("Failed to list screens: {e}"))?;
-    if screens.is_empty() {
-        return Err("No display found".to_string());
-    }
+async fn capture_screen_window(
+    app: tauri::AppHandle,
+    store: tauri::State<'_, history::HistoryStore>,
+    window_id: u32,
+    hide_windows: Option<bool>,
+    edits: Option<CaptureEdits>,
+) -> Result<FetchImageResult, String> {
+    capture_window_and_attach(&app, &store, window_id, hide_windows, &edits.unwrap_or_default()).await
+}
 
-    let screen = screens
-        .iter()
-        .find(|screen| {
-            let info = screen.display_info;
-            let right = info.x + info.width as i32;
-            let bottom = info.y + info.height as i32;
-            x >= info.x && x < right && y >= info.y && y < bottom
-        })
-        .or_else(|| screens.first())
-        .ok_or_else(|| "No display found".to_string())?;
+/// Capture the window that was focused before the pet was clicked.
+#[tauri::command]
+async fn capture_active_window(
+    app: tauri::AppHandle,
+    store: tauri::State<'_, history::HistoryStore>,
+    hide_windows: Option<bool>,
+    edits: Option<CaptureEdits>,
+) -> Result<FetchImageResult, String> {
+    let window = capture_backend::active_window(&*capture_backend::active(), capture_visibility::is_own_window(&app))?;
+    capture_window_and_attach(&app, &store, window.id, hide_windows, &edits.unwrap_or_default()).await
+}
 
-    let image = screen
-        .capture()
-        .map_err(|e| format!("Failed to capture screen: {e}"))?;
-    let jpeg_bytes = encode_screen_for_chat(image)?;
+#[tauri::command]
+async fn read_browser_page(
+    app: tauri::AppHandle,
+    store: tauri::State<'_, history::HistoryStore>,
+    pet_x: i32,
+    pet_y: i32,
+) -> Result<BrowserReadResult, String> {
+    let page = browser::read_page(pet_x, pet_y).await?;
 
     use base64::Engine;
-    let base64 = base64::engine::general_purpose::STANDARD.encode(&jpeg_bytes);
+    let screenshot = base64::engine::general_purpose::STANDARD
+        .decode(&page.screenshot)
+        .ok()
+        .filter(|bytes| !bytes.is_empty());
+    if let Some(image) = screenshot
+        .as_deref()
+        .and_then(|bytes| image::load_from_memory(bytes).ok())
+    {
+        let context = capture_save::CaptureContext {
+            kind: "browser",
+            display: None,
+            app: Some("browser".to_string()),
+            title: Some(page.title.clone()),
+        };
+        capture_save::save(&app, &image.to_rgba8(), context);
+    }
+    let screenshot_attachment_id = screenshot.and_then(|bytes| store_attachment(&store, &bytes, "image/jpeg"));
 
-    Ok(FetchImageResult {
-        base64,
-        mime_type: "image/jpeg".to_string(),
+    Ok(BrowserReadResult {
+        page,
+        screenshot_attachment_id,
     })
 }
 
-#[tauri::command]
-async fn read_browser_page(pet_x: i32, pet_y: i32) -> Result<browser::BrowserPageData, String> {
-    browser::read_page(pet_x, pet_y).await
-}
-
 #[tauri::command]
 async fn clip_page_to_obsidian(pet_x: i32, pet_y: i32) -> Result<obsidian_clipper::ClipResult, String> {
     obsidian_clipper::clip_to_obsidian(pet_x, pet_y).await
@@ -365,24 +576,70 @@ async fn clip_page_to_obsidian(pet_x: i32, pet_y: i32) -> Result<obsidian_clippe
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_window_state::Builder::new().build())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(hotkeys::handle)
+                .build(),
+        )
         .invoke_handler(tauri::generate_handler![
             config_reader::read_openclaw_config,
             config_reader::read_openclaw_identity,
+            session_import::import_openclaw_sessions,
+            history::history_append,
+            history::history_page,
+            history::history_search,
+            history::history_delete,
+            history::history_migrate_local,
+            history::history_apply_retention,
+            history::history_encryption_status,
+            history::history_enable_encryption,
+            history::history_unlock,
+            history::history_lock,
+            history::panic_wipe,
+            history_export::export_history,
+            attachment_store::attachment_original,
+            attachment_store::attachment_thumbnail,
             fetch_image_url,
             read_image_file,
+            normalize_image_data,
+            read_document_file,
+            text_file::read_text_file,
+            folder_digest::read_folder_digest,
+            git_context::read_git_context,
             capture_screen_region,
             list_capture_displays,
             capture_screen_display,
             capture_screen_for_point,
+            capture_screen_under_cursor,
+            capture_all_displays,
+            list_capture_windows,
+            capture_screen_window,
+            capture_active_window,
             read_browser_page,
-            clip_page_to_obsidian
+            clip_page_to_obsidian,
+            capture_save::reveal_last_capture,
+            capture_backend::set_capture_backend,
+            hotkeys::list_hotkeys,
+            hotkeys::set_hotkey,
+            download::cancel_download
         ])
         .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            let retention = history::RetentionPolicy::from_env();
+            // A broken database must not keep the pet from starting; a panic wipe resets it
+            let history_store = history::HistoryStore::open(&data_dir, retention).unwrap_or_else(|e| {
+                eprintln!("[history] {}; running with history disabled", e);
+                history::HistoryStore::disabled(&data_dir, retention, e)
+            });
+            app.manage(history_store);
+
             // Build tray menu
             let show = MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
             let hide = MenuItem::with_id(app, "hide", "Hide", true, None::<&str>)?;
+            let recent = capture_save::tray_submenu(app.handle())?;
+            let backends = capture_backend::tray_submenu(app.handle())?;
             let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show, &hide, &quit])?;
+            let menu = Menu::with_items(app, &[&show, &hide, &recent, &backends, &quit])?;
 
             let tray_icon = Image::from_bytes(include_bytes!("../icons/32x32.png"));
             let icon = match tray_icon {
@@ -411,7 +668,10 @@ pub fn run() {
                     "quit" => {
                         app.exit(0);
                     }
-                    _ => {}
+                    id => {
+                        capture_save::handle_tray_event(id);
+                        capture_backend::handle_tray_event(app, id);
+                    }
                 })
                 .on_tray_icon_event(|tray, event| {
                     if let TrayIconEvent::Click {
@@ -429,6 +689,8 @@ pub fn run() {
                 })
                 .build(app)?;
 
+            hotkeys::register(app.handle());
+
             // Show the main window after setup
             if let Some(win) = app.get_webview_window("main") {
                 let _ = win.show();
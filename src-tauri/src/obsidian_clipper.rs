@@ -1,4 +1,5 @@
 use crate::browser;
+use crate::download;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -7,6 +8,8 @@ use std::sync::LazyLock;
 const DEFAULT_OBSIDIAN_BASE: &str = r"C:\obsidian\doyeon\03 Resources";
 const DEFAULT_OBSIDIAN_IMG_DIR: &str = r"C:\obsidian\doyeon\03 Resources\ref_img";
 const EXTRACTION_JS: &str = include_str!("extract_page.js");
+const MAX_CLIP_IMAGE_BYTES: usize = 20 * 1024 * 1024; // 20MB
+const CLIP_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36";
 
 static RE_IMG_PLACEHOLDER: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"\{\{IMG:\d+\}\}").unwrap());
@@ -175,7 +178,7 @@ fn generate_base_name(page: &ExtractedPage) -> String {
     }
 }
 
-fn unique_path(folder: &PathBuf, name: &str, ext: &str) -> PathBuf {
+pub(crate) fn unique_path(folder: &PathBuf, name: &str, ext: &str) -> PathBuf {
     let candidate = folder.join(format!("{}.{}", name, ext));
     if !candidate.exists() {
         return candidate;
@@ -208,28 +211,31 @@ fn compress_to_jpeg(raw_bytes: &[u8], quality: u8) -> Result<Vec<u8>, String> {
     Ok(buf)
 }
 
-async fn download_image(url: &str) -> Result<(Vec<u8>, String), String> {
-    let client = reqwest::Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let resp = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| format!("Download failed: {}", e))?;
-
-    if !resp.status().is_success() {
-        return Err(format!("HTTP {}", resp.status()));
+/// File extension for a sniffed image type (see `download::sniff_image_mime`).
+fn extension_for_mime(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        "image/tiff" => "tiff",
+        "image/x-icon" => "ico",
+        "image/avif" => "avif",
+        "image/svg+xml" => "svg",
+        _ => "jpg",
     }
+}
 
-    let bytes = resp
-        .bytes()
-        .await
-        .map_err(|e| format!("Download read error: {}", e))?;
-
-    let raw = bytes.to_vec();
+async fn download_image(url: &str) -> Result<(Vec<u8>, String), String> {
+    let registration = download::register(None);
+    let limits = download::DownloadLimits {
+        max_bytes: MAX_CLIP_IMAGE_BYTES,
+        user_agent: Some(CLIP_USER_AGENT),
+        // Clipped pages may live on an intranet or LAN; their images have to come along
+        public_only: false,
+    };
+    let downloaded = download::download_image(url, &limits, &registration.token).await?;
+    let raw = downloaded.bytes;
     let original_size = raw.len();
 
     // Compress to JPEG 80% quality (significant size reduction)
@@ -245,7 +251,7 @@ async fn download_image(url: &str) -> Result<(Vec<u8>, String), String> {
         }
         Err(_) => {
             // If compression fails, save original
-            Ok((raw, "jpg".to_string()))
+            Ok((raw, extension_for_mime(&downloaded.mime_type).to_string()))
         }
     }
 }
@@ -0,0 +1,397 @@
+use crate::config_reader;
+use crate::history::{HistoryAttachment, HistoryEntry, HistoryStore};
+use base64::Engine;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tauri::Manager;
+
+const DEFAULT_AGENT_ID: &str = "main";
+const MAX_SESSION_FILE_BYTES: u64 = 64 * 1024 * 1024; // 64MB
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionImportResult {
+    pub imported: usize,
+    pub parsed: usize,
+    pub session_files: usize,
+}
+
+// ---------- Session discovery ----------
+
+fn sessions_dir() -> Result<PathBuf, String> {
+    let state_dir = config_reader::discover_state_dir()
+        .ok_or_else(|| "OpenClaw state directory not found".to_string())?;
+    let agent_id = config_reader::discover_default_agent_id()
+        .unwrap_or_else(|| DEFAULT_AGENT_ID.to_string());
+
+    Ok(state_dir.join("agents").join(agent_id).join("sessions"))
+}
+
+fn find_session_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            // Skip `*.jsonl.deleted.*` and other leftovers: the extension must be exactly jsonl
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| ext.eq_ignore_ascii_case("jsonl"))
+                    .unwrap_or(false)
+        })
+        .collect();
+
+    files.sort();
+    files
+}
+
+/// `sessions.json` maps gateway session keys to transcript ids; invert it so imported
+/// entries carry the same session key the pet uses when it appends.
+fn read_session_keys(dir: &Path) -> HashMap<String, String> {
+    let mut keys = HashMap::new();
+    let Ok(content) = fs::read_to_string(dir.join("sessions.json")) else {
+        return keys;
+    };
+    let Ok(json) = serde_json::from_str::<Value>(&content) else {
+        return keys;
+    };
+
+    for (session_key, meta) in json.as_object().into_iter().flatten() {
+        if let Some(session_id) = meta.get("sessionId").and_then(|v| v.as_str()) {
+            keys.insert(session_id.to_string(), session_key.clone());
+        }
+    }
+
+    keys
+}
+
+// ---------- Transcript parsing ----------
+
+fn parse_timestamp(value: &Value) -> Option<i64> {
+    if let Some(ms) = value.as_i64() {
+        return Some(ms);
+    }
+    if let Some(ms) = value.as_f64() {
+        return Some(ms as i64);
+    }
+    let raw = value.as_str()?;
+    chrono::DateTime::parse_from_rfc3339(raw.trim())
+        .ok()
+        .map(|dt| dt.timestamp_millis())
+}
+
+fn parse_attachment(block: &Value) -> HistoryAttachment {
+    // OpenClaw: { type: "image", data, mimeType }
+    // Anthropic-style: { type: "image", source: { data, media_type } }
+    let source = block.get("source").unwrap_or(block);
+    let mime_type = source
+        .get("mimeType")
+        .or_else(|| source.get("media_type"))
+        .or_else(|| source.get("mime_type"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    let data = source
+        .get("data")
+        .and_then(|v| v.as_str())
+        .and_then(|b64| base64::engine::general_purpose::STANDARD.decode(b64).ok());
+
+    let file_name = block
+        .get("fileName")
+        .or_else(|| block.get("name"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    HistoryAttachment {
+        mime_type,
+        file_name,
+        size_bytes: data.as_ref().map(|d| d.len()).unwrap_or(0),
+        hash: None,
+        data,
+    }
+}
+
+fn parse_content(content: &Value) -> (String, Vec<HistoryAttachment>) {
+    if let Some(text) = content.as_str() {
+        return (text.trim().to_string(), Vec::new());
+    }
+
+    let mut texts: Vec<&str> = Vec::new();
+    let mut attachments = Vec::new();
+
+    for block in content.as_array().into_iter().flatten() {
+        match block.get("type").and_then(|v| v.as_str()) {
+            Some("text") => {
+                if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
+                    let trimmed = text.trim();
+                    if !trimmed.is_empty() {
+                        texts.push(trimmed);
+                    }
+                }
+            }
+            Some("image") => attachments.push(parse_attachment(block)),
+            // thinking, toolCall, etc. are not part of the visible conversation
+            _ => {}
+        }
+    }
+
+    (texts.join("\n\n"), attachments)
+}
+
+fn parse_session_line(
+    line: &str,
+    session_id: &str,
+    session_key: &str,
+    line_index: usize,
+    fallback_timestamp: i64,
+) -> Option<HistoryEntry> {
+    let json: Value = serde_json::from_str(line).ok()?;
+
+    // Transcript lines wrap the message: { type: "message", id, timestamp, message: {...} }
+    if let Some(kind) = json.get("type").and_then(|v| v.as_str()) {
+        if kind != "message" {
+            return None;
+        }
+    }
+    let message = json.get("message").unwrap_or(&json);
+
+    let role = message.get("role").and_then(|v| v.as_str())?;
+    if role != "user" && role != "assistant" {
+        return None;
+    }
+
+    let (text, attachments) = parse_content(message.get("content")?);
+    if text.is_empty() && attachments.is_empty() {
+        return None;
+    }
+
+    let timestamp = message
+        .get("timestamp")
+        .and_then(parse_timestamp)
+        .or_else(|| json.get("timestamp").and_then(parse_timestamp))
+        .unwrap_or(fallback_timestamp);
+
+    let message_id = json
+        .get("id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| format!("line{}", line_index));
+
+    Some(HistoryEntry {
+        id: format!("openclaw:{}:{}", session_id, message_id),
+        role: role.to_string(),
+        text,
+        timestamp,
+        session: Some(session_key.to_string()),
+        attachments,
+    })
+}
+
+fn file_modified_ms(path: &Path) -> i64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn parse_session_file(
+    path: &Path,
+    session_keys: &HashMap<String, String>,
+) -> Result<Vec<HistoryEntry>, String> {
+    let size = fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| format!("failed to read session: {}", e))?;
+    if size > MAX_SESSION_FILE_BYTES {
+        return Err(format!("session file too large: {}", path.display()));
+    }
+
+    let bytes = fs::read(path).map_err(|e| format!("failed to read session: {}", e))?;
+    let content = String::from_utf8_lossy(&bytes);
+
+    let session_id = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("session")
+        .to_string();
+    let session_key = session_keys
+        .get(&session_id)
+        .cloned()
+        .unwrap_or_else(|| session_id.clone());
+    let fallback_timestamp = file_modified_ms(path);
+
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| {
+            parse_session_line(line, &session_id, &session_key, i, fallback_timestamp)
+        })
+        .collect())
+}
+
+// ---------- Main entry point ----------
+
+/// Read every session transcript of the default agent.
+pub(crate) fn read_default_agent_sessions() -> Result<(Vec<HistoryEntry>, usize), String> {
+    let dir = sessions_dir()?;
+    let files = find_session_files(&dir);
+    if files.is_empty() {
+        return Err(format!("No OpenClaw sessions found in {}", dir.display()));
+    }
+
+    let session_keys = read_session_keys(&dir);
+    let mut entries = Vec::new();
+    for file in &files {
+        match parse_session_file(file, &session_keys) {
+            Ok(parsed) => entries.extend(parsed),
+            Err(e) => eprintln!("[session-import] {}", e),
+        }
+    }
+
+    eprintln!(
+        "[session-import] Parsed {} messages from {} session files",
+        entries.len(),
+        files.len()
+    );
+
+    Ok((entries, files.len()))
+}
+
+#[tauri::command]
+pub async fn import_openclaw_sessions(app: tauri::AppHandle) -> Result<SessionImportResult, String> {
+    // Transcripts run to tens of MB and the merge writes the database; keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        let (incoming, session_files) = read_default_agent_sessions()?;
+        let parsed = incoming.len();
+        let imported = app.state::<HistoryStore>().merge(incoming)?;
+
+        Ok(SessionImportResult {
+            imported,
+            parsed,
+            session_files,
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to import sessions: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Option<HistoryEntry> {
+        parse_session_line(line, "s1", "agent:main:main", 7, 42)
+    }
+
+    #[test]
+    fn transcript_lines_become_entries() {
+        let entry = parse(
+            r#"{"type":"message","id":"m1","timestamp":"2025-01-02T03:04:05+00:00","message":{"role":"user","content":"  hi  "}}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.id, "openclaw:s1:m1");
+        assert_eq!(entry.role, "user");
+        assert_eq!(entry.text, "hi");
+        assert_eq!(entry.timestamp, 1_735_787_045_000);
+        assert_eq!(entry.session.as_deref(), Some("agent:main:main"));
+
+        // The message's own timestamp (ms) wins over the wrapper's; no id falls back to the line
+        let entry = parse(
+            r#"{"type":"message","timestamp":"2025-01-02T03:04:05Z","message":{"role":"assistant","timestamp":1735787046123,"content":"ok"}}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.timestamp, 1_735_787_046_123);
+        assert_eq!(entry.id, "openclaw:s1:line7");
+
+        // Bare messages without a wrapper or timestamp use the file time
+        let entry = parse(r#"{"role":"assistant","content":"plain"}"#).unwrap();
+        assert_eq!(entry.timestamp, 42);
+    }
+
+    #[test]
+    fn other_roles_and_line_types_are_skipped() {
+        for line in [
+            r#"{"type":"session","id":"s1","timestamp":"2025-01-02T03:04:05Z"}"#,
+            r#"{"type":"message","message":{"role":"system","content":"rules"}}"#,
+            r#"{"type":"message","message":{"role":"toolResult","content":"output"}}"#,
+            r#"{"type":"message","message":{"role":"user","content":[{"type":"thinking","thinking":"hmm"}]}}"#,
+            r#"{"type":"message","message":{"role":"user"}}"#,
+            "not json",
+        ] {
+            assert!(parse(line).is_none(), "{line}");
+        }
+    }
+
+    #[test]
+    fn content_blocks_keep_text_and_images() {
+        let content: Value = serde_json::from_str(
+            r#"[
+                {"type":"text","text":" first "},
+                {"type":"thinking","thinking":"hidden"},
+                {"type":"text","text":"   "},
+                {"type":"text","text":"second"},
+                {"type":"image","data":"aGVsbG8=","mimeType":"image/png","fileName":"a.png"},
+                {"type":"image","source":{"type":"base64","media_type":"image/jpeg","data":"aGk="}},
+                {"type":"toolCall","name":"read"}
+            ]"#,
+        )
+        .unwrap();
+        let (text, attachments) = parse_content(&content);
+        assert_eq!(text, "first\n\nsecond");
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(attachments[0].mime_type, "image/png");
+        assert_eq!(attachments[0].file_name.as_deref(), Some("a.png"));
+        assert_eq!(attachments[0].data.as_deref(), Some(&b"hello"[..]));
+        assert_eq!(attachments[0].size_bytes, 5);
+        assert_eq!(attachments[1].mime_type, "image/jpeg");
+        assert_eq!(attachments[1].data.as_deref(), Some(&b"hi"[..]));
+
+        let (text, attachments) = parse_content(&Value::String(" just text ".into()));
+        assert_eq!(text, "just text");
+        assert!(attachments.is_empty());
+    }
+
+    #[test]
+    fn session_ids_map_to_gateway_keys() {
+        let dir = std::env::temp_dir().join(format!("clawpet-session-import-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("sessions.json"),
+            r#"{"agent:main:main":{"sessionId":"abc"},"agent:main:old":{"updatedAt":1}}"#,
+        )
+        .unwrap();
+        let line = r#"{"type":"message","id":"m1","message":{"role":"user","content":"hi"}}"#;
+        std::fs::write(dir.join("abc.jsonl"), line).unwrap();
+        std::fs::write(dir.join("def.jsonl"), format!("\n{line}\n")).unwrap();
+        std::fs::write(dir.join("ghi.jsonl.deleted.1700000000"), line).unwrap();
+
+        let keys = read_session_keys(&dir);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys.get("abc").map(String::as_str), Some("agent:main:main"));
+
+        let files = find_session_files(&dir);
+        assert_eq!(files, vec![dir.join("abc.jsonl"), dir.join("def.jsonl")]);
+        let mapped = parse_session_file(&files[0], &keys).unwrap();
+        assert_eq!(mapped[0].session.as_deref(), Some("agent:main:main"));
+        assert_eq!(mapped[0].id, "openclaw:abc:m1");
+        let unmapped = parse_session_file(&files[1], &keys).unwrap();
+        assert_eq!(unmapped.len(), 1);
+        assert_eq!(unmapped[0].session.as_deref(), Some("def"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
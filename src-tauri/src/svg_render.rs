@@ -0,0 +1,114 @@
+use resvg::{tiny_skia, usvg};
+use std::sync::{Arc, LazyLock};
+
+/// Longest side an SVG is rasterized to. Small icons are scaled up to the minimum so the
+/// model can read them; huge canvases are capped before the size pipeline takes over.
+const MIN_LONG_SIDE: f32 = 1024.0;
+const MAX_LONG_SIDE: f32 = 4096.0;
+
+static FONTS: LazyLock<Arc<usvg::fontdb::Database>> = LazyLock::new(|| {
+    let mut db = usvg::fontdb::Database::new();
+    db.load_system_fonts();
+    Arc::new(db)
+});
+
+fn options() -> usvg::Options<'static> {
+    usvg::Options {
+        fontdb: FONTS.clone(),
+        // Only inline data: images. A dropped or downloaded SVG must not be able to pull
+        // local files (`href="C:/Users/..."`) into the attachment.
+        image_href_resolver: usvg::ImageHrefResolver {
+            resolve_data: usvg::ImageHrefResolver::default_data_resolver(),
+            resolve_string: Box::new(|_: &str, _: &usvg::Options| None),
+        },
+        ..usvg::Options::default()
+    }
+}
+
+/// Render an SVG document to straight-alpha RGBA pixels.
+pub fn rasterize(bytes: &[u8]) -> Result<image::RgbaImage, String> {
+    let tree = usvg::Tree::from_data(bytes, &options())
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+
+    let size = tree.size();
+    let long_side = size.width().max(size.height());
+    if !long_side.is_finite() || long_side <= 0.0 {
+        return Err("Failed to decode image: SVG has no size".to_string());
+    }
+    let scale = long_side.clamp(MIN_LONG_SIDE, MAX_LONG_SIDE) / long_side;
+    let width = ((size.width() * scale).round() as u32).max(1);
+    let height = ((size.height() * scale).round() as u32).max(1);
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| "Failed to decode image: SVG is too large".to_string())?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    image::RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| "Failed to decode image: SVG render failed".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red_png() -> Vec<u8> {
+        let image = image::RgbaImage::from_pixel(8, 8, image::Rgba([255, 0, 0, 255]));
+        crate::image_pipeline::encode_png(&image::DynamicImage::ImageRgba8(image)).unwrap()
+    }
+
+    fn svg_with_image(href: &str) -> String {
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><image href="{}" width="10" height="10"/></svg>"#,
+            href
+        )
+    }
+
+    #[test]
+    fn long_side_is_clamped_keeping_the_view_box_ratio() {
+        let small = br#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 20 10"><rect width="20" height="10" fill="red"/></svg>"#;
+        let image = rasterize(small).unwrap();
+        assert_eq!(image.dimensions(), (1024, 512));
+        assert_eq!(image.get_pixel(512, 256).0, [255, 0, 0, 255]);
+
+        let huge = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10000" height="5000" viewBox="0 0 100 50"><rect width="100" height="50" fill="blue"/></svg>"#;
+        let image = rasterize(huge).unwrap();
+        assert_eq!(image.dimensions(), (4096, 2048));
+        assert_eq!(image.get_pixel(2048, 1024).0, [0, 0, 255, 255]);
+
+        assert!(rasterize(b"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"0\" height=\"0\"/>").is_err());
+    }
+
+    #[test]
+    fn only_inline_images_are_embedded() {
+        let dir = std::env::temp_dir().join(format!("clawpet-svg-href-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("secret.png");
+        std::fs::write(&file, red_png()).unwrap();
+
+        let external = rasterize(svg_with_image(&file.to_string_lossy()).as_bytes()).unwrap();
+        assert!(external.pixels().all(|pixel| pixel[3] == 0));
+
+        use base64::Engine;
+        let data_url = format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(red_png())
+        );
+        let inline = rasterize(svg_with_image(&data_url).as_bytes()).unwrap();
+        assert_eq!(inline.get_pixel(512, 512).0, [255, 0, 0, 255]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
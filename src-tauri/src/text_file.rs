@@ -0,0 +1,270 @@
+use crate::secret_scan::{self, SecretFinding};
+use encoding_rs::{Encoding, EUC_KR, WINDOWS_1252};
+use serde::Serialize;
+use std::path::Path;
+
+pub const MAX_TEXT_FILE_BYTES: u64 = 5 * 1024 * 1024; // 5MB
+/// Characters of file content sent to the agent; longer files are cut at a line boundary
+/// (inside the first line when that alone is too long, e.g. minified code).
+const MAX_TEXT_CHARS: usize = 60_000;
+/// Bytes inspected when deciding whether a file is binary.
+const SNIFF_BYTES: usize = 8192;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextFileResult {
+    pub file_name: String,
+    /// Fence language tag guessed from the file name ("text" when unknown).
+    pub language: &'static str,
+    pub encoding: &'static str,
+    /// File name header plus fenced code block, secrets masked, cut to the size budget.
+    pub text: String,
+    pub truncated: bool,
+    pub line_count: usize,
+    /// Credentials that were masked, with their line numbers.
+    pub secrets: Vec<SecretFinding>,
+}
+
+// ---------- Decoding ----------
+
+/// Decode text using the BOM, then UTF-8, then the legacy encodings users most likely
+/// have on disk (EUC-KR, Windows-1252). Binary files are rejected.
+pub fn decode(bytes: &[u8]) -> Result<(String, &'static str), String> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return Ok((text.into_owned(), encoding.name()));
+    }
+
+    let head = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    if head.contains(&0) {
+        return Err("Not a text file".to_string());
+    }
+
+    let (text, encoding) = match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), "UTF-8"),
+        Err(_) => match EUC_KR.decode_without_bom_handling_and_without_replacement(bytes) {
+            Some(text) => (text.into_owned(), EUC_KR.name()),
+            None => (
+                WINDOWS_1252.decode_without_bom_handling(bytes).0.into_owned(),
+                WINDOWS_1252.name(),
+            ),
+        },
+    };
+
+    // Control characters other than whitespace and ANSI escapes mean binary data
+    let sample: Vec<char> = text.chars().take(SNIFF_BYTES).collect();
+    let control = sample
+        .iter()
+        .filter(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c' | '\x1b'))
+        .count();
+    if control * 10 > sample.len() {
+        return Err("Not a text file".to_string());
+    }
+    Ok((text, encoding))
+}
+
+// ---------- Language ----------
+
+pub fn language_for(path: &Path) -> &'static str {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match name.as_str() {
+        "dockerfile" | "containerfile" => return "dockerfile",
+        "makefile" | "gnumakefile" => return "makefile",
+        "cmakelists.txt" => return "cmake",
+        ".gitignore" | ".dockerignore" | ".npmignore" => return "gitignore",
+        _ if name == ".env" || name.starts_with(".env.") => return "dotenv",
+        _ => {}
+    }
+
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "rs" => "rust",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "py" | "pyw" => "python",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "lua" => "lua",
+        "dart" => "dart",
+        "scala" => "scala",
+        "sh" | "bash" | "zsh" => "bash",
+        "ps1" | "psm1" => "powershell",
+        "bat" | "cmd" => "batch",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "json" | "jsonc" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "xml" | "plist" => "xml",
+        "ini" | "cfg" | "conf" => "ini",
+        "env" => "dotenv",
+        "md" | "markdown" => "markdown",
+        "diff" | "patch" => "diff",
+        "log" => "log",
+        _ => "text",
+    }
+}
+
+// ---------- Formatting ----------
+
+/// Longest run of backticks in the content plus one, so the fence can't be closed early.
+fn fence_for(content: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in content.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    "`".repeat((longest + 1).max(3))
+}
+
+/// `--- <label> ---` header followed by the content in a code fence.
+pub fn fenced_block(label: &str, language: &str, content: &str) -> String {
+    let body = content.trim_end_matches(['\r', '\n']);
+    let fence = fence_for(body);
+    format!("--- {} ---\n{}{}\n{}\n{}", label, fence, language, body, fence)
+}
+
+/// Whole lines up to `MAX_TEXT_CHARS`, and how many lines were kept. When the first line
+/// alone is over budget, its first `MAX_TEXT_CHARS` characters and 0 lines.
+fn cut_to_budget(content: &str) -> (&str, usize) {
+    let mut end = 0;
+    let mut chars = 0;
+    let mut lines = 0;
+    for line in content.split_inclusive('\n') {
+        chars += line.chars().count();
+        if chars > MAX_TEXT_CHARS {
+            break;
+        }
+        end += line.len();
+        lines += 1;
+    }
+    if lines == 0 {
+        end = content
+            .char_indices()
+            .nth(MAX_TEXT_CHARS)
+            .map_or(content.len(), |(i, _)| i);
+    }
+    (&content[..end], lines)
+}
+
+// ---------- Entry point ----------
+
+pub fn read(path: &Path, bytes: &[u8]) -> Result<TextFileResult, String> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".to_string());
+    let (decoded, encoding) = decode(bytes)?;
+    let language = language_for(path);
+
+    // Scan the whole file, so secrets past the cut are still reported
+    let (masked, secrets) = secret_scan::mask_secrets(&decoded);
+    let line_count = masked.lines().count();
+    let (shown, shown_lines) = cut_to_budget(&masked);
+    let truncated = shown.len() < masked.len();
+
+    let label = format!("File: {} ({} lines)", file_name, line_count);
+    let mut text = fenced_block(&label, language, shown);
+    if truncated && shown_lines == 0 {
+        text.push_str(&format!(
+            "\n[Truncated: first {} characters of line 1 shown]",
+            MAX_TEXT_CHARS
+        ));
+    } else if truncated {
+        text.push_str(&format!(
+            "\n[Truncated: first {} of {} lines shown]",
+            shown_lines, line_count
+        ));
+    }
+
+    Ok(TextFileResult {
+        file_name,
+        language,
+        encoding,
+        text,
+        truncated,
+        line_count,
+        secrets,
+    })
+}
+
+#[tauri::command]
+pub async fn read_text_file(path: String) -> Result<TextFileResult, String> {
+    let canonical = std::fs::canonicalize(&path)
+        .map_err(|e| format!("Failed to read file: {e}"))?;
+    let size = std::fs::metadata(&canonical)
+        .map_err(|e| format!("Failed to read file: {e}"))?
+        .len();
+    if size > MAX_TEXT_FILE_BYTES {
+        return Err(format!(
+            "Text file exceeds {}MB limit",
+            MAX_TEXT_FILE_BYTES / (1024 * 1024)
+        ));
+    }
+    let bytes = std::fs::read(&canonical).map_err(|e| format!("Failed to read file: {e}"))?;
+    let result = read(&canonical, &bytes)?;
+    if !result.secrets.is_empty() {
+        eprintln!(
+            "[text] Masked {} possible secret(s) in {}",
+            result.secrets.len(),
+            result.file_name
+        );
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_files_are_cut_at_a_line_boundary() {
+        let line = format!("{}\n", "a".repeat(999));
+        let content = line.repeat(100);
+        let (shown, lines) = cut_to_budget(&content);
+        assert_eq!((shown.len(), lines), (60 * 1000, 60));
+
+        let result = read(Path::new("notes.txt"), content.as_bytes()).unwrap();
+        assert!(result.truncated);
+        assert_eq!(result.line_count, 100);
+        assert!(result.text.ends_with("[Truncated: first 60 of 100 lines shown]"));
+
+        let short = read(Path::new("notes.txt"), b"one\ntwo\n").unwrap();
+        assert!(!short.truncated);
+        assert_eq!(short.text, "--- File: notes.txt (2 lines) ---\n```text\none\ntwo\n```");
+    }
+
+    #[test]
+    fn an_overlong_first_line_is_cut_inside_the_line() {
+        // Multi-byte characters, so a byte-based cut would split one
+        let content = format!("{}\nnext", "가".repeat(MAX_TEXT_CHARS + 10));
+        let (shown, lines) = cut_to_budget(&content);
+        assert_eq!((shown.chars().count(), lines), (MAX_TEXT_CHARS, 0));
+
+        let result = read(Path::new("bundle.min.js"), content.as_bytes()).unwrap();
+        assert!(result.truncated);
+        assert!(result.text.contains(&"가".repeat(100)));
+        assert!(result.text.ends_with("[Truncated: first 60000 characters of line 1 shown]"));
+    }
+}
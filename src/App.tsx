@@ -11,6 +11,7 @@ import { DisconnectedOverlay } from "./components/DisconnectedOverlay";
 import { useGateway } from "./hooks/useGateway";
 import { useDrop } from "./hooks/useDrop";
 import { useStore } from "./store/useStore";
+import { migrateLocalChatHistory } from "./utils/localChatHistory";
 
 const windowLabel = getCurrentWindow().label;
 const isParchmentWindow = windowLabel === "parchment";
@@ -34,6 +35,12 @@ function MainWindow() {
   useGateway();
   const { isDragOver } = useDrop();
 
+  useEffect(() => {
+    migrateLocalChatHistory().catch((err) => {
+      console.warn("[history] migration failed", err);
+    });
+  }, []);
+
   // Prevent right-click context menu
   useEffect(() => {
     const handler = (e: MouseEvent) => e.preventDefault();
@@ -49,12 +56,13 @@ function MainWindow() {
       const unlistenCapture = await listen<{
         base64?: string;
         mimeType?: string;
+        attachmentId?: string;
       }>("clawpet://capture-complete", (event) => {
         const base64 = event.payload?.base64 ?? "";
         const mimeType = event.payload?.mimeType ?? "image/png";
         if (!base64) return;
         const dataUrl = `data:${mimeType};base64,${base64}`;
-        setAttachedImage({ dataUrl, mimeType });
+        setAttachedImage({ dataUrl, mimeType, attachmentId: event.payload?.attachmentId });
         showChatInput();
       });
 
@@ -6,10 +6,28 @@ import "./CaptureOverlay.css";
 
 type Point = { x: number; y: number };
 type Rect = { x: number; y: number; width: number; height: number };
-type CaptureResult = { base64: string; mime_type: string };
-type CaptureRegion = { x: number; y: number; width: number; height: number };
+type CaptureResult = { base64: string; mime_type: string; attachment_id?: string };
+type CaptureRegion = { x: number; y: number; width: number; height: number; scaleFactor: number };
+type MaskMode = "solid" | "pixelate" | "blur";
+type Tool = "capture" | "mask" | "arrow" | "box";
+type Label = { kind: "label"; x: number; y: number; text: string };
+/** Masks and annotations drawn on the overlay before the capture area is selected. */
+type Edit =
+  | (Rect & { kind: "mask"; mode: MaskMode })
+  | (Rect & { kind: "box" })
+  | { kind: "arrow"; from: Point; to: Point }
+  | { kind: "marker"; x: number; y: number }
+  | Label;
+/** Masks and annotations in pixels of the captured image, as `CaptureEdits` expects. */
+type CaptureEdits = {
+  redactions: Array<Rect & { mode: MaskMode }>;
+  annotations: Array<Exclude<Edit, { kind: "mask" }>>;
+};
 
 const MIN_CAPTURE_SIZE = 4;
+// Longer labels are cut off by the backend anyway
+const MAX_LABEL_CHARS = 60;
+const MASK_MODES: MaskMode[] = ["solid", "pixelate", "blur"];
 
 function toRect(start: Point, end: Point): Rect {
   const x = Math.min(start.x, end.x);
@@ -27,11 +45,34 @@ export function CaptureOverlay() {
   const currentRef = useRef<Point | null>(null);
   const busyRef = useRef(false);
   const pointerIdRef = useRef<number | null>(null);
+  // Shift+drag draws a mask, Alt+drag an arrow (Alt+click a numbered marker) and Alt+Shift+drag
+  // a box (Alt+Shift+click a text label) instead of capturing
+  const [tool, setTool] = useState<Tool>("capture");
+  const toolRef = useRef<Tool>("capture");
+  const [edits, setEdits] = useState<Edit[]>([]);
+  const editsRef = useRef<Edit[]>([]);
+  const [maskMode, setMaskMode] = useState<MaskMode>("solid");
+  const maskModeRef = useRef<MaskMode>("solid");
+  // Text label being typed; it joins the edits on Enter or when focus leaves it
+  const [labelDraft, setLabelDraft] = useState<Label | null>(null);
+  const labelDraftRef = useRef<Label | null>(null);
 
   useEffect(() => {
     busyRef.current = busy;
   }, [busy]);
 
+  useEffect(() => {
+    labelDraftRef.current = labelDraft;
+  }, [labelDraft]);
+
+  useEffect(() => {
+    editsRef.current = edits;
+  }, [edits]);
+
+  useEffect(() => {
+    maskModeRef.current = maskMode;
+  }, [maskMode]);
+
   const selectionRect = useMemo(() => {
     if (!startPoint || !currentPoint) return null;
     return toRect(startPoint, currentPoint);
@@ -49,8 +90,14 @@ export function CaptureOverlay() {
 
   useEffect(() => {
     const onKeyDown = (event: KeyboardEvent) => {
+      // Keys belong to the label input while one is open
+      if (labelDraftRef.current) return;
       if (event.key === "Escape") {
         void closeWindow();
+      } else if (event.key === "m" || event.key === "M") {
+        setMaskMode((mode) => MASK_MODES[(MASK_MODES.indexOf(mode) + 1) % MASK_MODES.length]);
+      } else if (event.key === "Backspace") {
+        setEdits((current) => current.slice(0, -1));
       }
     };
     const onContextMenu = (event: MouseEvent) => event.preventDefault();
@@ -67,6 +114,7 @@ export function CaptureOverlay() {
     const payload = {
       base64: result.base64,
       mimeType: result.mime_type,
+      attachmentId: result.attachment_id,
     };
     await emitTo("main", "clawpet://capture-complete", payload);
   }, []);
@@ -86,14 +134,15 @@ export function CaptureOverlay() {
         y: Math.round(winPos.y + rect.y * scale),
         width: Math.max(1, Math.round(rect.width * scale)),
         height: Math.max(1, Math.round(rect.height * scale)),
+        scaleFactor: scale,
       };
     },
     [],
   );
 
   const finishCapture = useCallback(
-    async (region: CaptureRegion) => {
-      const result = await invoke<CaptureResult>("capture_screen_region", { region });
+    async (region: CaptureRegion, edits: CaptureEdits) => {
+      const result = await invoke<CaptureResult>("capture_screen_region", { region, edits });
       await emitCaptureComplete(result);
     },
     [emitCaptureComplete],
@@ -103,8 +152,31 @@ export function CaptureOverlay() {
     startRef.current = null;
     currentRef.current = null;
     pointerIdRef.current = null;
+    toolRef.current = "capture";
     setStartPoint(null);
     setCurrentPoint(null);
+    setTool("capture");
+  }, []);
+
+  const startLabel = useCallback((point: Point) => {
+    const draft: Label = { kind: "label", ...point, text: "" };
+    labelDraftRef.current = draft;
+    setLabelDraft(draft);
+  }, []);
+
+  const commitLabel = useCallback(() => {
+    const draft = labelDraftRef.current;
+    labelDraftRef.current = null;
+    setLabelDraft(null);
+    const text = draft?.text.trim();
+    if (draft && text) {
+      setEdits((list) => [...list, { ...draft, text }]);
+    }
+  }, []);
+
+  const cancelLabel = useCallback(() => {
+    labelDraftRef.current = null;
+    setLabelDraft(null);
   }, []);
 
   const updateCurrentPoint = useCallback((point: Point) => {
@@ -118,8 +190,32 @@ export function CaptureOverlay() {
     if (!start || !current || busyRef.current) return;
 
     const rect = toRect(start, current);
+    const activeTool = toolRef.current;
     clearDrag();
 
+    if (activeTool === "mask") {
+      if (rect.width >= MIN_CAPTURE_SIZE && rect.height >= MIN_CAPTURE_SIZE) {
+        setEdits((list) => [...list, { kind: "mask", ...rect, mode: maskModeRef.current }]);
+      }
+      return;
+    }
+    if (activeTool === "arrow") {
+      const edit: Edit =
+        rect.width < MIN_CAPTURE_SIZE && rect.height < MIN_CAPTURE_SIZE
+          ? { kind: "marker", x: start.x, y: start.y }
+          : { kind: "arrow", from: start, to: current };
+      setEdits((list) => [...list, edit]);
+      return;
+    }
+    if (activeTool === "box") {
+      if (rect.width < MIN_CAPTURE_SIZE && rect.height < MIN_CAPTURE_SIZE) {
+        startLabel(start);
+      } else {
+        setEdits((list) => [...list, { kind: "box", ...rect }]);
+      }
+      return;
+    }
+
     if (rect.width < MIN_CAPTURE_SIZE || rect.height < MIN_CAPTURE_SIZE) {
       await closeWindow();
       return;
@@ -129,20 +225,57 @@ export function CaptureOverlay() {
     setBusy(true);
     try {
       const region = await resolveRegion(rect);
+      // Relative to the selection, in captured pixels; masks round outwards so no edge leaks
+      const scale = region.scaleFactor;
+      const toCapture = (point: Point) => ({ x: (point.x - rect.x) * scale, y: (point.y - rect.y) * scale });
+      const captureEdits: CaptureEdits = { redactions: [], annotations: [] };
+      for (const edit of editsRef.current) {
+        if (edit.kind === "mask") {
+          captureEdits.redactions.push({
+            x: Math.floor((edit.x - rect.x) * scale),
+            y: Math.floor((edit.y - rect.y) * scale),
+            width: Math.ceil(edit.width * scale) + 1,
+            height: Math.ceil(edit.height * scale) + 1,
+            mode: edit.mode,
+          });
+        } else if (edit.kind === "arrow") {
+          captureEdits.annotations.push({ kind: "arrow", from: toCapture(edit.from), to: toCapture(edit.to) });
+        } else if (edit.kind === "box") {
+          captureEdits.annotations.push({
+            kind: "box",
+            ...toCapture(edit),
+            width: edit.width * scale,
+            height: edit.height * scale,
+          });
+        } else if (edit.kind === "label") {
+          captureEdits.annotations.push({ kind: "label", ...toCapture(edit), text: edit.text });
+        } else {
+          captureEdits.annotations.push({ kind: "marker", ...toCapture(edit) });
+        }
+      }
       // Hide first so the overlay disappears immediately while capture/encode runs.
       await hideWindow();
-      await finishCapture(region);
+      await finishCapture(region, captureEdits);
     } catch (err) {
       await emitCaptureError(String(err));
     } finally {
       await closeWindow();
     }
-  }, [clearDrag, closeWindow, emitCaptureError, finishCapture, hideWindow, resolveRegion]);
+  }, [clearDrag, closeWindow, emitCaptureError, finishCapture, hideWindow, resolveRegion, startLabel]);
 
   const handlePointerDown = useCallback(
     (event: React.PointerEvent<HTMLDivElement>) => {
       if (event.button !== 0 || busyRef.current) return;
+      // Clicking away from an open label only finishes it
+      if (labelDraftRef.current) {
+        commitLabel();
+        return;
+      }
       pointerIdRef.current = event.pointerId;
+      const nextTool: Tool =
+        event.altKey && event.shiftKey ? "box" : event.shiftKey ? "mask" : event.altKey ? "arrow" : "capture";
+      toolRef.current = nextTool;
+      setTool(nextTool);
       event.currentTarget.setPointerCapture(event.pointerId);
       const next = { x: event.clientX, y: event.clientY };
       startRef.current = next;
@@ -150,7 +283,7 @@ export function CaptureOverlay() {
       setStartPoint(next);
       setCurrentPoint(next);
     },
-    [],
+    [commitLabel],
   );
 
   const handlePointerMove = useCallback((event: React.PointerEvent<HTMLDivElement>) => {
@@ -213,10 +346,104 @@ export function CaptureOverlay() {
       onPointerUp={handlePointerUp}
       onPointerCancel={handlePointerCancel}
     >
-      <div className="capture-hint">Drag to capture area</div>
-      {selectionRect && (
+      <div className="capture-hint">
+        Drag to capture area · Shift+drag to mask ({maskMode}, M to change) · Alt+drag arrow, Alt+click marker ·
+        Alt+Shift+drag box, Alt+Shift+click label
+        {edits.length > 0 && " · Backspace undoes"}
+      </div>
+      {edits.map((edit, index) =>
+        edit.kind === "mask" ? (
+          <div
+            key={index}
+            className={`capture-mask capture-mask-${edit.mode}`}
+            style={{
+              left: `${edit.x}px`,
+              top: `${edit.y}px`,
+              width: `${edit.width}px`,
+              height: `${edit.height}px`,
+            }}
+          />
+        ) : edit.kind === "label" ? (
+          <div key={index} className="capture-label" style={{ left: `${edit.x}px`, top: `${edit.y}px` }}>
+            {edit.text}
+          </div>
+        ) : null,
+      )}
+      {labelDraft && (
+        <input
+          className="capture-label capture-label-input"
+          style={{ left: `${labelDraft.x}px`, top: `${labelDraft.y}px` }}
+          value={labelDraft.text}
+          maxLength={MAX_LABEL_CHARS}
+          size={Math.max(8, labelDraft.text.length + 1)}
+          placeholder="Label"
+          autoFocus
+          onChange={(event) => {
+            const text = event.target.value;
+            setLabelDraft((draft) => draft && { ...draft, text });
+          }}
+          onKeyDown={(event) => {
+            if (event.key === "Enter") {
+              commitLabel();
+            } else if (event.key === "Escape") {
+              cancelLabel();
+            }
+          }}
+          onBlur={commitLabel}
+          onPointerDown={(event) => event.stopPropagation()}
+        />
+      )}
+      <svg className="capture-annotations">
+        <defs>
+          <marker id="capture-arrow-head" markerWidth="8" markerHeight="8" refX="6" refY="4" orient="auto">
+            <path d="M0,0 L8,4 L0,8" fill="none" stroke="#ff3b30" strokeWidth="1.5" />
+          </marker>
+        </defs>
+        {edits.map((edit, index) => {
+          if (edit.kind === "arrow") {
+            return (
+              <line
+                key={index}
+                x1={edit.from.x}
+                y1={edit.from.y}
+                x2={edit.to.x}
+                y2={edit.to.y}
+                markerEnd="url(#capture-arrow-head)"
+              />
+            );
+          }
+          if (edit.kind === "box") {
+            return <rect key={index} x={edit.x} y={edit.y} width={edit.width} height={edit.height} />;
+          }
+          if (edit.kind === "marker") {
+            const number = edits.slice(0, index + 1).filter((e) => e.kind === "marker").length;
+            return (
+              <g key={index}>
+                <circle cx={edit.x} cy={edit.y} r={12} />
+                <text x={edit.x} y={edit.y}>
+                  {number}
+                </text>
+              </g>
+            );
+          }
+          return null;
+        })}
+        {tool === "arrow" && startPoint && currentPoint && (
+          <line
+            x1={startPoint.x}
+            y1={startPoint.y}
+            x2={currentPoint.x}
+            y2={currentPoint.y}
+            markerEnd="url(#capture-arrow-head)"
+          />
+        )}
+        {tool === "box" && selectionRect && (
+          <rect x={selectionRect.x} y={selectionRect.y} width={selectionRect.width} height={selectionRect.height} />
+        )}
+      </svg>
+      {selectionRect && (tool === "capture" || tool === "mask") && (
         <div
-          className="capture-selection"
+          className={`capture-selection${tool === "mask" ? " masking" : ""}`}
           style={{
             left: `${selectionRect.x}px`,
             top: `${selectionRect.y}px`,
@@ -1,6 +1,7 @@
 import { useCallback, useEffect, useRef, useState, type CSSProperties } from "react";
 import { WebviewWindow } from "@tauri-apps/api/webviewWindow";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import { useStore } from "../store/useStore";
 import { useAnimation } from "../hooks/useAnimation";
@@ -17,13 +18,32 @@ const SPRITE_MAP = {
 const ENABLE_AREA_CAPTURE = import.meta.env.VITE_ENABLE_AREA_CAPTURE !== "false";
 const DEFAULT_NAME = "OpenClaw";
 
-type ActionId = "capture-area" | "capture-display" | "read-browser" | "history" | "clip-to-obsidian";
+type ActionId = "capture-area" | "capture-window" | "capture-display" | "capture-all" | "read-browser" | "history" | "clip-to-obsidian";
 type OpenClawIdentity = { name?: string | null };
-type CaptureResult = { base64: string; mime_type: string };
+type CaptureResult = { base64: string; mime_type: string; attachment_id?: string };
+type HotkeyAction = "capture-area" | "capture-display" | "read-browser" | "clip-to-obsidian" | "toggle-pet";
+type HotkeyStatus = { action: HotkeyAction; label: string; binding?: string | null; registered: boolean; error?: string };
+
+const MODIFIER_KEYS = ["Control", "Alt", "Shift", "Meta", "AltGraph", "OS"];
+
+/** Global shortcut string ("Ctrl+Shift+K") for a key press, or null for a bare modifier. */
+function shortcutFromEvent(event: KeyboardEvent): string | null {
+  if (MODIFIER_KEYS.includes(event.key)) return null;
+  const mods = [
+    event.ctrlKey && "Ctrl",
+    event.altKey && "Alt",
+    event.shiftKey && "Shift",
+    event.metaKey && "Super",
+  ].filter(Boolean);
+  const key = event.code.replace(/^Key/, "").replace(/^Digit/, "");
+  return [...mods, key].join("+");
+}
 
 const MENU_ACTIONS: Array<{ id: ActionId; label: string; shortLabel: string }> = [
   { id: "capture-area", label: "Area capture", shortLabel: "영역캡처" },
+  { id: "capture-window", label: "Active window capture", shortLabel: "창캡처" },
   { id: "capture-display", label: "Full screen capture", shortLabel: "전체캡처" },
+  { id: "capture-all", label: "All monitors capture", shortLabel: "모든화면" },
   { id: "read-browser", label: "Read browser page", shortLabel: "페이지읽기" },
   { id: "clip-to-obsidian", label: "Save to Obsidian", shortLabel: "옵시디언저장" },
   { id: "history", label: "Conversation history", shortLabel: "대화기록" },
@@ -47,6 +67,8 @@ export function Character() {
   const wrapperRef = useRef<HTMLDivElement>(null);
   const [menuOpen, setMenuOpen] = useState(false);
   const [characterName, setCharacterName] = useState(DEFAULT_NAME);
+  const [hotkeys, setHotkeys] = useState<HotkeyStatus[]>([]);
+  const [rebinding, setRebinding] = useState<HotkeyStatus | null>(null);
 
   const openCaptureWindow = useCallback(async () => {
     const existingOther = await WebviewWindow.getByLabel("capture-display");
@@ -153,18 +175,43 @@ export function Character() {
         void openCaptureWindow();
         return;
       }
+      if (action === "capture-window" || action === "capture-all") {
+        try {
+          const result = await invoke<CaptureResult>(
+            action === "capture-window" ? "capture_active_window" : "capture_all_displays",
+          );
+          setAttachedImage({
+            dataUrl: `data:${result.mime_type};base64,${result.base64}`,
+            mimeType: result.mime_type,
+            attachmentId: result.attachment_id,
+          });
+          showChatInput();
+        } catch (err) {
+          showSpeechBubble(String(err));
+        }
+        return;
+      }
       if (action === "capture-display") {
         try {
           const win = getCurrentWindow();
-          const [pos, size] = await Promise.all([win.outerPosition(), win.outerSize()]);
+          const [pos, size, scaleFactor] = await Promise.all([
+            win.outerPosition(),
+            win.outerSize(),
+            win.scaleFactor(),
+          ]);
           const centerX = Math.round(pos.x + size.width / 2);
           const centerY = Math.round(pos.y + size.height / 2);
           const result = await invoke<CaptureResult>("capture_screen_for_point", {
             x: centerX,
             y: centerY,
+            scaleFactor,
           });
           const dataUrl = `data:${result.mime_type};base64,${result.base64}`;
-          setAttachedImage({ dataUrl, mimeType: result.mime_type });
+          setAttachedImage({
+            dataUrl,
+            mimeType: result.mime_type,
+            attachmentId: result.attachment_id,
+          });
           showChatInput();
         } catch (err) {
           showSpeechBubble(String(err));
@@ -183,12 +230,14 @@ export function Character() {
             screenshot: string;
             url: string;
             title: string;
+            screenshot_attachment_id?: string;
           }>("read_browser_page", { petX, petY });
           setBrowserContext(result);
           if (result.screenshot) {
             setAttachedImage({
               dataUrl: `data:image/jpeg;base64,${result.screenshot}`,
               mimeType: "image/jpeg",
+              attachmentId: result.screenshot_attachment_id,
             });
           }
           hideSpeechBubble();
@@ -227,6 +276,118 @@ export function Character() {
     [openCaptureWindow, openHistoryWindow, setAttachedImage, setBrowserContext, hideSpeechBubble, showChatInput, showSpeechBubble],
   );
 
+  const handleHotkey = useCallback(
+    async (action: HotkeyAction) => {
+      if (action === "capture-display") {
+        // Hotkeys capture the screen being worked on, not the one the pet sits on
+        try {
+          const result = await invoke<CaptureResult>("capture_screen_under_cursor");
+          setAttachedImage({
+            dataUrl: `data:${result.mime_type};base64,${result.base64}`,
+            mimeType: result.mime_type,
+            attachmentId: result.attachment_id,
+          });
+          showChatInput();
+        } catch (err) {
+          showSpeechBubble(String(err));
+        }
+        return;
+      }
+      if (action === "capture-area" || action === "read-browser" || action === "clip-to-obsidian") {
+        void handleActionClick(action);
+      }
+    },
+    [handleActionClick, setAttachedImage, showChatInput, showSpeechBubble],
+  );
+
+  useEffect(() => {
+    let active = true;
+    let unlisten: (() => void) | undefined;
+
+    (async () => {
+      const stop = await listen<{ action: HotkeyAction }>("clawpet://hotkey", (event) => {
+        void handleHotkey(event.payload.action);
+      });
+      if (!active) {
+        stop();
+        return;
+      }
+      unlisten = stop;
+    })();
+
+    return () => {
+      active = false;
+      unlisten?.();
+    };
+  }, [handleHotkey]);
+
+  useEffect(() => {
+    (async () => {
+      try {
+        const statuses = await invoke<HotkeyStatus[]>("list_hotkeys");
+        setHotkeys(statuses);
+        const failed = statuses.filter((hotkey) => hotkey.error);
+        if (failed.length > 0) {
+          showSpeechBubble(failed.map((hotkey) => `${hotkey.label}: ${hotkey.error}`).join("\n"));
+        }
+      } catch (err) {
+        console.warn("[character] failed to read hotkeys:", err);
+      }
+    })();
+  }, [showSpeechBubble]);
+
+  const startRebinding = useCallback(
+    (event: React.MouseEvent, action: ActionId) => {
+      const hotkey = hotkeys.find((status) => status.action === action);
+      if (!hotkey) return;
+      event.preventDefault();
+      setMenuOpen(false);
+      setRebinding(hotkey);
+      showSpeechBubble(
+        `Press new keys for ${hotkey.label} (now ${hotkey.binding ?? "off"})\nEsc cancels, Backspace turns it off, Delete restores the default`,
+      );
+    },
+    [hotkeys, showSpeechBubble],
+  );
+
+  useEffect(() => {
+    if (!rebinding) return;
+
+    const save = async (binding: string | null) => {
+      setRebinding(null);
+      try {
+        const updated = await invoke<HotkeyStatus[]>("set_hotkey", { action: rebinding.action, binding });
+        setHotkeys(updated);
+        const status = updated.find((hotkey) => hotkey.action === rebinding.action);
+        showSpeechBubble(
+          status?.error
+            ? `${rebinding.label}: ${status.error}`
+            : `${rebinding.label}: ${status?.binding ?? "off"}`,
+        );
+      } catch (err) {
+        showSpeechBubble(`${rebinding.label}: ${String(err)}`);
+      }
+    };
+
+    const onKeyDown = (event: KeyboardEvent) => {
+      event.preventDefault();
+      if (event.key === "Escape") {
+        setRebinding(null);
+        hideSpeechBubble();
+      } else if (event.key === "Backspace") {
+        void save("off");
+      } else if (event.key === "Delete") {
+        void save(null);
+      } else {
+        const binding = shortcutFromEvent(event);
+        if (binding) void save(binding);
+      }
+    };
+
+    window.addEventListener("keydown", onKeyDown);
+    return () => window.removeEventListener("keydown", onKeyDown);
+  }, [rebinding, hideSpeechBubble, showSpeechBubble]);
+
   useEffect(() => {
     let cancelled = false;
 
@@ -281,6 +442,10 @@ export function Character() {
         <div className={`character-action-row${menuOpen ? " open" : ""}`} aria-hidden={!menuOpen}>
           {MENU_ACTIONS.map((action, index) => {
             const style = { "--index": index } as CSSProperties;
+            const hotkey = hotkeys.find((status) => status.action === action.id);
+            const title = hotkey
+              ? `${action.label} (${hotkey.binding ?? "no hotkey"}; right-click to change)`
+              : action.label;
             return (
               <button
                 key={action.id}
@@ -289,7 +454,8 @@ export function Character() {
                 onClick={() => {
                   void handleActionClick(action.id);
                 }}
-                title={action.label}
+                onContextMenu={(event) => startRebinding(event, action.id)}
+                title={title}
               >
                 {action.shortLabel}
               </button>
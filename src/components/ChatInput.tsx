@@ -2,7 +2,7 @@ import { useCallback, useEffect, useRef } from "react";
 import { useStore } from "../store/useStore";
 import { GatewayClient } from "../gateway/GatewayClient";
 import { generateUUID } from "../utils/uuid";
-import { appendLocalChatHistory } from "../utils/localChatHistory";
+import { appendLocalChatHistory, type NewLocalChatAttachment } from "../utils/localChatHistory";
 import "./ChatInput.css";
 
 // We need access to the gateway client ref from useGateway.
@@ -13,6 +13,12 @@ export function setGatewayClientRef(client: GatewayClient | null) {
   _gatewayClient = client;
 }
 
+/** Code fence longer than any backtick run in the text, so documents can't close it early. */
+function fenceFor(text: string): string {
+  const longest = Math.max(2, ...(text.match(/`+/g) ?? []).map((run) => run.length));
+  return "`".repeat(longest + 1);
+}
+
 export function ChatInput() {
   const visible = useStore((s) => s.chatInputVisible);
   const hideChatInput = useStore((s) => s.hideChatInput);
@@ -27,6 +33,12 @@ export function ChatInput() {
   const sessionKey = useStore((s) => s.sessionKey);
   const attachedImage = useStore((s) => s.attachedImage);
   const clearAttachedImage = useStore((s) => s.clearAttachedImage);
+  const attachedDocument = useStore((s) => s.attachedDocument);
+  const clearAttachedDocument = useStore((s) => s.clearAttachedDocument);
+  const attachedFolder = useStore((s) => s.attachedFolder);
+  const removeAttachedFolderFile = useStore((s) => s.removeAttachedFolderFile);
+  const removeAttachedFolderGit = useStore((s) => s.removeAttachedFolderGit);
+  const clearAttachedFolder = useStore((s) => s.clearAttachedFolder);
   const browserContext = useStore((s) => s.browserContext);
   const clearBrowserContext = useStore((s) => s.clearBrowserContext);
   const inputRef = useRef<HTMLTextAreaElement>(null);
@@ -63,7 +75,9 @@ export function ChatInput() {
       const trimmed = message.trim();
       const hasImage = !!attachedImage;
       const hasBrowser = !!browserContext;
-      if (!trimmed && !hasImage && !hasBrowser) return;
+      const hasDocument = !!attachedDocument;
+      const hasFolder = !!attachedFolder;
+      if (!trimmed && !hasImage && !hasBrowser && !hasDocument && !hasFolder) return;
       if (chatLoading) return;
 
       hideChatInput();
@@ -97,12 +111,77 @@ export function ChatInput() {
         parts.push("");
         parts.push(trimmed || "Describe this page");
         messageText = parts.join("\n");
+      } else if (hasFolder) {
+        const parts: string[] = [];
+        parts.push(`[Folder: ${attachedFolder.folderName}]`);
+        parts.push("");
+        parts.push(attachedFolder.overview);
+        if (attachedFolder.git) {
+          parts.push("");
+          parts.push(attachedFolder.git.text);
+        }
+        for (const file of attachedFolder.files) {
+          parts.push("");
+          parts.push(file.text);
+        }
+        parts.push("");
+        const hasChanges = !!attachedFolder.git && attachedFolder.git.changedFiles > 0;
+        parts.push(trimmed || (hasChanges ? "Review my changes" : "Give me an overview of this project"));
+        messageText = parts.join("\n");
+      } else if (hasDocument && attachedDocument.kind === "code") {
+        // read_text_file already returns the file as a named, fenced block
+        const parts: string[] = [];
+        parts.push(`[File: ${attachedDocument.fileName}]`);
+        parts.push("");
+        parts.push(attachedDocument.text);
+        parts.push("");
+        parts.push(trimmed || "Take a look at this file");
+        messageText = parts.join("\n");
+      } else if (hasDocument) {
+        const pages = attachedDocument.pageCount ? ` (${attachedDocument.pageCount} pages)` : "";
+        const fence = fenceFor(attachedDocument.text);
+        const parts: string[] = [];
+        parts.push(`[Document: ${attachedDocument.fileName}${pages}]`);
+        parts.push("");
+        parts.push(attachedDocument.kind === "csv" ? `${fence}markdown` : fence);
+        parts.push(attachedDocument.text);
+        parts.push(fence);
+        parts.push("");
+        parts.push(trimmed || "Summarize this document");
+        messageText = parts.join("\n");
       } else {
         messageText = trimmed || (hasImage ? "What's in this image?" : "");
       }
 
-      const outgoingText = trimmed || (hasBrowser ? "[Browser page]" : hasImage ? "[Image attachment]" : "");
-      appendLocalChatHistory("user", outgoingText);
+      const outgoingText =
+        trimmed ||
+        (hasBrowser
+          ? "[Browser page]"
+          : hasFolder
+            ? `[Folder: ${attachedFolder.folderName}]`
+            : hasDocument
+              ? `[${attachedDocument.kind === "code" ? "File" : "Document"}: ${attachedDocument.fileName}]`
+              : hasImage
+                ? "[Image attachment]"
+                : "");
+      const historyAttachments: NewLocalChatAttachment[] = [];
+      if (hasImage) {
+        if (attachedImage.attachmentId) {
+          historyAttachments.push({
+            mimeType: attachedImage.mimeType,
+            hash: attachedImage.attachmentId,
+          });
+        } else {
+          const historyImage = attachedImage.dataUrl.split(",")[1] ?? "";
+          if (historyImage) {
+            historyAttachments.push({ mimeType: attachedImage.mimeType, base64: historyImage });
+          }
+        }
+      }
+      appendLocalChatHistory("user", outgoingText, {
+        session: sessionKey,
+        attachments: historyAttachments,
+      });
 
       const params: Record<string, unknown> = {
         sessionKey,
@@ -123,6 +202,12 @@ export function ChatInput() {
       if (hasBrowser) {
         clearBrowserContext();
       }
+      if (hasDocument) {
+        clearAttachedDocument();
+      }
+      if (hasFolder) {
+        clearAttachedFolder();
+      }
 
       try {
         const res = await _gatewayClient.request<{ runId?: string }>("chat.send", params);
@@ -148,6 +233,10 @@ export function ChatInput() {
       sessionKey,
       attachedImage,
       clearAttachedImage,
+      attachedDocument,
+      clearAttachedDocument,
+      attachedFolder,
+      clearAttachedFolder,
       browserContext,
       clearBrowserContext,
       chatLoading,
@@ -161,13 +250,23 @@ export function ChatInput() {
         handleSubmit(inputRef.current?.value ?? "");
       } else if (e.key === "Escape") {
         clearAttachedImage();
+        clearAttachedDocument();
+        clearAttachedFolder();
         clearBrowserContext();
         if (inputRef.current) inputRef.current.value = "";
         resetHeight();
         hideChatInput();
       }
     },
-    [handleSubmit, hideChatInput, clearAttachedImage, clearBrowserContext, resetHeight],
+    [
+      handleSubmit,
+      hideChatInput,
+      clearAttachedImage,
+      clearAttachedDocument,
+      clearAttachedFolder,
+      clearBrowserContext,
+      resetHeight,
+    ],
   );
 
   const handleRemoveImage = useCallback(() => {
@@ -186,6 +285,55 @@ export function ChatInput() {
           </button>
         </div>
       )}
+      {attachedDocument && (
+        <div className="chat-browser-context chat-document-context">
+          <span className="chat-browser-url">
+            {attachedDocument.fileName}
+            {attachedDocument.truncated ? " (truncated)" : ""}
+          </span>
+          <button className="chat-image-remove" onClick={() => { clearAttachedDocument(); clearAttachedImage(); }} title="Remove document">
+            x
+          </button>
+        </div>
+      )}
+      {attachedFolder && (
+        <div className="chat-browser-context chat-folder-context">
+          <span className="chat-browser-url">{attachedFolder.folderName}/</span>
+          <button className="chat-image-remove" onClick={clearAttachedFolder} title="Remove folder">
+            x
+          </button>
+          {(attachedFolder.files.length > 0 || attachedFolder.git) && (
+            <ul className="chat-folder-files">
+              {attachedFolder.git && (
+                <li>
+                  <span className="chat-browser-url">
+                    git: {attachedFolder.git.branch} ({attachedFolder.git.changedFiles} changed)
+                  </span>
+                  <button
+                    className="chat-folder-file-remove"
+                    onClick={removeAttachedFolderGit}
+                    title="Leave out git status and diffs"
+                  >
+                    x
+                  </button>
+                </li>
+              )}
+              {attachedFolder.files.map((file) => (
+                <li key={file.path}>
+                  <span className="chat-browser-url">{file.path}</span>
+                  <button
+                    className="chat-folder-file-remove"
+                    onClick={() => removeAttachedFolderFile(file.path)}
+                    title="Leave this file out"
+                  >
+                    x
+                  </button>
+                </li>
+              ))}
+            </ul>
+          )}
+        </div>
+      )}
       {attachedImage && (
         <div className="chat-image-preview">
           <img src={attachedImage.dataUrl} alt="attached" className="chat-image-thumb" />
@@ -201,7 +349,11 @@ export function ChatInput() {
         placeholder={
           browserContext
             ? "Ask about this page... (Enter to send)"
-            : attachedImage
+            : attachedFolder
+              ? "Ask about this project... (Enter to send)"
+              : attachedDocument
+              ? "Ask about this document... (Enter to send)"
+              : attachedImage
               ? "Add a question... (Enter to send)"
               : "Ask me anything..."
         }
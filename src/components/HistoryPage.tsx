@@ -1,6 +1,23 @@
 import { useCallback, useEffect, useMemo, useRef, useState } from "react";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import { readLocalChatHistory, type LocalChatEntry } from "../utils/localChatHistory";
+import {
+  deleteLocalChatHistory,
+  enableHistoryEncryption,
+  exportLocalChatHistory,
+  importOpenClawSessions,
+  lockHistory,
+  panicWipeHistory,
+  readAttachmentOriginal,
+  readAttachmentThumbnail,
+  readHistoryEncryptionStatus,
+  readLocalChatHistory,
+  searchLocalChatHistory,
+  unlockHistory,
+  type ExportFormat,
+  type HistoryEncryptionStatus,
+  type LocalChatAttachment,
+  type LocalChatEntry,
+} from "../utils/localChatHistory";
 import { renderMarkdown } from "../utils/renderMarkdown";
 import "./HistoryPage.css";
 
@@ -15,22 +32,77 @@ function formatStamp(timestamp: number) {
   });
 }
 
-export function HistoryPage() {
-  const [entries, setEntries] = useState<LocalChatEntry[]>(() => readLocalChatHistory());
-  const listRef = useRef<HTMLDivElement>(null);
+function AttachmentThumb({ attachment }: { attachment: LocalChatAttachment }) {
+  const [src, setSrc] = useState<string | null>(null);
 
   useEffect(() => {
-    const refresh = () => {
-      setEntries(readLocalChatHistory());
+    if (!attachment.hash) return;
+    let cancelled = false;
+    readAttachmentThumbnail(attachment.hash)
+      .then((dataUrl) => {
+        if (!cancelled) setSrc(dataUrl);
+      })
+      .catch(() => {});
+    return () => {
+      cancelled = true;
     };
+  }, [attachment.hash]);
+
+  const handleOpen = useCallback(() => {
+    if (!attachment.hash) return;
+    readAttachmentOriginal(attachment.hash)
+      .then((dataUrl) => {
+        const win = window.open();
+        win?.document.write(`<img src="${dataUrl}" style="max-width:100%">`);
+      })
+      .catch(() => {});
+  }, [attachment.hash]);
+
+  if (!src) {
+    return <div className="history-attachments">[{attachment.mimeType}]</div>;
+  }
+  return <img className="history-thumb" src={src} alt="attachment" onClick={handleOpen} />;
+}
+
+export function HistoryPage() {
+  const [entries, setEntries] = useState<LocalChatEntry[]>([]);
+  const [total, setTotal] = useState(0);
+  const [query, setQuery] = useState("");
+  const [importStatus, setImportStatus] = useState<string | null>(null);
+  const [encryption, setEncryption] = useState<HistoryEncryptionStatus | null>(null);
+  const [passphrase, setPassphrase] = useState("");
+  const listRef = useRef<HTMLDivElement>(null);
+
+  const refresh = useCallback(async () => {
+    try {
+      const status = await readHistoryEncryptionStatus();
+      setEncryption(status);
+      if (status.locked) {
+        setEntries([]);
+        setTotal(0);
+        return;
+      }
+      if (query.trim()) {
+        const hits = await searchLocalChatHistory(query);
+        setEntries(hits.map((hit) => hit.entry).sort((a, b) => a.timestamp - b.timestamp));
+        setTotal(hits.length);
+      } else {
+        const page = await readLocalChatHistory();
+        setEntries(page.entries);
+        setTotal(page.total);
+      }
+    } catch (err) {
+      setImportStatus(String(err));
+    }
+  }, [query]);
 
-    const timer = window.setInterval(refresh, 5000);
-    window.addEventListener("storage", refresh);
+  useEffect(() => {
+    void refresh();
+    const timer = window.setInterval(() => void refresh(), 5000);
     return () => {
       window.clearInterval(timer);
-      window.removeEventListener("storage", refresh);
     };
-  }, []);
+  }, [refresh]);
 
   useEffect(() => {
     const scrollToBottom = () => {
@@ -46,8 +118,95 @@ export function HistoryPage() {
   }, [entries]);
 
   const titleText = useMemo(() => {
-    return entries.length > 0 ? `ClawPet Conversations (${entries.length})` : "ClawPet Conversations";
-  }, [entries.length]);
+    return total > 0 ? `ClawPet Conversations (${total})` : "ClawPet Conversations";
+  }, [total]);
+
+  const handleImport = useCallback(() => {
+    const importAsync = async () => {
+      setImportStatus("Importing...");
+      try {
+        const imported = await importOpenClawSessions();
+        await refresh();
+        setImportStatus(`Imported ${imported}`);
+      } catch (err) {
+        setImportStatus(String(err));
+      }
+    };
+    void importAsync();
+  }, [refresh]);
+
+  const handleExport = useCallback((format: ExportFormat) => {
+    const exportAsync = async () => {
+      setImportStatus("Exporting...");
+      try {
+        const result = await exportLocalChatHistory(format);
+        setImportStatus(`Saved ${result.entries} to ${result.path}`);
+      } catch (err) {
+        setImportStatus(String(err));
+      }
+    };
+    void exportAsync();
+  }, []);
+
+  const handleDelete = useCallback(
+    (id: string) => {
+      const deleteAsync = async () => {
+        try {
+          await deleteLocalChatHistory([id]);
+          await refresh();
+        } catch (err) {
+          setImportStatus(String(err));
+        }
+      };
+      void deleteAsync();
+    },
+    [refresh],
+  );
+
+  const runPassphraseAction = useCallback(
+    (action: (value: string) => Promise<void>, pending: string, done: string) => {
+      const runAsync = async () => {
+        setImportStatus(pending);
+        try {
+          await action(passphrase);
+          setPassphrase("");
+          await refresh();
+          setImportStatus(done);
+        } catch (err) {
+          setImportStatus(String(err));
+        }
+      };
+      void runAsync();
+    },
+    [passphrase, refresh],
+  );
+
+  const handleLock = useCallback(() => {
+    const lockAsync = async () => {
+      try {
+        await lockHistory();
+        await refresh();
+        setImportStatus("Locked");
+      } catch (err) {
+        setImportStatus(String(err));
+      }
+    };
+    void lockAsync();
+  }, [refresh]);
+
+  const handleWipe = useCallback(() => {
+    if (!window.confirm("Delete ALL history and stored captures? This cannot be undone.")) return;
+    const wipeAsync = async () => {
+      try {
+        await panicWipeHistory();
+        await refresh();
+        setImportStatus("Wiped");
+      } catch (err) {
+        setImportStatus(String(err));
+      }
+    };
+    void wipeAsync();
+  }, [refresh]);
 
   const handleClose = useCallback(() => {
     const closeAsync = async () => {
@@ -68,16 +227,92 @@ export function HistoryPage() {
           X
         </button>
         <div className="history-title">{titleText}</div>
+        <div className="history-toolbar">
+          <button className="history-action" onClick={handleImport}>
+            Import OpenClaw
+          </button>
+          <button className="history-action" onClick={() => handleExport("markdown")}>
+            MD
+          </button>
+          <button className="history-action" onClick={() => handleExport("html")}>
+            HTML
+          </button>
+          <button className="history-action" onClick={() => handleExport("jsonl")}>
+            JSON
+          </button>
+          <input
+            className="history-search"
+            type="search"
+            placeholder="Search"
+            value={query}
+            onChange={(e) => setQuery(e.target.value)}
+          />
+          {importStatus && <span className="history-status">{importStatus}</span>}
+        </div>
+        <div className="history-toolbar">
+          {encryption && (!encryption.encrypted || encryption.locked) && (
+            <>
+              <input
+                className="history-search"
+                type="password"
+                placeholder="Passphrase"
+                value={passphrase}
+                onChange={(e) => setPassphrase(e.target.value)}
+              />
+              {encryption.locked ? (
+                <button
+                  className="history-action"
+                  onClick={() => runPassphraseAction(unlockHistory, "Unlocking...", "Unlocked")}
+                >
+                  Unlock
+                </button>
+              ) : (
+                <button
+                  className="history-action"
+                  onClick={() =>
+                    runPassphraseAction(enableHistoryEncryption, "Encrypting...", "Encrypted")
+                  }
+                >
+                  Encrypt
+                </button>
+              )}
+            </>
+          )}
+          {encryption?.encrypted && !encryption.locked && (
+            <button className="history-action" onClick={handleLock}>
+              Lock
+            </button>
+          )}
+          <button className="history-action history-wipe" onClick={handleWipe}>
+            Wipe
+          </button>
+        </div>
 
         <div className="history-list" ref={listRef}>
           {entries.length === 0 && (
-            <div className="history-empty">No ClawPet conversation history yet.</div>
+            <div className="history-empty">
+              {encryption?.locked
+                ? "History is encrypted. Enter the passphrase to unlock."
+                : query.trim()
+                  ? "No matching messages."
+                  : "No ClawPet conversation history yet."}
+            </div>
           )}
 
           {entries.map((entry) => (
             <div key={entry.id} className={`history-row ${entry.role}`}>
-              <div className="history-bubble">{renderMarkdown(entry.text)}</div>
-              <div className="history-time">{formatStamp(entry.timestamp)}</div>
+              <div className="history-bubble">
+                {renderMarkdown(entry.text)}
+                {entry.attachments?.map((attachment, i) => (
+                  <AttachmentThumb key={attachment.hash ?? i} attachment={attachment} />
+                ))}
+              </div>
+              <div className="history-time">
+                {formatStamp(entry.timestamp)}
+                <button className="history-delete" onClick={() => handleDelete(entry.id)}>
+                  x
+                </button>
+              </div>
             </div>
           ))}
         </div>
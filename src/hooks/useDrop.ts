@@ -1,23 +1,113 @@
-﻿import { useCallback, useEffect, useState } from "react";
+﻿import { useCallback, useEffect, useRef, useState } from "react";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import { invoke } from "@tauri-apps/api/core";
 import { useStore } from "../store/useStore";
 
-const MAX_SIZE = 10 * 1024 * 1024; // 10MB
-const IMAGE_SIZE_LIMIT_MESSAGE = "Image too large! (max 10MB)";
-
-const IMAGE_EXTENSIONS = [".png", ".jpg", ".jpeg", ".gif", ".webp", ".bmp"];
-const IMAGE_URL_RE = /\.(png|jpe?g|gif|webp|bmp)([?#].*)?$/i;
+const MAX_SIZE = 40 * 1024 * 1024; // 40MB, larger images are downscaled by the backend
+const IMAGE_SIZE_LIMIT_MESSAGE = "Image too large! (max 40MB)";
+
+const IMAGE_EXTENSIONS = [
+  ".png",
+  ".jpg",
+  ".jpeg",
+  ".gif",
+  ".webp",
+  ".bmp",
+  ".svg",
+  ".tif",
+  ".tiff",
+  ".avif",
+  ".ico",
+];
+const DOCUMENT_EXTENSIONS = [".pdf", ".docx", ".txt", ".md", ".markdown", ".csv", ".tsv"];
+const IMAGE_URL_RE = /\.(png|jpe?g|gif|webp|bmp|svg|tiff?|avif|ico)([?#].*)?$/i;
 const ENABLE_IMAGE_DROP = import.meta.env.VITE_ENABLE_IMAGE_DROP !== "false";
 const ENABLE_CLIPBOARD_IMAGE = import.meta.env.VITE_ENABLE_CLIPBOARD_IMAGE !== "false";
 
-type ImageReadResult = { base64: string; mime_type: string };
+type SecretFinding = {
+  kind: string;
+  line: number;
+};
+
+type DocumentReadResult = {
+  fileName: string;
+  kind: string;
+  text: string;
+  truncated: boolean;
+  pageCount?: number;
+  totalChars: number;
+  secrets?: SecretFinding[];
+  preview?: ImageReadResult;
+};
+
+type FolderDigestResult = {
+  folderName: string;
+  overview: string;
+  files: { path: string; language: string; bytes: number; text: string; secrets: number }[];
+  fileCount: number;
+  dirCount: number;
+  totalBytes: number;
+  truncated: boolean;
+};
+
+type GitContextResult = {
+  repoName: string;
+  branch: string;
+  text: string;
+  changedFiles: number;
+  truncated: boolean;
+  secrets: number;
+};
+
+type TextReadResult = {
+  fileName: string;
+  language: string;
+  encoding: string;
+  text: string;
+  truncated: boolean;
+  lineCount: number;
+  secrets: SecretFinding[];
+};
+
+type ImageReadResult = {
+  base64: string;
+  mime_type: string;
+  attachment_id?: string;
+  original_width: number;
+  original_height: number;
+  width: number;
+  height: number;
+  scale: number;
+  animation_frames?: number;
+  keyframes?: number;
+};
+
+function describeResize(result: ImageReadResult): string | null {
+  if (result.animation_frames && result.keyframes) {
+    return `Animation sent as ${result.keyframes} keyframes (of ${result.animation_frames} frames)`;
+  }
+  if (result.scale >= 1) return null;
+  const percent = Math.max(1, Math.round(result.scale * 100));
+  return `Image resized to ${percent}% (${result.original_width}×${result.original_height} → ${result.width}×${result.height})`;
+}
+
+function describeSecrets(fileName: string, secrets: SecretFinding[] | undefined): string | null {
+  if (!secrets || secrets.length === 0) return null;
+  const lines = [...new Set(secrets.map((s) => s.line))].slice(0, 5).join(", ");
+  const noun = secrets.length === 1 ? "secret" : "secrets";
+  return `Masked ${secrets.length} possible ${noun} in ${fileName} (line ${lines})`;
+}
 
 function isLikelyImageFileName(name: string) {
   const lower = name.toLowerCase();
   return IMAGE_EXTENSIONS.some((ext) => lower.endsWith(ext));
 }
 
+function isLikelyDocumentFileName(name: string) {
+  const lower = name.toLowerCase();
+  return DOCUMENT_EXTENSIONS.some((ext) => lower.endsWith(ext));
+}
+
 function extractFirstUrlFromUriList(value: string): string | null {
   const line = value
     .split(/\r?\n/)
@@ -73,19 +163,46 @@ function parseDataImageUrl(dataUrl: string): { base64: string; mimeType: string
 
 export function useDrop() {
   const [isDragOver, setIsDragOver] = useState(false);
+  const pendingFetchRef = useRef<string | null>(null);
   const connectionState = useStore((s) => s.connectionState);
   const parchmentVisible = useStore((s) => s.parchmentVisible);
   const setAttachedImage = useStore((s) => s.setAttachedImage);
+  const setAttachedDocument = useStore((s) => s.setAttachedDocument);
+  const setAttachedFolder = useStore((s) => s.setAttachedFolder);
   const showChatInput = useStore((s) => s.showChatInput);
   const showSpeechBubble = useStore((s) => s.showSpeechBubble);
 
-  const storeImageFromBase64 = useCallback(
-    (base64: string, mimeType: string) => {
-      const dataUrl = `data:${mimeType};base64,${base64}`;
-      setAttachedImage({ dataUrl, mimeType });
+  const storeImageResult = useCallback(
+    async (result: ImageReadResult) => {
+      const mimeType = result.mime_type;
+      const dataUrl = `data:${mimeType};base64,${result.base64}`;
+      setAttachedImage({ dataUrl, mimeType, attachmentId: result.attachment_id });
       showChatInput();
+      const note = describeResize(result);
+      if (note) showSpeechBubble(note);
+    },
+    [setAttachedImage, showChatInput, showSpeechBubble],
+  );
+
+  // Images the webview read itself still go through the backend size pipeline
+  const handleImageData = useCallback(
+    async (base64: string) => {
+      try {
+        const result = await invoke<ImageReadResult>("normalize_image_data", { base64 });
+        await storeImageResult(result);
+      } catch (err) {
+        const msg = String(err);
+        if (msg.includes("MB limit")) {
+          showSpeechBubble(IMAGE_SIZE_LIMIT_MESSAGE);
+        } else if (msg.includes("too large")) {
+          showSpeechBubble("Image is too large to attach");
+        } else {
+          showSpeechBubble("Failed to read image");
+          console.error("[useDrop] normalize_image_data error:", err);
+        }
+      }
     },
-    [setAttachedImage, showChatInput],
+    [showSpeechBubble, storeImageResult],
   );
 
   const handleImageFile = useCallback(
@@ -97,14 +214,13 @@ export function useDrop() {
       const reader = new FileReader();
       reader.onload = () => {
         if (typeof reader.result === "string") {
-          const mimeType = file.type || "image/png";
-          setAttachedImage({ dataUrl: reader.result, mimeType });
-          showChatInput();
+          const parsed = parseDataImageUrl(reader.result);
+          if (parsed) void handleImageData(parsed.base64);
         }
       };
       reader.readAsDataURL(file);
     },
-    [setAttachedImage, showChatInput, showSpeechBubble],
+    [handleImageData, showSpeechBubble],
   );
 
   const handleImageUrl = useCallback(
@@ -112,26 +228,45 @@ export function useDrop() {
       if (url.startsWith("data:image/")) {
         const parsed = parseDataImageUrl(url);
         if (!parsed) return;
-        storeImageFromBase64(parsed.base64, parsed.mimeType);
+        await handleImageData(parsed.base64);
         return;
       }
 
+      // A newer drop replaces a download that is still running
+      if (pendingFetchRef.current) {
+        void invoke("cancel_download", { requestId: pendingFetchRef.current });
+      }
+      const requestId = `fetch-${Date.now()}-${Math.random().toString(16).slice(2, 8)}`;
+      pendingFetchRef.current = requestId;
+
       try {
-        const result = await invoke<ImageReadResult>("fetch_image_url", { url });
-        storeImageFromBase64(result.base64, result.mime_type);
+        const result = await invoke<ImageReadResult>("fetch_image_url", { url, requestId });
+        await storeImageResult(result);
       } catch (err) {
         const msg = String(err);
-        if (msg.includes("10MB")) {
+        if (msg.includes("cancelled")) {
+          return;
+        } else if (msg.includes("MB limit")) {
           showSpeechBubble(IMAGE_SIZE_LIMIT_MESSAGE);
-        } else if (msg.includes("supported image") || msg.includes("not an image")) {
+        } else if (msg.includes("too large")) {
+          showSpeechBubble("Image is too large to attach");
+        } else if (
+          msg.includes("supported image") ||
+          msg.includes("not an image") ||
+          msg.includes("image type")
+        ) {
           showSpeechBubble("Only image URLs are supported");
         } else {
           showSpeechBubble("Failed to fetch image");
           console.error("[useDrop] fetch_image_url error:", err);
         }
+      } finally {
+        if (pendingFetchRef.current === requestId) {
+          pendingFetchRef.current = null;
+        }
       }
     },
-    [showSpeechBubble, storeImageFromBase64],
+    [handleImageData, showSpeechBubble, storeImageResult],
   );
 
   const resolveDroppedImageUrl = useCallback((dt: DataTransfer): string | null => {
@@ -155,23 +290,150 @@ export function useDrop() {
     return null;
   }, []);
 
+  const handleDocumentPath = useCallback(
+    async (path: string) => {
+      try {
+        const result = await invoke<DocumentReadResult>("read_document_file", { path });
+        const { preview } = result;
+        setAttachedDocument({
+          fileName: result.fileName,
+          kind: result.kind,
+          text: result.text,
+          truncated: result.truncated,
+          pageCount: result.pageCount,
+        });
+        if (preview) {
+          const dataUrl = `data:${preview.mime_type};base64,${preview.base64}`;
+          setAttachedImage({ dataUrl, mimeType: preview.mime_type, attachmentId: preview.attachment_id });
+        }
+        showChatInput();
+        const secretsNote = describeSecrets(result.fileName, result.secrets);
+        if (secretsNote) {
+          showSpeechBubble(secretsNote);
+        } else if (result.truncated) {
+          showSpeechBubble(`Long document: sending the first part of ${result.fileName}`);
+        }
+      } catch (err) {
+        const msg = String(err);
+        if (msg.includes("MB limit")) {
+          showSpeechBubble("Document too large! (max 50MB)");
+        } else {
+          showSpeechBubble("Failed to read document");
+          console.error("[useDrop] read_document_file error:", err);
+        }
+      }
+    },
+    [setAttachedDocument, setAttachedImage, showChatInput, showSpeechBubble],
+  );
+
+  // Source code, logs and config files: returns false for binaries so they can be tried as images
+  const handleTextPath = useCallback(
+    async (path: string): Promise<boolean> => {
+      try {
+        const result = await invoke<TextReadResult>("read_text_file", { path });
+        setAttachedDocument({
+          fileName: result.fileName,
+          kind: "code",
+          text: result.text,
+          truncated: result.truncated,
+        });
+        showChatInput();
+        const secretsNote = describeSecrets(result.fileName, result.secrets);
+        if (secretsNote) {
+          showSpeechBubble(secretsNote);
+        } else if (result.truncated) {
+          showSpeechBubble(`Long file: sending the first part of ${result.fileName}`);
+        }
+        return true;
+      } catch (err) {
+        const msg = String(err);
+        if (msg.includes("Not a text file")) return false;
+        if (msg.includes("MB limit")) {
+          showSpeechBubble("File too large! (max 5MB)");
+        } else {
+          showSpeechBubble("Failed to read file");
+          console.error("[useDrop] read_text_file error:", err);
+        }
+        return true;
+      }
+    },
+    [setAttachedDocument, showChatInput, showSpeechBubble],
+  );
+
+  // Returns false when the path is not a folder
+  const handleFolderPath = useCallback(
+    async (path: string): Promise<boolean> => {
+      try {
+        const result = await invoke<FolderDigestResult>("read_folder_digest", { path });
+        // Repositories also get branch, status, log and diffs; plain folders just the digest
+        const git = await invoke<GitContextResult>("read_git_context", { path }).catch((err) => {
+          if (!String(err).includes("Not a git repository")) {
+            console.error("[useDrop] read_git_context error:", err);
+          }
+          return null;
+        });
+        setAttachedFolder({
+          folderName: result.folderName,
+          overview: result.overview,
+          files: result.files.map((file) => ({
+            path: file.path,
+            language: file.language,
+            bytes: file.bytes,
+            text: file.text,
+          })),
+          git: git ? { branch: git.branch, changedFiles: git.changedFiles, text: git.text } : undefined,
+        });
+        showChatInput();
+        const secrets = result.files.reduce((sum, file) => sum + file.secrets, 0) + (git?.secrets ?? 0);
+        if (secrets > 0) {
+          showSpeechBubble(`Masked ${secrets} possible ${secrets === 1 ? "secret" : "secrets"} in ${result.folderName}`);
+        } else if (git) {
+          showSpeechBubble(`On ${git.branch} with ${git.changedFiles} changed ${git.changedFiles === 1 ? "file" : "files"}`);
+        } else {
+          showSpeechBubble(`${result.fileCount} files scanned, ${result.files.length} included`);
+        }
+        return true;
+      } catch (err) {
+        const msg = String(err);
+        if (msg.includes("Not a folder")) return false;
+        showSpeechBubble("Failed to read folder");
+        console.error("[useDrop] read_folder_digest error:", err);
+        return true;
+      }
+    },
+    [setAttachedFolder, showChatInput, showSpeechBubble],
+  );
+
   // Handle file paths from Tauri native drag-drop
   const handleFilePaths = useCallback(
     async (paths: string[]) => {
       if (paths.length === 0) return;
 
       for (const path of paths) {
+        if (isLikelyDocumentFileName(path)) {
+          await handleDocumentPath(path);
+          return;
+        }
+        if (!isLikelyImageFileName(path)) {
+          if ((await handleFolderPath(path)) || (await handleTextPath(path))) {
+            return;
+          }
+        }
         try {
           const result = await invoke<ImageReadResult>("read_image_file", { path });
-          storeImageFromBase64(result.base64, result.mime_type);
+          await storeImageResult(result);
           return;
         } catch (err) {
           const msg = String(err);
-          if (msg.includes("10MB")) {
+          if (msg.includes("MB limit")) {
             showSpeechBubble(IMAGE_SIZE_LIMIT_MESSAGE);
             return;
           }
-          if (msg.includes("Not a supported")) {
+          if (msg.includes("too large")) {
+            showSpeechBubble("Image is too large to attach");
+            return;
+          }
+          if (msg.includes("Not a supported") || msg.includes("Failed to decode")) {
             continue;
           }
           showSpeechBubble("Failed to read image");
@@ -180,7 +442,7 @@ export function useDrop() {
         }
       }
     },
-    [storeImageFromBase64, showSpeechBubble],
+    [handleDocumentPath, handleFolderPath, handleTextPath, storeImageResult, showSpeechBubble],
   );
 
   // Tauri native drag-drop events
@@ -144,7 +144,7 @@ export function useGateway() {
         }
 
         if (finalText) {
-          appendLocalChatHistory("assistant", finalText);
+          appendLocalChatHistory("assistant", finalText, { session: store.sessionKey });
           setLastResponse(finalText);
           showSpeechBubble(finalText);
         }
@@ -154,7 +154,7 @@ export function useGateway() {
       } else if (payload.state === "error") {
         const errMsg = payload.errorMessage ?? "An error occurred";
         if (store.chatRunId) {
-          appendLocalChatHistory("assistant", `[Error] ${errMsg}`);
+          appendLocalChatHistory("assistant", `[Error] ${errMsg}`, { session: store.sessionKey });
         }
         showSpeechBubble(errMsg);
         setChatRunId(null);
@@ -163,7 +163,7 @@ export function useGateway() {
       } else if (payload.state === "aborted") {
         const text = store.streamingText;
         if (text) {
-          appendLocalChatHistory("assistant", text);
+          appendLocalChatHistory("assistant", text, { session: store.sessionKey });
           setLastResponse(text);
           showSpeechBubble(text);
         }
@@ -11,6 +11,40 @@ export type BrowserContext = {
   title: string;
 };
 
+export type AttachedDocument = {
+  fileName: string;
+  kind: string;
+  text: string;
+  truncated: boolean;
+  pageCount?: number;
+};
+
+export type FolderFile = {
+  path: string;
+  language: string;
+  bytes: number;
+  text: string;
+};
+
+export type GitContext = {
+  branch: string;
+  changedFiles: number;
+  text: string;
+};
+
+export type AttachedFolder = {
+  folderName: string;
+  overview: string;
+  files: FolderFile[];
+  git?: GitContext;
+};
+
+export type AttachedImage = {
+  dataUrl: string;
+  mimeType: string;
+  attachmentId?: string;
+};
+
 interface ClawPetState {
   // Connection
   connectionState: ConnectionState;
@@ -25,8 +59,20 @@ interface ClawPetState {
   streamingText: string;
   lastResponse: string | null;
 
-  // Image attachment
-  attachedImage: { dataUrl: string; mimeType: string } | null;
+  // Image attachment (attachmentId: hash in the Rust attachment store, when saved there)
+  attachedImage: AttachedImage | null;
+
+  // Document text extracted by the Rust side (PDF, DOCX, TXT, CSV)
+  attachedDocument: AttachedDocument | null;
+  setAttachedDocument: (doc: AttachedDocument) => void;
+  clearAttachedDocument: () => void;
+
+  // Folder digest (tree overview plus inlined files the user can remove before sending)
+  attachedFolder: AttachedFolder | null;
+  setAttachedFolder: (folder: AttachedFolder) => void;
+  removeAttachedFolderFile: (path: string) => void;
+  removeAttachedFolderGit: () => void;
+  clearAttachedFolder: () => void;
 
   // Browser context
   browserContext: BrowserContext | null;
@@ -54,7 +100,7 @@ interface ClawPetState {
   appendStreamingText: (text: string) => void;
   clearStreamingText: () => void;
 
-  setAttachedImage: (img: { dataUrl: string; mimeType: string }) => void;
+  setAttachedImage: (img: AttachedImage) => void;
   clearAttachedImage: () => void;
 
   showSpeechBubble: (text: string) => void;
@@ -109,6 +155,29 @@ export const useStore = create<ClawPetState>((set) => ({
   setAttachedImage: (img) => set({ attachedImage: img }),
   clearAttachedImage: () => set({ attachedImage: null }),
 
+  attachedDocument: null,
+  setAttachedDocument: (doc) => set({ attachedDocument: doc }),
+  clearAttachedDocument: () => set({ attachedDocument: null }),
+
+  attachedFolder: null,
+  setAttachedFolder: (folder) => set({ attachedFolder: folder }),
+  removeAttachedFolderFile: (path) =>
+    set((state) =>
+      state.attachedFolder
+        ? {
+            attachedFolder: {
+              ...state.attachedFolder,
+              files: state.attachedFolder.files.filter((file) => file.path !== path),
+            },
+          }
+        : {},
+    ),
+  removeAttachedFolderGit: () =>
+    set((state) =>
+      state.attachedFolder ? { attachedFolder: { ...state.attachedFolder, git: undefined } } : {},
+    ),
+  clearAttachedFolder: () => set({ attachedFolder: null }),
+
   // Browser context
   browserContext: null,
   setBrowserContext: (ctx) => set({ browserContext: ctx }),
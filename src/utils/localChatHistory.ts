@@ -1,14 +1,42 @@
+import { invoke } from "@tauri-apps/api/core";
+
 export type LocalChatRole = "user" | "assistant";
 
+export type LocalChatAttachment = {
+  mimeType: string;
+  fileName?: string | null;
+  sizeBytes: number;
+  hash?: string;
+};
+
 export type LocalChatEntry = {
   id: string;
   role: LocalChatRole;
   text: string;
   timestamp: number;
+  attachments?: LocalChatAttachment[];
+};
+
+export type LocalChatPage = {
+  entries: LocalChatEntry[];
+  total: number;
+  hasMore: boolean;
 };
 
-const STORAGE_KEY = "clawpet-local-chat-history-v1";
-const MAX_ENTRIES = 600;
+export type LocalChatSearchHit = {
+  entry: LocalChatEntry;
+  snippet: string;
+};
+
+type SessionImportResult = {
+  imported: number;
+  parsed: number;
+  sessionFiles: number;
+};
+
+// Legacy webview storage; migrated once into the Rust history store.
+const LEGACY_STORAGE_KEY = "clawpet-local-chat-history-v1";
+const DEFAULT_PAGE_SIZE = 200;
 
 function createId(): string {
   if (typeof crypto !== "undefined" && typeof crypto.randomUUID === "function") {
@@ -35,44 +63,123 @@ function normalizeEntry(value: unknown): LocalChatEntry | null {
   };
 }
 
-export function readLocalChatHistory(): LocalChatEntry[] {
-  if (typeof localStorage === "undefined") return [];
+function readLegacyChatHistory(): LocalChatEntry[] | null {
+  if (typeof localStorage === "undefined") return null;
 
   try {
-    const raw = localStorage.getItem(STORAGE_KEY);
-    if (!raw) return [];
+    const raw = localStorage.getItem(LEGACY_STORAGE_KEY);
+    if (raw === null) return null;
     const parsed = JSON.parse(raw);
     if (!Array.isArray(parsed)) return [];
 
     return parsed
       .map((item) => normalizeEntry(item))
-      .filter((item): item is LocalChatEntry => item !== null)
-      .sort((a, b) => a.timestamp - b.timestamp);
+      .filter((item): item is LocalChatEntry => item !== null);
   } catch {
     return [];
   }
 }
 
-function writeLocalChatHistory(entries: LocalChatEntry[]) {
-  if (typeof localStorage === "undefined") return;
-  localStorage.setItem(STORAGE_KEY, JSON.stringify(entries));
+export async function migrateLocalChatHistory(): Promise<void> {
+  const legacy = readLegacyChatHistory();
+  if (legacy === null) return;
+
+  await invoke("history_migrate_local", { entries: legacy });
+  localStorage.removeItem(LEGACY_STORAGE_KEY);
+}
+
+export async function readLocalChatHistory(
+  offset = 0,
+  limit = DEFAULT_PAGE_SIZE,
+): Promise<LocalChatPage> {
+  return invoke<LocalChatPage>("history_page", { offset, limit });
+}
+
+export async function searchLocalChatHistory(query: string): Promise<LocalChatSearchHit[]> {
+  return invoke<LocalChatSearchHit[]>("history_search", { query });
+}
+
+export async function deleteLocalChatHistory(ids: string[]): Promise<number> {
+  return invoke<number>("history_delete", { ids });
 }
 
-export function appendLocalChatHistory(role: LocalChatRole, text: string): LocalChatEntry | null {
+export type NewLocalChatAttachment = {
+  mimeType: string;
+  fileName?: string;
+} & ({ hash: string } | { base64: string });
+
+type AttachmentData = { base64: string; mime_type: string };
+
+export type ExportFormat = "markdown" | "html" | "jsonl";
+
+type ExportResult = {
+  path: string;
+  entries: number;
+  attachments: number;
+};
+
+export function appendLocalChatHistory(
+  role: LocalChatRole,
+  text: string,
+  options: { session?: string; attachments?: NewLocalChatAttachment[] } = {},
+) {
   const trimmed = text.trim();
-  if (!trimmed) return null;
+  const attachments = options.attachments ?? [];
+  if (!trimmed && attachments.length === 0) return;
 
-  const entries = readLocalChatHistory();
-  const next: LocalChatEntry = {
-    id: createId(),
+  invoke("history_append", {
     role,
     text: trimmed,
-    timestamp: Date.now(),
-  };
+    session: options.session ?? null,
+    attachments,
+  }).catch((err) => {
+    console.warn("[history] append failed", err);
+  });
+}
+
+export async function importOpenClawSessions(): Promise<number> {
+  const result = await invoke<SessionImportResult>("import_openclaw_sessions");
+  return result.imported;
+}
+
+export async function exportLocalChatHistory(
+  format: ExportFormat,
+  range: { from?: number; to?: number; session?: string } = {},
+): Promise<ExportResult> {
+  return invoke<ExportResult>("export_history", { request: { format, ...range } });
+}
+
+export async function readAttachmentThumbnail(hash: string): Promise<string> {
+  const result = await invoke<AttachmentData>("attachment_thumbnail", { hash });
+  return `data:${result.mime_type};base64,${result.base64}`;
+}
+
+export async function readAttachmentOriginal(hash: string): Promise<string> {
+  const result = await invoke<AttachmentData>("attachment_original", { hash });
+  return `data:${result.mime_type};base64,${result.base64}`;
+}
+
+export type HistoryEncryptionStatus = {
+  encrypted: boolean;
+  locked: boolean;
+};
+
+export async function readHistoryEncryptionStatus(): Promise<HistoryEncryptionStatus> {
+  return invoke<HistoryEncryptionStatus>("history_encryption_status");
+}
 
-  const merged = [...entries, next];
-  const capped = merged.length > MAX_ENTRIES ? merged.slice(-MAX_ENTRIES) : merged;
-  writeLocalChatHistory(capped);
-  return next;
+export async function enableHistoryEncryption(passphrase: string): Promise<void> {
+  await invoke("history_enable_encryption", { passphrase });
 }
 
+export async function unlockHistory(passphrase: string): Promise<void> {
+  await invoke("history_unlock", { passphrase });
+}
+
+export async function lockHistory(): Promise<void> {
+  await invoke("history_lock");
+}
+
+export async function panicWipeHistory(): Promise<void> {
+  await invoke("panic_wipe");
+}